@@ -0,0 +1,490 @@
+use crate::{
+    addrlib::{self, AddressBreakdown, ComputeSurfaceAddrFromCoordInput, MicroTileAddress},
+    for_each_run, AaMode, HwConfig, SwizzleError, TileMode, MICRO_TILE_SIZE,
+};
+use alloc::vec::Vec;
+
+/// The coordinates and tiled address in bytes of a single texel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TexelAddress {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    pub address: usize,
+}
+
+/// An iterator over the tiled addresses for each texel of a mipmap in linear order.
+///
+/// Addresses for micro and macro tiled surfaces are only fully calculated once per micro tile.
+/// The remaining texels in each micro tile only update the element offset.
+/// This produces the same addresses as [deswizzle_mipmap](crate::deswizzle_mipmap).
+#[derive(Debug, Clone)]
+pub struct TiledAddresses {
+    surface: SurfaceParams,
+    x: u32,
+    y: u32,
+    z: u32,
+    // The address of the start of the current row for linear surfaces.
+    row_address: usize,
+    // The addresses for each micro tile in the current row of tiles.
+    tiles: Vec<MicroTileAddress>,
+    // The element offsets for each pixel in a micro tile for the current slice.
+    element_offsets: [u32; 64],
+}
+
+impl TiledAddresses {
+    /// Iterate over the tiled addresses for a mipmap with the same parameters as
+    /// [deswizzle_mipmap](crate::deswizzle_mipmap).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        swizzle: u32,
+        pitch: u32,
+        tile_mode: TileMode,
+        bytes_per_pixel: u32,
+        aa: AaMode,
+    ) -> Result<Self, SwizzleError> {
+        let surface = SurfaceParams {
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+        };
+
+        // Empty surfaces have no texels.
+        let z = if width == 0 || height == 0 || bytes_per_pixel == 0 {
+            depth_or_array_layers
+        } else {
+            // Check for unsupported tile modes before iterating.
+            addrlib::dispatch_compute_surface_addrfrom_coord(
+                &HwConfig::LATTE,
+                &surface.input(0, 0, 0),
+            )?;
+            0
+        };
+
+        Ok(Self {
+            surface,
+            x: 0,
+            y: 0,
+            z,
+            row_address: 0,
+            tiles: Vec::new(),
+            element_offsets: [0; 64],
+        })
+    }
+
+    fn remaining(&self) -> usize {
+        let s = &self.surface;
+        if self.z >= s.depth_or_array_layers {
+            0
+        } else {
+            let slice_size = s.width as usize * s.height as usize;
+            let remaining_slices = (s.depth_or_array_layers - self.z) as usize;
+            remaining_slices * slice_size - (self.y as usize * s.width as usize + self.x as usize)
+        }
+    }
+
+    fn address(&mut self) -> usize {
+        let s = &self.surface;
+        let (x, y, z) = (self.x, self.y, self.z);
+
+        if s.is_linear() {
+            // Each row is contiguous in memory for linear surfaces.
+            if x == 0 {
+                self.row_address = s.address(0, y, z);
+            }
+            return self.row_address + (x * s.bytes_per_pixel) as usize;
+        }
+
+        if x == 0 && y % MICRO_TILE_SIZE == 0 {
+            // Only update the tile addresses when starting a new row of micro tiles.
+            self.tiles = (0..s.width)
+                .step_by(MICRO_TILE_SIZE as usize)
+                .map_while(|tile_x| {
+                    addrlib::compute_micro_tile_address(&HwConfig::LATTE, &s.input(tile_x, y, z))
+                })
+                .collect();
+
+            // The element offsets only change with the slice for thick tile modes.
+            if y == 0 {
+                let bpp = s.bytes_per_pixel * u8::BITS;
+                for (i, offset) in self.element_offsets.iter_mut().enumerate() {
+                    let i = i as u32;
+                    *offset = addrlib::micro_tile_element_offset(
+                        i % MICRO_TILE_SIZE,
+                        i / MICRO_TILE_SIZE,
+                        z,
+                        bpp,
+                        s.tile_mode,
+                    );
+                }
+            }
+        }
+
+        match self.tiles.get((x / MICRO_TILE_SIZE) as usize) {
+            Some(tile) => {
+                let i = (y % MICRO_TILE_SIZE) * MICRO_TILE_SIZE + x % MICRO_TILE_SIZE;
+                tile.address(self.element_offsets[i as usize]) as usize
+            }
+            // Multisampled surfaces can split samples across tiles.
+            None => s.address(x, y, z),
+        }
+    }
+}
+
+impl Iterator for TiledAddresses {
+    type Item = TexelAddress;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.z >= self.surface.depth_or_array_layers {
+            return None;
+        }
+
+        let texel = TexelAddress {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            address: self.address(),
+        };
+
+        self.x += 1;
+        if self.x == self.surface.width {
+            self.x = 0;
+            self.y += 1;
+            if self.y == self.surface.height {
+                self.y = 0;
+                self.z += 1;
+            }
+        }
+
+        Some(texel)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for TiledAddresses {}
+
+/// An iterator over the linear coordinates for each texel of a mipmap in tiled memory order.
+///
+/// This allows reading tiled data or writing linear data sequentially.
+/// Texels are sorted by their address from [TiledAddresses].
+#[derive(Debug, Clone)]
+pub struct LinearCoordinates {
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+    // Runs of texels with consecutive addresses in both layouts sorted by tiled address.
+    runs: Vec<(usize, usize, usize)>,
+    run_index: usize,
+    texel_index: usize,
+}
+
+impl LinearCoordinates {
+    /// Iterate over the linear coordinates for a mipmap with the same parameters as
+    /// [deswizzle_mipmap](crate::deswizzle_mipmap).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        swizzle: u32,
+        pitch: u32,
+        tile_mode: TileMode,
+        bytes_per_pixel: u32,
+        aa: AaMode,
+    ) -> Result<Self, SwizzleError> {
+        let mut runs = Vec::new();
+        if width != 0 && height != 0 && bytes_per_pixel != 0 {
+            for_each_run(
+                width,
+                height,
+                depth_or_array_layers,
+                swizzle,
+                pitch,
+                tile_mode,
+                bytes_per_pixel,
+                aa,
+                &HwConfig::LATTE,
+                &mut |address, linear_address, size| {
+                    runs.push((address, linear_address, size / bytes_per_pixel as usize));
+                    Ok(())
+                },
+            )?;
+        }
+        // Use a stable sort to preserve linear order for any duplicate addresses.
+        runs.sort_by_key(|(address, _, _)| *address);
+
+        Ok(Self {
+            width,
+            height,
+            bytes_per_pixel,
+            runs,
+            run_index: 0,
+            texel_index: 0,
+        })
+    }
+}
+
+impl Iterator for LinearCoordinates {
+    type Item = TexelAddress;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (address, linear_address, len) = *self.runs.get(self.run_index)?;
+
+        let bytes_per_pixel = self.bytes_per_pixel as usize;
+        let offset = self.texel_index * bytes_per_pixel;
+        let index = (linear_address + offset) / bytes_per_pixel;
+        let width = self.width as usize;
+        let slice_size = width * self.height as usize;
+
+        self.texel_index += 1;
+        if self.texel_index == len {
+            self.texel_index = 0;
+            self.run_index += 1;
+        }
+
+        Some(TexelAddress {
+            x: (index % width) as u32,
+            y: (index % slice_size / width) as u32,
+            z: (index / slice_size) as u32,
+            address: address + offset,
+        })
+    }
+}
+
+/// Calculate the tiled address of the texel at `x`, `y`, and `z`
+/// and return the intermediate values for debugging.
+///
+/// The remaining parameters are the same as [deswizzle_mipmap](crate::deswizzle_mipmap).
+#[allow(clippy::too_many_arguments)]
+pub fn explain_address(
+    x: u32,
+    y: u32,
+    z: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+) -> Result<AddressBreakdown, SwizzleError> {
+    let surface = SurfaceParams {
+        width: pitch,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+    };
+    addrlib::explain_surface_addr_from_coord(&HwConfig::LATTE, &surface.input(x, y, z))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SurfaceParams {
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+}
+
+impl SurfaceParams {
+    fn is_linear(&self) -> bool {
+        self.tile_mode.is_linear()
+    }
+
+    fn address(&self, x: u32, y: u32, slice: u32) -> usize {
+        // The tile mode is checked when creating the iterator.
+        addrlib::dispatch_compute_surface_addrfrom_coord(&HwConfig::LATTE, &self.input(x, y, slice))
+            .unwrap_or_default() as usize
+    }
+
+    fn input(&self, x: u32, y: u32, slice: u32) -> ComputeSurfaceAddrFromCoordInput {
+        let (pipe_swizzle, bank_swizzle) =
+            addrlib::pipe_bank_swizzle(&HwConfig::LATTE, self.swizzle);
+        ComputeSurfaceAddrFromCoordInput {
+            x,
+            y,
+            slice,
+            sample: 0,
+            bpp: self.bytes_per_pixel * u8::BITS,
+            pitch: self.pitch,
+            height: self.height,
+            num_slices: self.depth_or_array_layers,
+            num_samples: 1 << self.aa as u32,
+            tile_mode: self.tile_mode,
+            is_depth: false,
+            tile_base: 0,
+            comp_bits: 0,
+            pipe_swizzle,
+            bank_swizzle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texel_addresses(
+        width: u32,
+        height: u32,
+        depth: u32,
+        tile_mode: TileMode,
+        aa: AaMode,
+    ) -> Vec<TexelAddress> {
+        let mut texels = Vec::new();
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let p_in = SurfaceParams {
+                        width,
+                        height,
+                        depth_or_array_layers: depth,
+                        swizzle: 0x700,
+                        pitch: 64,
+                        tile_mode,
+                        bytes_per_pixel: 4,
+                        aa,
+                    }
+                    .input(x, y, z);
+                    let address =
+                        addrlib::dispatch_compute_surface_addrfrom_coord(&HwConfig::LATTE, &p_in)
+                            .unwrap() as usize;
+                    texels.push(TexelAddress { x, y, z, address });
+                }
+            }
+        }
+        texels
+    }
+
+    #[test]
+    fn tiled_addresses_match_texels() {
+        for (tile_mode, aa) in [
+            (TileMode::LinearAligned, AaMode::X1),
+            (TileMode::D1TiledThin1, AaMode::X1),
+            (TileMode::D2TiledThin1, AaMode::X1),
+            (TileMode::D2TiledThick, AaMode::X1),
+            (TileMode::B2TiledThin2, AaMode::X1),
+            (TileMode::D2TiledThin1, AaMode::X4),
+        ] {
+            let addresses = TiledAddresses::new(29, 23, 5, 0x700, 64, tile_mode, 4, aa).unwrap();
+            assert_eq!(29 * 23 * 5, addresses.len());
+            assert_eq!(
+                texel_addresses(29, 23, 5, tile_mode, aa),
+                addresses.collect::<Vec<_>>(),
+                "{tile_mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn linear_coordinates_tiled_order() {
+        for tile_mode in [
+            TileMode::LinearAligned,
+            TileMode::D1TiledThin1,
+            TileMode::D2TiledThin1,
+            TileMode::D2TiledThick,
+        ] {
+            let texels: Vec<_> =
+                LinearCoordinates::new(32, 32, 4, 0x700, 64, tile_mode, 4, AaMode::X1)
+                    .unwrap()
+                    .collect();
+            assert!(texels.windows(2).all(|t| t[0].address < t[1].address));
+
+            let mut expected = texel_addresses(32, 32, 4, tile_mode, AaMode::X1);
+            expected.sort_by_key(|t| t.address);
+            assert_eq!(expected, texels, "{tile_mode:?}");
+        }
+    }
+
+    #[test]
+    fn explain_address_matches_tiled_addresses() {
+        for tile_mode in [
+            TileMode::LinearAligned,
+            TileMode::D1TiledThick,
+            TileMode::D2TiledThin1,
+            TileMode::B2TiledThin2,
+            TileMode::D3TiledThick,
+        ] {
+            let texels =
+                TiledAddresses::new(32, 32, 4, 0x500, 64, tile_mode, 4, AaMode::X1).unwrap();
+            for texel in texels {
+                let breakdown = explain_address(
+                    texel.x,
+                    texel.y,
+                    texel.z,
+                    32,
+                    4,
+                    0x500,
+                    64,
+                    tile_mode,
+                    4,
+                    AaMode::X1,
+                )
+                .unwrap();
+                assert_eq!(texel.address, breakdown.address as usize, "{tile_mode:?}");
+                assert_eq!(
+                    breakdown.address,
+                    breakdown.offset_high
+                        | breakdown.bank << 9
+                        | breakdown.pipe << 8
+                        | breakdown.offset_low
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn explain_address_macro_tiled() {
+        let breakdown = explain_address(
+            9,
+            17,
+            0,
+            64,
+            1,
+            0x500,
+            64,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+        assert_eq!(1, breakdown.macro_tile_y);
+        assert_eq!(0, breakdown.macro_tile_x);
+        // Pipe swizzle 1 and bank swizzle 2.
+        assert_eq!(5, breakdown.swizzle_contribution);
+        assert_eq!(0, breakdown.bank_swap);
+        assert_eq!(breakdown.pixel_index * 4, breakdown.element_offset);
+    }
+
+    #[test]
+    fn addresses_empty() {
+        let texels =
+            TiledAddresses::new(0, 4, 1, 0, 32, TileMode::D1TiledThin1, 4, AaMode::X1).unwrap();
+        assert_eq!(0, texels.len());
+        assert_eq!(
+            None,
+            LinearCoordinates::new(4, 0, 1, 0, 32, TileMode::D1TiledThin1, 4, AaMode::X1)
+                .unwrap()
+                .next()
+        );
+    }
+}
@@ -1,22 +1,76 @@
-use crate::{c_enum, SurfaceFormat};
+use crate::{c_enum, SurfaceFormat, SwizzleError};
 use bitflags::bitflags;
 
-// These are class member variables in addrlib.
-// Values taken from Cemu.
-// https://github.com/cemu-project/Cemu/blob/85141f17f977157b91b72883d879f50b27f17dda/src/Cafe/HW/Latte/LatteAddrLib/LatteAddrLib.h#L6-L15
-// License: https://github.com/cemu-project/Cemu/blob/main/LICENSE.txt
-const M_BANKS: u32 = 4;
-const M_PIPES: u32 = 2;
-const M_PIPE_INTERLEAVE_BYTES: u32 = 256;
-const M_SPLIT_SIZE: u32 = 2048;
-const M_ROW_SIZE: u32 = 2048;
-const M_SWAP_SIZE: u32 = 256;
+// Log intermediate values with the "trace" feature to compare with other implementations.
+#[cfg(feature = "trace")]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        log::trace!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "trace"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+/// Hardware parameters for R6XX and R7XX family GPUs that affect tiling.
+///
+/// These are class member variables in addrlib.
+/// Use [HwConfig::LATTE] for the Wii U.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HwConfig {
+    /// The number of memory banks. This should be 4 or 8.
+    pub num_banks: u32,
+    /// The number of memory pipes. This should be 1, 2, 4, or 8.
+    pub num_pipes: u32,
+    /// The size in bytes of a contiguous group before switching pipes and banks.
+    pub pipe_interleave_bytes: u32,
+    /// The size in bytes for splitting multisampled tiles into separate slices.
+    pub split_size: u32,
+    /// The size in bytes of a DRAM row.
+    pub row_size: u32,
+    /// The size in bytes for bank swapping.
+    pub swap_size: u32,
+    pub chip_family: ChipFamily,
+}
+
+/// The GPU family for family specific alignment rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChipFamily {
+    R6xx,
+    R7xx,
+}
+
+impl HwConfig {
+    /// The configuration for the Wii U's Latte GPU.
+    ///
+    /// Latte is an R7XX family GPU, so R6XX specific alignment rules don't apply.
+    /// This matches the alignment of surfaces created with GX2.
+    // Values taken from Cemu.
+    // https://github.com/cemu-project/Cemu/blob/85141f17f977157b91b72883d879f50b27f17dda/src/Cafe/HW/Latte/LatteAddrLib/LatteAddrLib.h#L6-L15
+    // License: https://github.com/cemu-project/Cemu/blob/main/LICENSE.txt
+    pub const LATTE: Self = Self {
+        num_banks: 4,
+        num_pipes: 2,
+        pipe_interleave_bytes: 256,
+        split_size: 2048,
+        row_size: 2048,
+        swap_size: 256,
+        chip_family: ChipFamily::R7xx,
+    };
+}
+
+impl Default for HwConfig {
+    fn default() -> Self {
+        Self::LATTE
+    }
+}
 
 // Ported from c++:
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/core/addrcommon.h
 const MICRO_TILE_WIDTH: u32 = 8;
 const MICRO_TILE_HEIGHT: u32 = 8;
-const THICK_TILE_THICKNESS: u32 = 4;
 const MICRO_TILE_PIXELS: u32 = MICRO_TILE_WIDTH * MICRO_TILE_HEIGHT;
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/include/addrlib/addrtypes.h#L83
@@ -65,6 +119,84 @@ c_enum! {
     Count = 0x13,
 }
 
+impl TileMode {
+    // PowerSave and Count are not supported.
+    pub(crate) fn supported() -> impl Iterator<Item = TileMode> {
+        (0..=0x11).filter_map(TileMode::from_repr)
+    }
+
+    /// The number of slices in each micro tile.
+    pub fn thickness(self) -> u32 {
+        compute_surface_thickness(self)
+    }
+
+    /// Returns `true` if texels are stored in rows without any tiling.
+    pub fn is_linear(self) -> bool {
+        matches!(self, TileMode::LinearGeneral | TileMode::LinearAligned)
+    }
+
+    /// Returns `true` for 1D tiled modes using only micro tiles.
+    pub fn is_micro_tiled(self) -> bool {
+        matches!(self, TileMode::D1TiledThin1 | TileMode::D1TiledThick)
+    }
+
+    /// Returns `true` for 2D and 3D tiled modes that group micro tiles into macro tiles.
+    pub fn is_macro_tiled(self) -> bool {
+        (TileMode::D2TiledThin1..=TileMode::D3TiledXThick).contains(&self)
+    }
+
+    /// Returns `true` for the 2B and 3B tiled modes that swap banks.
+    pub fn is_bank_swapped(self) -> bool {
+        is_bank_swapped_tile_mode(self)
+    }
+
+    /// The equivalent tile mode without bank swapping.
+    pub fn to_non_bank_swapped(self) -> TileMode {
+        convert_to_non_bank_swapped_mode(self)
+    }
+
+    /// The ratio of the macro tile height to the default macro tile height.
+    /// This is 1 for tile modes without macro tiles.
+    pub fn macro_tile_aspect_ratio(self) -> u32 {
+        compute_macro_tile_aspect_ratio(self)
+    }
+
+    /// The pipe and bank rotation applied to each slice.
+    pub fn rotation(self, hw_config: &HwConfig) -> u32 {
+        compute_surface_rotation_from_tile_mode(hw_config, self)
+    }
+
+    /// The width, height, and depth in texels of a micro tile
+    /// or `None` for linear tile modes.
+    ///
+    /// Micro tiles have the same dimensions for all bytes per pixel values.
+    pub fn micro_tile_dimensions(self) -> Option<(u32, u32, u32)> {
+        (self.is_micro_tiled() || self.is_macro_tiled()).then(|| {
+            (
+                MICRO_TILE_WIDTH,
+                MICRO_TILE_HEIGHT,
+                compute_surface_thickness(self),
+            )
+        })
+    }
+
+    /// The width, height, and depth in texels of a macro tile
+    /// or `None` for linear and micro tiled modes.
+    ///
+    /// Macro tiles have the same dimensions for all bytes per pixel values.
+    /// The pitch alignment may be a multiple of the width to fill a pipe interleave group.
+    pub fn macro_tile_dimensions(self, hw_config: &HwConfig) -> Option<(u32, u32, u32)> {
+        self.is_macro_tiled().then(|| {
+            let aspect_ratio = compute_macro_tile_aspect_ratio(self);
+            (
+                MICRO_TILE_WIDTH * hw_config.num_banks / aspect_ratio,
+                MICRO_TILE_HEIGHT * hw_config.num_pipes * aspect_ratio,
+                compute_surface_thickness(self),
+            )
+        })
+    }
+}
+
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/include/addrlib/addrtypes.h#L230C1-L237C1
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -106,7 +238,7 @@ bitflags! {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/include/addrlib/addrinterface.h#L311
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct TileInfo {
     _banks: u32,
     _bank_width: u32,
@@ -117,6 +249,8 @@ pub struct TileInfo {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/include/addrlib/addrinterface.h#L343
+#[allow(dead_code)]
+#[derive(Debug)]
 pub struct ComputeSurfaceInfoInput {
     pub size: u32,
     pub tile_mode: TileMode,
@@ -136,6 +270,8 @@ pub struct ComputeSurfaceInfoInput {
 
 // Modified to remove unused fields.
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/include/addrlib/addrinterface.h#L374
+#[allow(dead_code)]
+#[derive(Debug)]
 pub struct ComputeSurfaceInfoOutput {
     pub size: u32,
     pub pitch: u32,
@@ -183,8 +319,7 @@ pub struct ComputeSurfaceAddrFromCoordInput {
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/core/addrcommon.h#L50
 fn bits_to_bytes(x: u32) -> u32 {
-    // div_round_up
-    (x + u8::BITS - 1) / u8::BITS
+    x.div_ceil(u8::BITS)
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/core/addrcommon.h#L52
@@ -226,6 +361,9 @@ fn pad_dimensions(
     slice_align: u32,
 ) -> u32 {
     let thickness = compute_surface_thickness(tile_mode);
+    trace!(
+        "pad_dimensions: tile_mode={tile_mode:?} pad_dims={pad_dims} pitch={pitch} pitch_align={pitch_align} height={height} height_align={height_align} slices={slices} slice_align={slice_align}"
+    );
 
     let mut pad_dims = pad_dims;
     if pad_dims == 0 {
@@ -254,6 +392,7 @@ fn pad_dimensions(
         }
     }
 
+    trace!("pad_dimensions: pitch={pitch} height={height} slices={slices}");
     pad_dims
 }
 
@@ -385,7 +524,12 @@ fn convert_to_non_bank_swapped_mode(tile_mode: TileMode) -> TileMode {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L387
-fn compute_surface_tile_slices(tile_mode: TileMode, bpp: u32, num_samples: u32) -> u32 {
+fn compute_surface_tile_slices(
+    hw: &HwConfig,
+    tile_mode: TileMode,
+    bpp: u32,
+    num_samples: u32,
+) -> u32 {
     let mut num_samples = num_samples;
     let bytes_per_sample = bits_to_bytes(bpp * 64);
     let mut tile_slices = 1;
@@ -394,11 +538,9 @@ fn compute_surface_tile_slices(tile_mode: TileMode, bpp: u32, num_samples: u32)
         num_samples = 4;
     }
 
-    if bytes_per_sample != 0 {
-        let sample_per_tile = M_SPLIT_SIZE / bytes_per_sample;
-
-        if sample_per_tile != 0 {
-            tile_slices = (num_samples / sample_per_tile).max(1);
+    if let Some(sample_per_tile) = hw.split_size.checked_div(bytes_per_sample) {
+        if let Some(slices) = num_samples.checked_div(sample_per_tile) {
+            tile_slices = slices.max(1);
         }
     }
 
@@ -406,7 +548,7 @@ fn compute_surface_tile_slices(tile_mode: TileMode, bpp: u32, num_samples: u32)
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L421
-fn compute_surface_rotation_from_tile_mode(tile_mode: TileMode) -> u32 {
+fn compute_surface_rotation_from_tile_mode(hw: &HwConfig, tile_mode: TileMode) -> u32 {
     match tile_mode {
         TileMode::D2TiledThin1
         | TileMode::D2TiledThin2
@@ -415,13 +557,15 @@ fn compute_surface_rotation_from_tile_mode(tile_mode: TileMode) -> u32 {
         | TileMode::B2TiledThin1
         | TileMode::B2TiledThin2
         | TileMode::B2TiledThin4
-        | TileMode::B2TiledThick => M_PIPES * ((M_BANKS >> 1) - 1),
+        | TileMode::B2TiledThick
+        | TileMode::D2TiledXthick => hw.num_pipes * ((hw.num_banks >> 1) - 1),
         TileMode::D3TiledThin1
         | TileMode::D3TiledThick
         | TileMode::B3TiledThin1
-        | TileMode::B3TiledThick => {
-            if M_PIPES >= 4 {
-                (M_PIPES >> 1) - 1
+        | TileMode::B3TiledThick
+        | TileMode::D3TiledXThick => {
+            if hw.num_pipes >= 4 {
+                (hw.num_pipes >> 1) - 1
             } else {
                 1
             }
@@ -431,7 +575,10 @@ fn compute_surface_rotation_from_tile_mode(tile_mode: TileMode) -> u32 {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L466
+// Keep the nested conditions to match the original C++ code.
+#[allow(clippy::collapsible_match)]
 fn hwl_degrade_thick_tile_mode(
+    hw: &HwConfig,
     tile_mode: TileMode,
     num_samples: u32,
     tile_slices: u32,
@@ -456,12 +603,12 @@ fn hwl_degrade_thick_tile_mode(
             }
         }
         TileMode::D2TiledThin2 => {
-            if 2 * M_PIPE_INTERLEAVE_BYTES > M_SPLIT_SIZE {
+            if 2 * hw.pipe_interleave_bytes > hw.split_size {
                 tile_mode = TileMode::D2TiledThin1;
             }
         }
         TileMode::D2TiledThin4 => {
-            if 4 * M_PIPE_INTERLEAVE_BYTES > M_SPLIT_SIZE {
+            if 4 * hw.pipe_interleave_bytes > hw.split_size {
                 tile_mode = TileMode::D2TiledThin2;
             }
         }
@@ -471,12 +618,12 @@ fn hwl_degrade_thick_tile_mode(
             }
         }
         TileMode::B2TiledThin2 => {
-            if 2 * M_PIPE_INTERLEAVE_BYTES > M_SPLIT_SIZE {
+            if 2 * hw.pipe_interleave_bytes > hw.split_size {
                 tile_mode = TileMode::B2TiledThin1;
             }
         }
         TileMode::B2TiledThin4 => {
-            if 4 * M_PIPE_INTERLEAVE_BYTES > M_SPLIT_SIZE {
+            if 4 * hw.pipe_interleave_bytes > hw.split_size {
                 tile_mode = TileMode::B2TiledThin2;
             }
         }
@@ -495,6 +642,17 @@ fn hwl_degrade_thick_tile_mode(
                 tile_mode = TileMode::B3TiledThin1;
             }
         }
+        // XTHICK modes use the same rules as THICK modes.
+        TileMode::D2TiledXthick => {
+            if num_samples > 1 || tile_slices > 1 || is_depth {
+                tile_mode = TileMode::D2TiledThin1;
+            }
+        }
+        TileMode::D3TiledXThick => {
+            if num_samples > 1 || tile_slices > 1 || is_depth {
+                tile_mode = TileMode::D3TiledThin1;
+            }
+        }
         _ => (),
     }
 
@@ -502,8 +660,9 @@ fn hwl_degrade_thick_tile_mode(
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L544
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::collapsible_match)]
 pub fn compute_surface_mip_level_tile_mode(
+    hw: &HwConfig,
     base_tile_mode: TileMode,
     bpp: u32,
     level: u32,
@@ -514,12 +673,12 @@ pub fn compute_surface_mip_level_tile_mode(
     is_depth: bool,
     no_recursive: bool,
 ) -> TileMode {
-    let tile_slices = compute_surface_tile_slices(base_tile_mode, bpp, num_samples);
+    let tile_slices = compute_surface_tile_slices(hw, base_tile_mode, bpp, num_samples);
     let mut tile_mode =
-        hwl_degrade_thick_tile_mode(base_tile_mode, num_samples, tile_slices, is_depth);
-    let rotation = compute_surface_rotation_from_tile_mode(tile_mode);
+        hwl_degrade_thick_tile_mode(hw, base_tile_mode, num_samples, tile_slices, is_depth);
+    let rotation = compute_surface_rotation_from_tile_mode(hw, tile_mode);
 
-    if (rotation % M_PIPES) == 0 {
+    if rotation.is_multiple_of(hw.num_pipes) {
         match tile_mode {
             TileMode::D3TiledThin1 => {
                 tile_mode = TileMode::D2TiledThin1;
@@ -533,11 +692,17 @@ pub fn compute_surface_mip_level_tile_mode(
             TileMode::B3TiledThick => {
                 tile_mode = TileMode::B2TiledThick;
             }
+            TileMode::D3TiledXThick => {
+                tile_mode = TileMode::D2TiledXthick;
+            }
             _ => (),
         }
     }
 
     if no_recursive || level == 0 {
+        trace!(
+            "compute_surface_mip_level_tile_mode: base_tile_mode={base_tile_mode:?} bpp={bpp} level={level} width={width} height={height} num_slices={num_slices} num_samples={num_samples} is_depth={is_depth} -> {tile_mode:?}"
+        );
         return tile_mode;
     }
 
@@ -556,12 +721,12 @@ pub fn compute_surface_mip_level_tile_mode(
     let micro_tile_bytes = bits_to_bytes(num_samples * bpp * thickness * 64);
     let mut width_align_factor = 1;
 
-    if micro_tile_bytes <= M_PIPE_INTERLEAVE_BYTES {
-        width_align_factor = M_PIPE_INTERLEAVE_BYTES / micro_tile_bytes;
+    if micro_tile_bytes <= hw.pipe_interleave_bytes {
+        width_align_factor = hw.pipe_interleave_bytes / micro_tile_bytes;
     }
 
-    let mut macro_tile_width = 8 * M_BANKS;
-    let mut macro_tile_height = 8 * M_PIPES;
+    let mut macro_tile_width = 8 * hw.num_banks;
+    let mut macro_tile_height = 8 * hw.num_pipes;
 
     // Reduce the tile mode from 2D/3D to 1D in following conditions
     match tile_mode {
@@ -586,7 +751,10 @@ pub fn compute_surface_mip_level_tile_mode(
                 tile_mode = TileMode::D1TiledThin1;
             }
         }
-        TileMode::D2TiledThick | TileMode::D3TiledThick => {
+        TileMode::D2TiledThick
+        | TileMode::D3TiledThick
+        | TileMode::D2TiledXthick
+        | TileMode::D3TiledXThick => {
             if width < width_align_factor * macro_tile_width || height < macro_tile_height {
                 tile_mode = TileMode::D1TiledThick;
             }
@@ -594,6 +762,13 @@ pub fn compute_surface_mip_level_tile_mode(
         _ => (),
     }
 
+    // XTHICK modes need at least 8 slices like in Evergreen addrlib's ComputeSurfaceMipLevelTileMode.
+    if tile_mode == TileMode::D2TiledXthick && num_slices < 8 {
+        tile_mode = TileMode::D2TiledThick;
+    } else if tile_mode == TileMode::D3TiledXThick && num_slices < 8 {
+        tile_mode = TileMode::D3TiledThick;
+    }
+
     if tile_mode == TileMode::D1TiledThick && num_slices < 4 {
         tile_mode = TileMode::D1TiledThin1;
     } else if tile_mode == TileMode::D2TiledThick && num_slices < 4 {
@@ -603,6 +778,7 @@ pub fn compute_surface_mip_level_tile_mode(
     }
 
     compute_surface_mip_level_tile_mode(
+        hw,
         tile_mode,
         bpp,
         level,
@@ -638,6 +814,7 @@ fn compute_surface_addr_from_coord_linear(
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L670
 fn compute_surface_alignments_linear(
+    hw: &HwConfig,
     tile_mode: TileMode,
     bpp: u32,
     flags: SurfaceFlags,
@@ -654,8 +831,8 @@ fn compute_surface_alignments_linear(
             *height_align = 1;
         }
         TileMode::LinearAligned => {
-            *base_align = M_PIPE_INTERLEAVE_BYTES;
-            *pitch_align = ((8 * M_PIPE_INTERLEAVE_BYTES) / bpp).max(64);
+            *base_align = hw.pipe_interleave_bytes;
+            *pitch_align = ((8 * hw.pipe_interleave_bytes) / bpp).max(64);
             *height_align = 1;
         }
         _ => {
@@ -666,11 +843,16 @@ fn compute_surface_alignments_linear(
     }
 
     adjust_pitch_alignment(flags, pitch_align);
+    trace!(
+        "compute_surface_alignments_linear: tile_mode={tile_mode:?} bpp={bpp} flags={flags:?} -> base_align={base_align} pitch_align={pitch_align} height_align={height_align}"
+    );
     valid
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L714
+#[allow(clippy::too_many_arguments)]
 fn compute_surface_alignments_micro_tiled(
+    hw: &HwConfig,
     tile_mode: TileMode,
     bpp: u32,
     flags: SurfaceFlags,
@@ -685,13 +867,16 @@ fn compute_surface_alignments_micro_tiled(
     }
 
     let micro_tile_thickness = compute_surface_thickness(tile_mode);
-    let pitch_alignment = M_PIPE_INTERLEAVE_BYTES / bpp / num_samples / micro_tile_thickness;
+    let pitch_alignment = hw.pipe_interleave_bytes / bpp / num_samples / micro_tile_thickness;
 
-    *base_align = M_PIPE_INTERLEAVE_BYTES;
+    *base_align = hw.pipe_interleave_bytes;
     *pitch_align = pitch_alignment.max(8);
     *height_align = 8;
 
     adjust_pitch_alignment(flags, pitch_align);
+    trace!(
+        "compute_surface_alignments_micro_tiled: tile_mode={tile_mode:?} bpp={bpp} flags={flags:?} num_samples={num_samples} -> base_align={base_align} pitch_align={pitch_align} height_align={height_align}"
+    );
     true
 }
 
@@ -706,13 +891,14 @@ fn compute_macro_tile_aspect_ratio(tile_mode: TileMode) -> u32 {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L781
-fn is_dual_base_align_needed(tile_mode: TileMode) -> bool {
-    tile_mode > TileMode::D1TiledThick
+fn is_dual_base_align_needed(hw: &HwConfig, tile_mode: TileMode) -> bool {
+    hw.chip_family == ChipFamily::R6xx && tile_mode > TileMode::D1TiledThick
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L805
 #[allow(clippy::too_many_arguments)]
 fn compute_surface_alignments_macrotiled(
+    hw: &HwConfig,
     tile_mode: TileMode,
     bpp: u32,
     _flags: SurfaceFlags,
@@ -735,10 +921,10 @@ fn compute_surface_alignments_macrotiled(
         bpp = 1;
     }
 
-    let num_banks = M_BANKS;
-    let num_pipes = M_PIPES;
-    let group_bytes = M_PIPE_INTERLEAVE_BYTES;
-    let split_bytes = M_SPLIT_SIZE;
+    let num_banks = hw.num_banks;
+    let num_pipes = hw.num_pipes;
+    let group_bytes = hw.pipe_interleave_bytes;
+    let split_bytes = hw.split_size;
     *base_align = 0;
 
     let macro_tile_width = 8 * num_banks / aspect_ratio;
@@ -752,7 +938,7 @@ fn compute_surface_alignments_macrotiled(
     let mut macro_tile_bytes =
         num_samples * bits_to_bytes(bpp * macro_tile_height * macro_tile_width);
 
-    if num_samples == 1 {
+    if hw.chip_family == ChipFamily::R6xx && num_samples == 1 {
         macro_tile_bytes *= 2;
     }
 
@@ -777,21 +963,25 @@ fn compute_surface_alignments_macrotiled(
 
     *base_align /= num_slices_per_micro_tile;
 
-    if is_dual_base_align_needed(tile_mode) {
+    if is_dual_base_align_needed(hw, tile_mode) {
         let macro_bytes = bits_to_bytes(bpp * macro_tile_height * macro_tile_width);
 
-        if *base_align / macro_bytes % 2 == 0 {
+        if (*base_align / macro_bytes).is_multiple_of(2) {
             *base_align += macro_bytes;
         }
     }
 
     *macro_width = macro_tile_width;
     *macro_height = macro_tile_height;
+    trace!(
+        "compute_surface_alignments_macrotiled: tile_mode={tile_mode:?} bpp={bpp} num_samples={num_samples} -> base_align={base_align} pitch_align={pitch_align} height_align={height_align} macro_width={macro_width} macro_height={macro_height}"
+    );
     true
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L889
 fn compute_surface_info_linear(
+    hw: &HwConfig,
     p_in: &ComputeSurfaceInfoInput,
     p_out: &mut ComputeSurfaceInfoOutput,
     pad_dims: u32,
@@ -807,6 +997,7 @@ fn compute_surface_info_linear(
     let mut pad_dims = pad_dims;
 
     compute_surface_alignments_linear(
+        hw,
         p_in.tile_mode,
         p_in.bpp,
         p_in.flags,
@@ -864,6 +1055,7 @@ fn compute_surface_info_linear(
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L969
 fn compute_surface_info_micro_tiled(
+    hw: &HwConfig,
     p_in: &ComputeSurfaceInfoInput,
     p_out: &mut ComputeSurfaceInfoOutput,
     pad_dims: u32,
@@ -900,6 +1092,7 @@ fn compute_surface_info_micro_tiled(
     }
 
     compute_surface_alignments_micro_tiled(
+        hw,
         tile_mode,
         p_in.bpp,
         p_in.flags,
@@ -940,6 +1133,8 @@ fn is_thick_macro_tiled(tile_mode: TileMode) -> bool {
             | TileMode::B2TiledThick
             | TileMode::D3TiledThick
             | TileMode::B3TiledThick
+            | TileMode::D2TiledXthick
+            | TileMode::D3TiledXThick
     )
 }
 
@@ -957,8 +1152,13 @@ fn is_bank_swapped_tile_mode(tile_mode: TileMode) -> bool {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1098
-fn is_dual_pitch_align_needed(tile_mode: TileMode, is_depth: bool, mip_level: u32) -> bool {
-    if is_depth || mip_level != 0 {
+fn is_dual_pitch_align_needed(
+    hw: &HwConfig,
+    tile_mode: TileMode,
+    is_depth: bool,
+    mip_level: u32,
+) -> bool {
+    if is_depth || mip_level != 0 || hw.chip_family != ChipFamily::R6xx {
         return false;
     }
 
@@ -977,18 +1177,24 @@ fn is_dual_pitch_align_needed(tile_mode: TileMode, is_depth: bool, mip_level: u3
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1134
 fn compute_surface_bank_swapped_width(
+    hw: &HwConfig,
     tile_mode: TileMode,
     bpp: u32,
     num_samples: u32,
     pitch: u32,
 ) -> u32 {
+    // Avoid dividing by zero or halving forever for empty surfaces.
+    if bpp == 0 || pitch == 0 {
+        return 0;
+    }
+
     let mut bank_swap_width = 0;
-    let num_banks = M_BANKS;
-    let num_pipes = M_PIPES;
-    let swap_size = M_SWAP_SIZE;
-    let row_size = M_ROW_SIZE;
-    let split_size = M_SPLIT_SIZE;
-    let group_size = M_PIPE_INTERLEAVE_BYTES;
+    let num_banks = hw.num_banks;
+    let num_pipes = hw.num_pipes;
+    let swap_size = hw.swap_size;
+    let row_size = hw.row_size;
+    let split_size = hw.split_size;
+    let group_size = hw.pipe_interleave_bytes;
     let mut slices_per_tile = 1;
     let bytes_per_sample = 8 * bpp;
     let samples_per_tile = split_size / bytes_per_sample;
@@ -1024,6 +1230,7 @@ fn compute_surface_bank_swapped_width(
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1198
 fn compute_surface_info_macro_tiled(
+    hw: &HwConfig,
     p_in: &ComputeSurfaceInfoInput,
     p_out: &mut ComputeSurfaceInfoOutput,
     pad_dims: u32,
@@ -1069,6 +1276,7 @@ fn compute_surface_info_macro_tiled(
         && !is_thick_macro_tiled(tile_mode)
     {
         compute_surface_alignments_macrotiled(
+            hw,
             base_tile_mode,
             p_in.bpp,
             p_in.flags,
@@ -1080,14 +1288,21 @@ fn compute_surface_info_macro_tiled(
             &mut macro_height,
         );
 
-        let pitch_align_factor = ((M_PIPE_INTERLEAVE_BYTES >> 3) / bpp).max(1);
+        let pitch_align_factor = ((hw.pipe_interleave_bytes >> 3) / bpp).max(1);
 
         if pitch < (p_out.pitch_align * pitch_align_factor) || height < p_out.height_align {
-            return compute_surface_info_micro_tiled(p_in, p_out, pad_dims, TileMode::D1TiledThin1);
+            return compute_surface_info_micro_tiled(
+                hw,
+                p_in,
+                p_out,
+                pad_dims,
+                TileMode::D1TiledThin1,
+            );
         }
     }
 
     compute_surface_alignments_macrotiled(
+        hw,
         tile_mode,
         p_in.bpp,
         p_in.flags,
@@ -1099,15 +1314,17 @@ fn compute_surface_info_macro_tiled(
         &mut macro_height,
     );
 
-    let bank_swapped_width = compute_surface_bank_swapped_width(tile_mode, bpp, num_samples, pitch);
+    let bank_swapped_width =
+        compute_surface_bank_swapped_width(hw, tile_mode, bpp, num_samples, pitch);
     pitch_align = pitch_align.max(bank_swapped_width);
 
     if is_dual_pitch_align_needed(
+        hw,
         tile_mode,
         p_in.flags.contains(SurfaceFlags::depth),
         mip_level,
     ) {
-        let mut tile_per_group = (M_PIPE_INTERLEAVE_BYTES >> 3) / bpp / num_samples;
+        let mut tile_per_group = (hw.pipe_interleave_bytes >> 3) / bpp / num_samples;
         tile_per_group = (tile_per_group / compute_surface_thickness(tile_mode)).max(1);
 
         let even_width = ((pitch - 1) / macro_width) & 1 == 0;
@@ -1148,9 +1365,10 @@ fn compute_surface_info_macro_tiled(
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1315
 pub fn hwl_compute_surface_info(
+    hw: &HwConfig,
     p_in: &ComputeSurfaceInfoInput,
     p_out: &mut ComputeSurfaceInfoOutput,
-) {
+) -> Result<(), SwizzleError> {
     let num_samples = p_in.num_samples.max(1);
     let mut tile_mode = p_in.tile_mode;
     let mut pad_dims = 0;
@@ -1163,6 +1381,7 @@ pub fn hwl_compute_surface_info(
         tile_mode = convert_to_non_bank_swapped_mode(tile_mode);
     } else {
         tile_mode = compute_surface_mip_level_tile_mode(
+            hw,
             tile_mode,
             p_in.bpp,
             p_in.mip_level,
@@ -1177,10 +1396,10 @@ pub fn hwl_compute_surface_info(
 
     match tile_mode {
         TileMode::LinearGeneral | TileMode::LinearAligned => {
-            compute_surface_info_linear(p_in, p_out, pad_dims, tile_mode);
+            compute_surface_info_linear(hw, p_in, p_out, pad_dims, tile_mode);
         }
         TileMode::D1TiledThin1 | TileMode::D1TiledThick => {
-            compute_surface_info_micro_tiled(p_in, p_out, pad_dims, tile_mode);
+            compute_surface_info_micro_tiled(hw, p_in, p_out, pad_dims, tile_mode);
         }
         TileMode::D2TiledThin1
         | TileMode::D2TiledThin2
@@ -1193,14 +1412,16 @@ pub fn hwl_compute_surface_info(
         | TileMode::D3TiledThin1
         | TileMode::D3TiledThick
         | TileMode::B3TiledThin1
-        | TileMode::B3TiledThick => {
-            compute_surface_info_macro_tiled(p_in, p_out, pad_dims, tile_mode, p_in.tile_mode);
-        }
-        _ => {
-            // TODO: return type?
-            // ADDR_INVALIDPARAMS
+        | TileMode::B3TiledThick
+        | TileMode::D2TiledXthick
+        | TileMode::D3TiledXThick => {
+            compute_surface_info_macro_tiled(hw, p_in, p_out, pad_dims, tile_mode, p_in.tile_mode);
         }
+        // ADDR_INVALIDPARAMS
+        _ => return Err(SwizzleError::UnsupportedTileMode { tile_mode }),
     }
+    trace!("hwl_compute_surface_info: {p_in:?} -> {p_out:?}");
+    Ok(())
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1384
@@ -1225,6 +1446,35 @@ fn compute_surface_addr_from_coord_micro_tiled(
     is_depth: bool,
     tile_base: u32,
     comp_bits: u32,
+) -> u32 {
+    let micro_tile_offset =
+        compute_micro_tiled_tile_offset(x, y, slice, bpp, pitch, height, tile_mode);
+
+    let pixel_index =
+        compute_pixel_index_within_micro_tile(x, y, slice, bpp, tile_mode, get_tile_type(is_depth));
+    let mut pixel_offset;
+
+    if comp_bits != 0 && comp_bits != bpp && is_depth {
+        pixel_offset = tile_base + comp_bits * pixel_index;
+    } else {
+        pixel_offset = bpp * pixel_index;
+    }
+
+    pixel_offset /= 8;
+
+    pixel_offset + micro_tile_offset
+}
+
+// The offset of the micro tile containing x, y, and slice.
+// Split from compute_surface_addr_from_coord_micro_tiled to share with MicroTileAddress.
+fn compute_micro_tiled_tile_offset(
+    x: u32,
+    y: u32,
+    slice: u32,
+    bpp: u32,
+    pitch: u32,
+    height: u32,
+    tile_mode: TileMode,
 ) -> u32 {
     let mut micro_tile_thickness = 1;
 
@@ -1244,23 +1494,11 @@ fn compute_surface_addr_from_coord_micro_tiled(
     let slice_bytes = bits_to_bytes(pitch * height * micro_tile_thickness * bpp);
     let slice_offset = micro_tile_index_z * slice_bytes;
 
-    let pixel_index =
-        compute_pixel_index_within_micro_tile(x, y, slice, bpp, tile_mode, get_tile_type(is_depth));
-    let mut pixel_offset;
-
-    if comp_bits != 0 && comp_bits != bpp && is_depth {
-        pixel_offset = tile_base + comp_bits * pixel_index;
-    } else {
-        pixel_offset = bpp * pixel_index;
-    }
-
-    pixel_offset /= 8;
-
-    pixel_offset + micro_tile_offset + slice_offset
+    micro_tile_offset + slice_offset
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1464
-fn compute_pipe_from_coord_wo_rotation(x: u32, y: u32) -> u32 {
+fn compute_pipe_from_coord_wo_rotation(hw: &HwConfig, x: u32, y: u32) -> u32 {
     let mut pipe_bit0 = 0;
     let mut pipe_bit1 = 0;
     let mut pipe_bit2 = 0;
@@ -1272,7 +1510,7 @@ fn compute_pipe_from_coord_wo_rotation(x: u32, y: u32) -> u32 {
     let y4 = bit(y, 4);
     let y5 = bit(y, 5);
 
-    match M_PIPES {
+    match hw.num_pipes {
         1 => {
             pipe_bit0 = 0;
         }
@@ -1295,9 +1533,9 @@ fn compute_pipe_from_coord_wo_rotation(x: u32, y: u32) -> u32 {
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1517
-fn compute_bank_from_coord_wo_rotation(x: u32, y: u32) -> u32 {
-    let num_pipes = M_PIPES;
-    let num_banks = M_BANKS;
+fn compute_bank_from_coord_wo_rotation(hw: &HwConfig, x: u32, y: u32) -> u32 {
+    let num_pipes = hw.num_pipes;
+    let num_banks = hw.num_banks;
     let bank_opt = 1; // mConfigFlags.optimalBankSwap is always 1?
 
     let tx = x / num_banks;
@@ -1317,7 +1555,7 @@ fn compute_bank_from_coord_wo_rotation(x: u32, y: u32) -> u32 {
     let ty4 = bit(ty, 4);
     let ty5 = bit(ty, 5);
 
-    match M_BANKS {
+    match hw.num_banks {
         4 => {
             bank_bit0 = ty4 ^ x3;
 
@@ -1346,6 +1584,7 @@ fn compute_bank_from_coord_wo_rotation(x: u32, y: u32) -> u32 {
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1582
 #[allow(clippy::too_many_arguments)]
 fn compute_surface_addr_from_coord_macro_tiled(
+    hw: &HwConfig,
     x: u32,
     y: u32,
     slice: u32,
@@ -1361,12 +1600,68 @@ fn compute_surface_addr_from_coord_macro_tiled(
     pipe_swizzle: u32,
     bank_swizzle: u32,
 ) -> u32 {
-    let num_pipes = M_PIPES;
-    let num_banks = M_BANKS;
-    let num_group_bits = M_PIPE_INTERLEAVE_BYTES.ilog2();
-    let num_pipe_bits = M_PIPES.ilog2();
-    let num_bank_bits = M_BANKS.ilog2();
+    let element = compute_macro_tiled_element_offset(
+        hw,
+        x,
+        y,
+        slice,
+        sample,
+        bpp,
+        num_samples,
+        tile_mode,
+        is_depth,
+        tile_base,
+        comp_bits,
+    );
+    let tile = compute_macro_tiled_tile_offset(
+        hw,
+        x,
+        y,
+        slice,
+        element.sample_slice,
+        element.num_sample_splits,
+        bpp,
+        pitch,
+        height,
+        element.num_samples,
+        tile_mode,
+        pipe_swizzle,
+        bank_swizzle,
+    );
+    macro_tiled_address(
+        hw,
+        element.element_offset + tile.tile_offset,
+        tile.bank,
+        tile.pipe,
+    )
+}
 
+// Intermediate values from compute_macro_tiled_element_offset.
+struct MacroTiledElementOffset {
+    pixel_index: u32,
+    sample_offset: u32,
+    element_offset: u32,
+    sample_slice: u32,
+    num_sample_splits: u32,
+    num_samples: u32,
+}
+
+// The offset within the micro tile and the sample slice for multisampled surfaces.
+// Split from compute_surface_addr_from_coord_macro_tiled to share with explain_surface_addr_from_coord.
+#[allow(clippy::too_many_arguments)]
+fn compute_macro_tiled_element_offset(
+    hw: &HwConfig,
+    x: u32,
+    y: u32,
+    slice: u32,
+    sample: u32,
+    bpp: u32,
+    num_samples: u32,
+    tile_mode: TileMode,
+    is_depth: bool,
+    tile_base: u32,
+    comp_bits: u32,
+) -> MacroTiledElementOffset {
     let micro_tile_thickness = compute_surface_thickness(tile_mode);
     let micro_tile_bits = MICRO_TILE_PIXELS * micro_tile_thickness * bpp * num_samples;
     let micro_tile_bytes = micro_tile_bits / 8;
@@ -1399,8 +1694,8 @@ fn compute_surface_addr_from_coord_macro_tiled(
     let tile_slice_bits;
 
     let mut num_samples = num_samples;
-    if num_samples > 1 && micro_tile_bytes > M_SPLIT_SIZE {
-        samples_per_slice = M_SPLIT_SIZE / bytes_per_sample;
+    if num_samples > 1 && micro_tile_bytes > hw.split_size {
+        samples_per_slice = hw.split_size / bytes_per_sample;
         num_sample_splits = num_samples / samples_per_slice;
         num_samples = samples_per_slice;
 
@@ -1414,20 +1709,70 @@ fn compute_surface_addr_from_coord_macro_tiled(
 
     elem_offset /= 8;
 
-    let mut pipe = compute_pipe_from_coord_wo_rotation(x, y);
-    let mut bank = compute_bank_from_coord_wo_rotation(x, y);
+    MacroTiledElementOffset {
+        pixel_index,
+        sample_offset,
+        element_offset: elem_offset,
+        sample_slice,
+        num_sample_splits,
+        num_samples,
+    }
+}
+
+// Intermediate values from compute_macro_tiled_tile_offset.
+struct MacroTiledTileOffset {
+    tile_offset: u32,
+    bank: u32,
+    pipe: u32,
+    tile_slice: u32,
+    macro_tile_x: u32,
+    macro_tile_y: u32,
+    rotation: u32,
+    swizzle_contribution: u32,
+    bank_swap: u32,
+}
+
+// The offset, bank, and pipe of the micro tile containing x, y, and slice.
+// Split from compute_surface_addr_from_coord_macro_tiled to share with MicroTileAddress.
+#[allow(clippy::too_many_arguments)]
+fn compute_macro_tiled_tile_offset(
+    hw: &HwConfig,
+    x: u32,
+    y: u32,
+    slice: u32,
+    sample_slice: u32,
+    num_sample_splits: u32,
+    bpp: u32,
+    pitch: u32,
+    height: u32,
+    num_samples: u32,
+    tile_mode: TileMode,
+    pipe_swizzle: u32,
+    bank_swizzle: u32,
+) -> MacroTiledTileOffset {
+    let num_pipes = hw.num_pipes;
+    let num_banks = hw.num_banks;
+    let num_pipe_bits = hw.num_pipes.ilog2();
+    let num_bank_bits = hw.num_banks.ilog2();
+
+    let micro_tile_thickness = compute_surface_thickness(tile_mode);
+
+    let mut pipe = compute_pipe_from_coord_wo_rotation(hw, x, y);
+    let mut bank = compute_bank_from_coord_wo_rotation(hw, x, y);
 
     let mut bank_pipe = pipe + num_pipes * bank;
-    let rotation = compute_surface_rotation_from_tile_mode(tile_mode);
+    let rotation = compute_surface_rotation_from_tile_mode(hw, tile_mode);
     let swizzle = pipe_swizzle + num_pipes * bank_swizzle;
     let mut slice_in = slice;
 
+    // Evergreen addrlib's ComputeBankFromCoord divides by the thickness,
+    // so XTHICK modes rotate every 8 slices instead of every 4 slices.
     if is_thick_macro_tiled(tile_mode) {
-        slice_in /= THICK_TILE_THICKNESS;
+        slice_in /= micro_tile_thickness;
     }
 
-    bank_pipe ^=
-        (num_pipes * sample_slice * ((num_banks >> 1) + 1)) ^ (swizzle + slice_in * rotation);
+    let swizzle_contribution = swizzle + slice_in * rotation;
+    bank_pipe ^= (num_pipes * sample_slice * ((num_banks >> 1) + 1)) ^ swizzle_contribution;
     bank_pipe %= num_pipes * num_banks;
     pipe = bank_pipe % num_pipes;
     bank = bank_pipe / num_pipes;
@@ -1461,40 +1806,67 @@ fn compute_surface_addr_from_coord_macro_tiled(
         macro_tile_bytes * (macro_tile_index_x + macro_tiles_per_row * macro_tile_index_y);
 
     // Do bank swapping if needed
-    if matches!(
-        tile_mode,
-        TileMode::B2TiledThin1
-            | TileMode::B2TiledThin2
-            | TileMode::B2TiledThin4
-            | TileMode::B2TiledThick
-            | TileMode::B3TiledThin1
-            | TileMode::B3TiledThick
-    ) {
+    let mut bank_swap = 0;
+    if is_bank_swapped_tile_mode(tile_mode) {
         let bank_swap_order = [0, 1, 3, 2, 6, 7, 5, 4, 0, 0];
         let bank_swap_width =
-            compute_surface_bank_swapped_width(tile_mode, bpp, num_samples, pitch);
-        let swap_index = macro_tile_pitch * macro_tile_index_x / bank_swap_width;
-        bank ^= bank_swap_order[(swap_index & (M_BANKS - 1)) as usize];
+            compute_surface_bank_swapped_width(hw, tile_mode, bpp, num_samples, pitch);
+        // The width is 0 for empty surfaces.
+        if let Some(swap_index) =
+            (macro_tile_pitch * macro_tile_index_x).checked_div(bank_swap_width)
+        {
+            bank_swap = bank_swap_order[(swap_index & (hw.num_banks - 1)) as usize];
+            bank ^= bank_swap;
+        }
     }
 
-    // Calculate final offset
+    let tile_offset = (macro_tile_offset + slice_offset) >> (num_bank_bits + num_pipe_bits);
+    MacroTiledTileOffset {
+        tile_offset,
+        bank,
+        pipe,
+        tile_slice: slice_in,
+        macro_tile_x: macro_tile_index_x,
+        macro_tile_y: macro_tile_index_y,
+        rotation,
+        swizzle_contribution,
+        bank_swap,
+    }
+}
+
+// Combine the byte offset with the bank and pipe bits.
+fn macro_tiled_address(hw: &HwConfig, total_offset: u32, bank: u32, pipe: u32) -> u32 {
+    let num_group_bits = hw.pipe_interleave_bytes.ilog2();
+    let num_pipe_bits = hw.num_pipes.ilog2();
+
+    let (offset_high, offset_low) = split_macro_tiled_offset(hw, total_offset);
+    let bank_bits = bank << (num_pipe_bits + num_group_bits);
+    let pipe_bits = pipe << num_group_bits;
+    bank_bits | pipe_bits | offset_low | offset_high
+}
+
+// Split the byte offset into the bits above and below the bank and pipe bits.
+fn split_macro_tiled_offset(hw: &HwConfig, total_offset: u32) -> (u32, u32) {
+    let num_group_bits = hw.pipe_interleave_bytes.ilog2();
+    let num_pipe_bits = hw.num_pipes.ilog2();
+    let num_bank_bits = hw.num_banks.ilog2();
+
     let group_mask = (1 << num_group_bits) - 1;
-    let total_offset =
-        elem_offset + ((macro_tile_offset + slice_offset) >> (num_bank_bits + num_pipe_bits));
 
     // TODO: offset_high is causing indexing out of bounds?
     let offset_high = (total_offset & !group_mask) << (num_bank_bits + num_pipe_bits);
     let offset_low = total_offset & group_mask;
-    let bank_bits = bank << (num_pipe_bits + num_group_bits);
-    let pipe_bits = pipe << num_group_bits;
-    bank_bits | pipe_bits | offset_low | offset_high
+    (offset_high, offset_low)
 }
 
 // https://github.com/decaf-emu/addrlib/blob/194162c47469ce620dd2470eb767ff5e42f5954a/src/r600/r600addrlib.cpp#L1736
-pub fn dispatch_compute_surface_addrfrom_coord(p_in: &ComputeSurfaceAddrFromCoordInput) -> u32 {
+pub fn dispatch_compute_surface_addrfrom_coord(
+    hw: &HwConfig,
+    p_in: &ComputeSurfaceAddrFromCoordInput,
+) -> Result<u32, SwizzleError> {
     let num_samples = core::cmp::max(1, p_in.num_samples);
 
-    match p_in.tile_mode {
+    Ok(match p_in.tile_mode {
         TileMode::LinearGeneral | TileMode::LinearAligned => {
             compute_surface_addr_from_coord_linear(
                 p_in.x,
@@ -1532,7 +1904,10 @@ pub fn dispatch_compute_surface_addrfrom_coord(p_in: &ComputeSurfaceAddrFromCoor
         | TileMode::D3TiledThin1
         | TileMode::D3TiledThick
         | TileMode::B3TiledThin1
-        | TileMode::B3TiledThick => compute_surface_addr_from_coord_macro_tiled(
+        | TileMode::B3TiledThick
+        | TileMode::D2TiledXthick
+        | TileMode::D3TiledXThick => compute_surface_addr_from_coord_macro_tiled(
+            hw,
             p_in.x,
             p_in.y,
             p_in.slice,
@@ -1548,13 +1923,276 @@ pub fn dispatch_compute_surface_addrfrom_coord(p_in: &ComputeSurfaceAddrFromCoor
             p_in.pipe_swizzle,
             p_in.bank_swizzle,
         ),
-        _ => 0,
+        tile_mode => return Err(SwizzleError::UnsupportedTileMode { tile_mode }),
+    })
+}
+
+/// The intermediate values used to calculate a tiled address.
+///
+/// The fields match the variables in addrlib's `ComputeSurfaceAddrFromCoordMacroTiled`.
+/// Fields only used for macro tiling are 0 for linear and micro tiled modes.
+/// The final address is always
+/// `offset_high | bank << (pipe_bits + group_bits) | pipe << group_bits | offset_low`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AddressBreakdown {
+    /// The index of the pixel within its micro tile.
+    pub pixel_index: u32,
+    /// The offset in bits of the sample within the micro tile.
+    pub sample_offset: u32,
+    /// The offset in bytes of the pixel and sample within the micro tile.
+    pub element_offset: u32,
+    /// The slice of samples for multisampled micro tiles larger than the split size.
+    pub sample_slice: u32,
+    /// The slice divided by the micro tile thickness used for the slice rotation.
+    pub tile_slice: u32,
+    /// The index of the macro tile along the x-axis.
+    pub macro_tile_x: u32,
+    /// The index of the macro tile along the y-axis.
+    pub macro_tile_y: u32,
+    /// The rotation of the pipe and bank for each tile slice.
+    pub rotation: u32,
+    /// The value from the pipe swizzle, bank swizzle, and rotation
+    /// XORed with `pipe + num_pipes * bank`.
+    pub swizzle_contribution: u32,
+    /// The value XORed with the bank for bank swapped tile modes.
+    pub bank_swap: u32,
+    /// The final pipe.
+    pub pipe: u32,
+    /// The final bank.
+    pub bank: u32,
+    /// The byte offset of the micro tile before inserting the pipe and bank bits.
+    pub tile_offset: u32,
+    /// The bits of the address above the bank bits.
+    pub offset_high: u32,
+    /// The bits of the address below the pipe bits.
+    pub offset_low: u32,
+    /// The final address in bytes.
+    pub address: u32,
+}
+
+/// Calculate the address for the pixel in `p_in` like [dispatch_compute_surface_addrfrom_coord]
+/// and return the intermediate values.
+pub fn explain_surface_addr_from_coord(
+    hw: &HwConfig,
+    p_in: &ComputeSurfaceAddrFromCoordInput,
+) -> Result<AddressBreakdown, SwizzleError> {
+    let num_samples = core::cmp::max(1, p_in.num_samples);
+
+    if p_in.tile_mode.is_macro_tiled() {
+        let element = compute_macro_tiled_element_offset(
+            hw,
+            p_in.x,
+            p_in.y,
+            p_in.slice,
+            p_in.sample,
+            p_in.bpp,
+            num_samples,
+            p_in.tile_mode,
+            p_in.is_depth,
+            p_in.tile_base,
+            p_in.comp_bits,
+        );
+        let tile = compute_macro_tiled_tile_offset(
+            hw,
+            p_in.x,
+            p_in.y,
+            p_in.slice,
+            element.sample_slice,
+            element.num_sample_splits,
+            p_in.bpp,
+            p_in.pitch,
+            p_in.height,
+            element.num_samples,
+            p_in.tile_mode,
+            p_in.pipe_swizzle,
+            p_in.bank_swizzle,
+        );
+        let total_offset = element.element_offset + tile.tile_offset;
+        let (offset_high, offset_low) = split_macro_tiled_offset(hw, total_offset);
+        Ok(AddressBreakdown {
+            pixel_index: element.pixel_index,
+            sample_offset: element.sample_offset,
+            element_offset: element.element_offset,
+            sample_slice: element.sample_slice,
+            tile_slice: tile.tile_slice,
+            macro_tile_x: tile.macro_tile_x,
+            macro_tile_y: tile.macro_tile_y,
+            rotation: tile.rotation,
+            swizzle_contribution: tile.swizzle_contribution,
+            bank_swap: tile.bank_swap,
+            pipe: tile.pipe,
+            bank: tile.bank,
+            tile_offset: tile.tile_offset,
+            offset_high,
+            offset_low,
+            address: macro_tiled_address(hw, total_offset, tile.bank, tile.pipe),
+        })
+    } else if p_in.tile_mode.is_micro_tiled() {
+        let pixel_index = compute_pixel_index_within_micro_tile(
+            p_in.x,
+            p_in.y,
+            p_in.slice,
+            p_in.bpp,
+            p_in.tile_mode,
+            get_tile_type(p_in.is_depth),
+        );
+        let tile_offset = compute_micro_tiled_tile_offset(
+            p_in.x,
+            p_in.y,
+            p_in.slice,
+            p_in.bpp,
+            p_in.pitch,
+            p_in.height,
+            p_in.tile_mode,
+        );
+        let address = dispatch_compute_surface_addrfrom_coord(hw, p_in)?;
+        Ok(AddressBreakdown {
+            pixel_index,
+            element_offset: address - tile_offset,
+            tile_offset,
+            offset_low: address,
+            address,
+            ..Default::default()
+        })
+    } else {
+        let address = dispatch_compute_surface_addrfrom_coord(hw, p_in)?;
+        Ok(AddressBreakdown {
+            offset_low: address,
+            address,
+            ..Default::default()
+        })
+    }
+}
+
+/// The address calculation shared by all pixels in a micro tile.
+///
+/// Tiled addresses for single sample color surfaces only depend on the pixel coordinates
+/// within a micro tile through the element offset from [micro_tile_element_offset].
+#[derive(Debug, Clone, Copy)]
+pub struct MicroTileAddress {
+    offset: u32,
+    macro_tiled: Option<(u32, u32)>,
+    hw: HwConfig,
+}
+
+impl MicroTileAddress {
+    /// The address for the byte at `element_offset` within the micro tile.
+    pub fn address(&self, element_offset: u32) -> u32 {
+        match self.macro_tiled {
+            Some((bank, pipe)) => {
+                macro_tiled_address(&self.hw, self.offset + element_offset, bank, pipe)
+            }
+            None => self.offset + element_offset,
+        }
+    }
+
+    /// The number of bytes starting at `element_offset` with consecutive addresses.
+    pub fn contiguous_bytes(&self, element_offset: u32) -> u32 {
+        match self.macro_tiled {
+            // Macro tiling inserts the bank and pipe bits between pipe interleave groups.
+            Some(_) => {
+                self.hw.pipe_interleave_bytes
+                    - (self.offset + element_offset) % self.hw.pipe_interleave_bytes
+            }
+            None => u32::MAX,
+        }
+    }
+}
+
+/// Calculate the address for the micro tile containing the pixel in `p_in`.
+///
+/// Returns `None` for linear, depth, and multisampled surfaces
+/// or tile modes that should use [dispatch_compute_surface_addrfrom_coord] for each pixel.
+pub fn compute_micro_tile_address(
+    hw: &HwConfig,
+    p_in: &ComputeSurfaceAddrFromCoordInput,
+) -> Option<MicroTileAddress> {
+    if p_in.is_depth || p_in.num_samples > 1 {
+        return None;
+    }
+
+    match p_in.tile_mode {
+        TileMode::D1TiledThin1 | TileMode::D1TiledThick => Some(MicroTileAddress {
+            offset: compute_micro_tiled_tile_offset(
+                p_in.x,
+                p_in.y,
+                p_in.slice,
+                p_in.bpp,
+                p_in.pitch,
+                p_in.height,
+                p_in.tile_mode,
+            ),
+            macro_tiled: None,
+            hw: *hw,
+        }),
+        TileMode::D2TiledThin1
+        | TileMode::D2TiledThin2
+        | TileMode::D2TiledThin4
+        | TileMode::D2TiledThick
+        | TileMode::B2TiledThin1
+        | TileMode::B2TiledThin2
+        | TileMode::B2TiledThin4
+        | TileMode::B2TiledThick
+        | TileMode::D3TiledThin1
+        | TileMode::D3TiledThick
+        | TileMode::B3TiledThin1
+        | TileMode::B3TiledThick
+        | TileMode::D2TiledXthick
+        | TileMode::D3TiledXThick => {
+            let tile = compute_macro_tiled_tile_offset(
+                hw,
+                p_in.x,
+                p_in.y,
+                p_in.slice,
+                0,
+                1,
+                p_in.bpp,
+                p_in.pitch,
+                p_in.height,
+                1,
+                p_in.tile_mode,
+                p_in.pipe_swizzle,
+                p_in.bank_swizzle,
+            );
+            Some(MicroTileAddress {
+                offset: tile.tile_offset,
+                macro_tiled: Some((tile.bank, tile.pipe)),
+                hw: *hw,
+            })
+        }
+        _ => None,
     }
 }
 
+/// The byte offset of a color pixel within its micro tile.
+///
+/// Only the lowest 3 bits of each coordinate are used.
+pub fn micro_tile_element_offset(x: u32, y: u32, z: u32, bpp: u32, tile_mode: TileMode) -> u32 {
+    let pixel_index =
+        compute_pixel_index_within_micro_tile(x, y, z, bpp, tile_mode, TileType::Displayable);
+    bpp * pixel_index / 8
+}
+
+/// Split the raw `swizzle` field of a surface into the pipe swizzle and bank swizzle.
+///
+/// Use [HwConfig::LATTE] for the Wii U.
 // Pipe and bank swizzle values from Cemu.
 // License: https://github.com/cemu-project/Cemu/blob/main/LICENSE.txt
 // https://github.com/cemu-project/Cemu/blob/85141f17f977157b91b72883d879f50b27f17dda/src/Cafe/HW/Latte/Core/LatteTextureLoader.cpp#L30-L31
-pub fn pipe_bank_swizzle(swizzle: u32) -> (u32, u32) {
-    ((swizzle >> 8) & 1, (swizzle >> 9) & 3)
+// The bank bits start after the pipe bits.
+pub fn pipe_bank_swizzle(hw_config: &HwConfig, swizzle: u32) -> (u32, u32) {
+    let num_pipe_bits = hw_config.num_pipes.ilog2();
+    (
+        (swizzle >> 8) & (hw_config.num_pipes - 1),
+        (swizzle >> (8 + num_pipe_bits)) & (hw_config.num_banks - 1),
+    )
+}
+
+/// Combine a pipe swizzle and bank swizzle into a raw `swizzle` value.
+///
+/// This is the inverse of [pipe_bank_swizzle] and only sets the pipe and bank bits.
+pub fn swizzle_from_pipe_bank(hw_config: &HwConfig, pipe_swizzle: u32, bank_swizzle: u32) -> u32 {
+    let num_pipe_bits = hw_config.num_pipes.ilog2();
+    ((pipe_swizzle & (hw_config.num_pipes - 1)) << 8)
+        | ((bank_swizzle & (hw_config.num_banks - 1)) << (8 + num_pipe_bits))
 }
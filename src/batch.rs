@@ -0,0 +1,147 @@
+use crate::{swizzled_mipmap_size, Gx2Surface, HwConfig, MipmapParams, SwizzleError, SwizzlePlan};
+use alloc::{
+    collections::{btree_map::Entry, BTreeMap},
+    vec::Vec,
+};
+
+/// Convert all layers and mipmaps for each surface from tiled to a combined linear vector.
+///
+/// This produces the same output as calling [Gx2Surface::deswizzle] for each surface.
+/// Errors are returned for each surface instead of failing the entire batch.
+/// Mipmaps with identical layouts reuse the same [SwizzlePlan].
+pub fn deswizzle_surfaces(surfaces: &[Gx2Surface]) -> Vec<Result<Vec<u8>, SwizzleError>> {
+    deswizzle_surfaces_with_callbacks(surfaces, |_, _| (), || false)
+}
+
+/// The same as [deswizzle_surfaces] but with callbacks for reporting progress and cancelling.
+///
+/// `progress` is called with the number of finished surfaces and the total number of surfaces
+/// after each surface. `is_cancelled` is checked before each surface,
+/// and all remaining surfaces return [SwizzleError::Cancelled] once it returns `true`.
+pub fn deswizzle_surfaces_with_callbacks<P, C>(
+    surfaces: &[Gx2Surface],
+    mut progress: P,
+    mut is_cancelled: C,
+) -> Vec<Result<Vec<u8>, SwizzleError>>
+where
+    P: FnMut(usize, usize),
+    C: FnMut() -> bool,
+{
+    let mut plans = BTreeMap::new();
+    let mut cancelled = false;
+
+    let mut results = Vec::with_capacity(surfaces.len());
+    for (i, surface) in surfaces.iter().enumerate() {
+        cancelled = cancelled || is_cancelled();
+        if cancelled {
+            results.push(Err(SwizzleError::Cancelled));
+        } else {
+            results.push(deswizzle_surface(surface, &mut plans));
+        }
+        progress(i + 1, surfaces.len());
+    }
+    results
+}
+
+fn deswizzle_surface(
+    surface: &Gx2Surface,
+    plans: &mut BTreeMap<MipmapParams, SwizzlePlan>,
+) -> Result<Vec<u8>, SwizzleError> {
+    surface.validate()?;
+    if surface.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mipmaps = (0..surface.mipmap_count)
+        .map(|mip| {
+            let (source, params) = surface.mipmap_params(mip, surface.swizzle)?;
+            let plan = match plans.entry(params) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(SwizzlePlan::new(
+                    params.width,
+                    params.height,
+                    params.depth_or_array_layers,
+                    params.swizzle,
+                    params.pitch,
+                    params.tile_mode,
+                    params.bytes_per_pixel,
+                    params.aa,
+                )?),
+            };
+
+            // Use the same size check as deswizzle_mipmap.
+            let expected_size = swizzled_mipmap_size(
+                params.width,
+                params.height,
+                params.depth_or_array_layers,
+                params.swizzle,
+                params.pitch,
+                params.tile_mode,
+                params.bytes_per_pixel,
+                params.aa,
+                &HwConfig::LATTE,
+            )?;
+            if plan.linear_size() > 0 && source.len() < expected_size {
+                return Err(SwizzleError::NotEnoughData {
+                    expected_size,
+                    actual_size: source.len(),
+                });
+            }
+            plan.deswizzle(source)
+        })
+        .collect();
+
+    surface.combine_mipmaps(mipmaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bc1_mipmaps_surface;
+    use alloc::vec;
+
+    #[test]
+    fn deswizzle_surfaces_individual_results() {
+        let expected = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_deswizzled.bin");
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+
+        let results = deswizzle_surfaces(&[
+            bc1_mipmaps_surface(swizzled),
+            bc1_mipmaps_surface(&swizzled[..1024]),
+            bc1_mipmaps_surface(swizzled),
+        ]);
+        assert_eq!(3, results.len());
+        assert_eq!(expected, &results[0].as_ref().unwrap()[..]);
+        assert_eq!(
+            bc1_mipmaps_surface(&swizzled[..1024]).deswizzle(),
+            results[1]
+        );
+        assert_eq!(expected, &results[2].as_ref().unwrap()[..]);
+    }
+
+    #[test]
+    fn deswizzle_surfaces_cancelled() {
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+
+        let mut progress = Vec::new();
+        let mut count = 0;
+        let results = deswizzle_surfaces_with_callbacks(
+            &[
+                bc1_mipmaps_surface(swizzled),
+                bc1_mipmaps_surface(swizzled),
+                bc1_mipmaps_surface(swizzled),
+            ],
+            |finished, total| progress.push((finished, total)),
+            || {
+                count += 1;
+                count > 1
+            },
+        );
+        assert!(results[0].is_ok());
+        assert_eq!(
+            vec![Err(SwizzleError::Cancelled), Err(SwizzleError::Cancelled)],
+            results[1..]
+        );
+        assert_eq!(vec![(1, 3), (2, 3), (3, 3)], progress);
+    }
+}
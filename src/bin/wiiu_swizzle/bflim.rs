@@ -0,0 +1,199 @@
+use std::error::Error;
+
+use wiiu_swizzle::{surface_layout, AaMode, SurfaceDim, SurfaceFormat, SwizzledSurface, TileMode};
+
+use crate::{read_u16, read_u32, write_u16, write_u32};
+
+// The FLIM header, imag block, and image size.
+const FOOTER_SIZE: usize = 0x28;
+
+/// A big endian layout image (.bflim) with the image data followed by a footer.
+pub struct Bflim {
+    image_data: Vec<u8>,
+    footer: Vec<u8>,
+}
+
+impl Bflim {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let footer_offset = bytes
+            .len()
+            .checked_sub(FOOTER_SIZE)
+            .ok_or("file is too small for a BFLIM footer")?;
+        let footer = bytes[footer_offset..].to_vec();
+        if &footer[..4] != b"FLIM" {
+            return Err("invalid FLIM magic".into());
+        }
+        if read_u16(&footer, 4)? != 0xFEFF {
+            return Err("only big endian BFLIM files are supported".into());
+        }
+        if &footer[0x14..0x18] != b"imag" {
+            return Err("invalid imag magic".into());
+        }
+
+        let image_size = read_u32(&footer, 0x24)? as usize;
+        let image_data = bytes[..footer_offset]
+            .get(..image_size)
+            .ok_or("image data out of range")?
+            .to_vec();
+
+        Ok(Self { image_data, footer })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.image_data.clone();
+        bytes.extend_from_slice(&self.footer);
+        bytes
+    }
+
+    pub fn surface(&self) -> Result<SwizzledSurface, Box<dyn Error>> {
+        let width = read_u16(&self.footer, 0x1C)? as u32;
+        let height = read_u16(&self.footer, 0x1E)? as u32;
+        let alignment = read_u16(&self.footer, 0x20)? as u32;
+        let format = surface_format(self.footer[0x22])?;
+        let (tile_mode, swizzle) = tile_mode_swizzle(self.footer[0x23])?;
+
+        // The pitch isn't stored in the file.
+        let layout = surface_layout(
+            SurfaceDim::D2,
+            width,
+            height,
+            1,
+            1,
+            format,
+            AaMode::X1,
+            tile_mode,
+            swizzle,
+        )?;
+
+        Ok(SwizzledSurface {
+            dim: SurfaceDim::D2,
+            width,
+            height,
+            depth_or_array_layers: 1,
+            mipmap_count: 1,
+            format,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: self.image_data.clone(),
+            mipmap_data: Vec::new(),
+            tile_mode,
+            swizzle,
+            alignment,
+            pitch: layout.pitch,
+            mipmap_offsets: [0; 13],
+        })
+    }
+
+    /// Replace the image data and update the dimensions.
+    pub fn replace_surface(&mut self, surface: &SwizzledSurface) -> Result<(), Box<dyn Error>> {
+        if surface.mipmap_count > 1 {
+            return Err("BFLIM files do not support mipmaps".into());
+        }
+
+        self.image_data = surface.image_data.clone();
+        write_u16(&mut self.footer, 0x1C, surface.width.try_into()?);
+        write_u16(&mut self.footer, 0x1E, surface.height.try_into()?);
+        write_u16(&mut self.footer, 0x20, surface.alignment.try_into()?);
+        write_u32(&mut self.footer, 0x24, self.image_data.len() as u32);
+
+        let file_size = self.image_data.len() + FOOTER_SIZE;
+        write_u32(&mut self.footer, 0x0C, file_size as u32);
+        Ok(())
+    }
+}
+
+fn surface_format(format: u8) -> Result<SurfaceFormat, Box<dyn Error>> {
+    match format {
+        0x00 | 0x01 => Ok(SurfaceFormat::R8Unorm),
+        0x02 => Ok(SurfaceFormat::R4G4Unorm),
+        0x03 | 0x04 => Ok(SurfaceFormat::R8G8Unorm),
+        0x05 | 0x19 => Ok(SurfaceFormat::R5G6B5Unorm),
+        0x06 | 0x09 => Ok(SurfaceFormat::R8G8B8A8Unorm),
+        0x07 => Ok(SurfaceFormat::R5G5B5A1Unorm),
+        0x08 => Ok(SurfaceFormat::R4G4B4A4Unorm),
+        0x0C => Ok(SurfaceFormat::Bc1Unorm),
+        0x0D => Ok(SurfaceFormat::Bc2Unorm),
+        0x0E => Ok(SurfaceFormat::Bc3Unorm),
+        0x0F | 0x10 => Ok(SurfaceFormat::Bc4Unorm),
+        0x11 => Ok(SurfaceFormat::Bc5Unorm),
+        0x14 => Ok(SurfaceFormat::R8G8B8A8Srgb),
+        0x15 => Ok(SurfaceFormat::Bc1Srgb),
+        0x16 => Ok(SurfaceFormat::Bc2Srgb),
+        0x17 => Ok(SurfaceFormat::Bc3Srgb),
+        0x18 => Ok(SurfaceFormat::R10G10B10A2Unorm),
+        // TODO: Support ETC1 and 4-bit formats.
+        _ => Err(format!("unsupported BFLIM format 0x{format:X}").into()),
+    }
+}
+
+fn tile_mode_swizzle(value: u8) -> Result<(TileMode, u32), Box<dyn Error>> {
+    let tile_mode = TileMode::from_repr(value as u32 & 0x1F).ok_or("unknown BFLIM tile mode")?;
+    let swizzle = ((value as u32 >> 5) & 0x7) << 8;
+    Ok((tile_mode, swizzle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bflim_bytes() -> Vec<u8> {
+        let mut footer = vec![0u8; FOOTER_SIZE];
+        footer[..4].copy_from_slice(b"FLIM");
+        write_u16(&mut footer, 4, 0xFEFF);
+        write_u32(&mut footer, 0x0C, (64 + FOOTER_SIZE) as u32);
+        footer[0x14..0x18].copy_from_slice(b"imag");
+        write_u16(&mut footer, 0x1C, 4);
+        write_u16(&mut footer, 0x1E, 4);
+        write_u16(&mut footer, 0x20, 512);
+        footer[0x22] = 0x06;
+        footer[0x23] = TileMode::D1TiledThin1 as u8;
+        write_u32(&mut footer, 0x24, 64);
+
+        let mut bytes = vec![2u8; 64];
+        bytes.extend_from_slice(&footer);
+        bytes
+    }
+
+    #[test]
+    fn bflim_round_trip() {
+        let bytes = bflim_bytes();
+        let bflim = Bflim::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, bflim.to_bytes());
+
+        let surface = bflim.surface().unwrap();
+        assert_eq!((4, 4), (surface.width, surface.height));
+        assert_eq!(SurfaceFormat::R8G8B8A8Unorm, surface.format);
+        assert_eq!(TileMode::D1TiledThin1, surface.tile_mode);
+        assert_eq!(vec![2; 64], surface.image_data);
+    }
+
+    #[test]
+    fn bflim_truncated() {
+        let bytes = bflim_bytes();
+        assert!(Bflim::from_bytes(&bytes[..FOOTER_SIZE - 1]).is_err());
+        // The image size is larger than the remaining data.
+        assert!(Bflim::from_bytes(&bytes[32..]).is_err());
+    }
+
+    #[test]
+    fn bflim_zero_size_image() {
+        let mut bytes = bflim_bytes()[64..].to_vec();
+        write_u32(&mut bytes, 0x24, 0);
+        let bflim = Bflim::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, bflim.to_bytes());
+    }
+
+    #[test]
+    fn bflim_replace_surface() {
+        let mut bflim = Bflim::from_bytes(&bflim_bytes()).unwrap();
+        let mut surface = bflim.surface().unwrap();
+        surface.image_data = vec![3; 128];
+        surface.height = 8;
+        bflim.replace_surface(&surface).unwrap();
+
+        let bflim = Bflim::from_bytes(&bflim.to_bytes()).unwrap();
+        let surface = bflim.surface().unwrap();
+        assert_eq!(8, surface.height);
+        assert_eq!(vec![3; 128], surface.image_data);
+    }
+}
@@ -0,0 +1,270 @@
+use std::error::Error;
+
+use ddsfile::{AlphaMode, Caps2, D3D10ResourceDimension, Dds, DxgiFormat, NewDxgiParams};
+use image::DynamicImage;
+use wiiu_swizzle::{Gx2Surface, SurfaceDim, SurfaceFormat};
+
+/// Linear surface data using the ordering of [Gx2Surface::deswizzle].
+pub struct LinearSurface {
+    pub dim: SurfaceDim,
+    pub width: u32,
+    pub height: u32,
+    pub depth_or_array_layers: u32,
+    pub mipmap_count: u32,
+    pub data: LinearData,
+}
+
+pub enum LinearData {
+    /// Data already in the surface format.
+    Encoded(Vec<u8>),
+    Rgba8(Vec<u8>),
+    Rgba32Float(Vec<f32>),
+}
+
+/// Create a DDS with the same format as `surface` if possible
+/// or with decoded RGBA data otherwise.
+pub fn create_dds(surface: &Gx2Surface) -> Result<Dds, Box<dyn Error>> {
+    let (format, data) = match dxgi_format(surface.format) {
+        Some(format) => {
+            let data = surface.deswizzle()?;
+            let data = match surface.dim {
+                // DDS stores array layers as [layer][mip].
+                SurfaceDim::D1 | SurfaceDim::D2 => reorder_layers::<true>(
+                    surface.width,
+                    surface.height,
+                    surface.depth_or_array_layers,
+                    surface.mipmap_count,
+                    surface.format,
+                    &data,
+                ),
+                SurfaceDim::D3 | SurfaceDim::Cube => data,
+            };
+            (format, data)
+        }
+        None => {
+            // Images are already ordered by [layer][mip].
+            let images = surface.to_images()?;
+            let mut data = Vec::new();
+            for image in images.iter().flatten() {
+                match image {
+                    DynamicImage::ImageRgba32F(image) => {
+                        data.extend(image.as_raw().iter().flat_map(|f| f.to_le_bytes()))
+                    }
+                    _ => data.extend_from_slice(image.as_bytes()),
+                }
+            }
+            let format = if surface.format.is_hdr() {
+                DxgiFormat::R32G32B32A32_Float
+            } else if surface.format.is_srgb() {
+                DxgiFormat::R8G8B8A8_UNorm_sRGB
+            } else {
+                DxgiFormat::R8G8B8A8_UNorm
+            };
+            (format, data)
+        }
+    };
+
+    let (depth, layers) = match surface.dim {
+        SurfaceDim::D3 => (Some(surface.depth_or_array_layers), None),
+        _ => (None, Some(surface.depth_or_array_layers)),
+    };
+    let mut dds = Dds::new_dxgi(NewDxgiParams {
+        height: surface.height,
+        width: surface.width,
+        depth,
+        format,
+        mipmap_levels: Some(surface.mipmap_count),
+        array_layers: layers,
+        caps2: (surface.dim == SurfaceDim::Cube)
+            .then_some(Caps2::CUBEMAP | Caps2::CUBEMAP_ALLFACES),
+        is_cubemap: surface.dim == SurfaceDim::Cube,
+        resource_dimension: match surface.dim {
+            SurfaceDim::D1 => D3D10ResourceDimension::Texture1D,
+            SurfaceDim::D2 | SurfaceDim::Cube => D3D10ResourceDimension::Texture2D,
+            SurfaceDim::D3 => D3D10ResourceDimension::Texture3D,
+        },
+        alpha_mode: AlphaMode::Unknown,
+    })?;
+    dds.data = data;
+    Ok(dds)
+}
+
+/// Read the data from `dds` using the ordering of [Gx2Surface::deswizzle].
+///
+/// DDS files with the same format as `format` are not decoded.
+pub fn read_dds(dds: &Dds, format: SurfaceFormat) -> Result<LinearSurface, Box<dyn Error>> {
+    let dds_format = dds.get_dxgi_format().ok_or("unsupported DDS format")?;
+
+    let is_cube = dds
+        .header10
+        .as_ref()
+        .map(|h| h.misc_flag.contains(ddsfile::MiscFlag::TEXTURECUBE))
+        .unwrap_or_default()
+        || dds.header.caps2.contains(Caps2::CUBEMAP);
+    let dim = if is_cube {
+        SurfaceDim::Cube
+    } else if dds.get_depth() > 1 {
+        SurfaceDim::D3
+    } else {
+        SurfaceDim::D2
+    };
+
+    let width = dds.get_width();
+    let height = dds.get_height();
+    let depth_or_array_layers = match dim {
+        SurfaceDim::D3 => dds.get_depth(),
+        // The DX10 header stores the number of cube maps instead of faces.
+        SurfaceDim::Cube if dds.header10.is_some() => dds.get_num_array_layers() * 6,
+        _ => dds.get_num_array_layers(),
+    };
+    let mipmap_count = dds.get_num_mipmap_levels();
+
+    let reorder = |format| match dim {
+        SurfaceDim::D1 | SurfaceDim::D2 => reorder_layers::<false>(
+            width,
+            height,
+            depth_or_array_layers,
+            mipmap_count,
+            format,
+            &dds.data,
+        ),
+        SurfaceDim::D3 | SurfaceDim::Cube => dds.data.clone(),
+    };
+
+    let data = if dxgi_format(format) == Some(dds_format) {
+        LinearData::Encoded(reorder(format))
+    } else {
+        match dds_format {
+            DxgiFormat::R8G8B8A8_UNorm | DxgiFormat::R8G8B8A8_UNorm_sRGB => {
+                LinearData::Rgba8(reorder(SurfaceFormat::R8G8B8A8Unorm))
+            }
+            DxgiFormat::R32G32B32A32_Float => LinearData::Rgba32Float(
+                reorder(SurfaceFormat::R32G32B32A32Float)
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+            _ => {
+                return Err(format!(
+                    "DDS format {dds_format:?} must match the surface format {format:?} or be RGBA8 or RGBA32F"
+                )
+                .into())
+            }
+        }
+    };
+
+    Ok(LinearSurface {
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        data,
+    })
+}
+
+/// Convert between the DDS `[layer][mip]` ordering and the `[mip][layer]` ordering
+/// used by [Gx2Surface::deswizzle] for 1D and 2D surfaces.
+fn reorder_layers<const MIP_MAJOR_TO_LAYER_MAJOR: bool>(
+    width: u32,
+    height: u32,
+    layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+) -> Vec<u8> {
+    let (block_width, block_height) = format.block_dim();
+    let mip_size = |mip: u32| {
+        (width >> mip).max(1).div_ceil(block_width) as usize
+            * (height >> mip).max(1).div_ceil(block_height) as usize
+            * format.bytes_per_pixel() as usize
+    };
+    let layer_size: usize = (0..mipmap_count).map(mip_size).sum();
+    let mip_offset = |mip: u32| (0..mip).map(mip_size).sum::<usize>();
+
+    let mut output = Vec::with_capacity(data.len());
+    let mut push = |offset: usize, size: usize| {
+        if let Some(bytes) = data.get(offset..offset + size) {
+            output.extend_from_slice(bytes);
+        }
+    };
+
+    if MIP_MAJOR_TO_LAYER_MAJOR {
+        for layer in 0..layers as usize {
+            for mip in 0..mipmap_count {
+                let offset = mip_offset(mip) * layers as usize + layer * mip_size(mip);
+                push(offset, mip_size(mip));
+            }
+        }
+    } else {
+        for mip in 0..mipmap_count {
+            for layer in 0..layers as usize {
+                let offset = layer * layer_size + mip_offset(mip);
+                push(offset, mip_size(mip));
+            }
+        }
+    }
+    output
+}
+
+fn dxgi_format(format: SurfaceFormat) -> Option<DxgiFormat> {
+    // Only include formats with the same channel and bit layout.
+    match format {
+        SurfaceFormat::R8Unorm => Some(DxgiFormat::R8_UNorm),
+        SurfaceFormat::R8Uint => Some(DxgiFormat::R8_UInt),
+        SurfaceFormat::R8Snorm => Some(DxgiFormat::R8_SNorm),
+        SurfaceFormat::R8Sint => Some(DxgiFormat::R8_SInt),
+        SurfaceFormat::R16Unorm => Some(DxgiFormat::R16_UNorm),
+        SurfaceFormat::R16Uint => Some(DxgiFormat::R16_UInt),
+        SurfaceFormat::R16Snorm => Some(DxgiFormat::R16_SNorm),
+        SurfaceFormat::R16Sint => Some(DxgiFormat::R16_SInt),
+        SurfaceFormat::R16Float => Some(DxgiFormat::R16_Float),
+        SurfaceFormat::R8G8Unorm => Some(DxgiFormat::R8G8_UNorm),
+        SurfaceFormat::R8G8Uint => Some(DxgiFormat::R8G8_UInt),
+        SurfaceFormat::R8G8Snorm => Some(DxgiFormat::R8G8_SNorm),
+        SurfaceFormat::R8G8Sint => Some(DxgiFormat::R8G8_SInt),
+        SurfaceFormat::R32Uint => Some(DxgiFormat::R32_UInt),
+        SurfaceFormat::R32Sint => Some(DxgiFormat::R32_SInt),
+        SurfaceFormat::R32Float => Some(DxgiFormat::R32_Float),
+        SurfaceFormat::R16G16Unorm => Some(DxgiFormat::R16G16_UNorm),
+        SurfaceFormat::R16G16Uint => Some(DxgiFormat::R16G16_UInt),
+        SurfaceFormat::R16G16Snorm => Some(DxgiFormat::R16G16_SNorm),
+        SurfaceFormat::R16G16Sint => Some(DxgiFormat::R16G16_SInt),
+        SurfaceFormat::R16G16Float => Some(DxgiFormat::R16G16_Float),
+        SurfaceFormat::R11G11B10Float => Some(DxgiFormat::R11G11B10_Float),
+        SurfaceFormat::R10G10B10A2Unorm => Some(DxgiFormat::R10G10B10A2_UNorm),
+        SurfaceFormat::R10G10B10A2Uint => Some(DxgiFormat::R10G10B10A2_UInt),
+        SurfaceFormat::R8G8B8A8Unorm => Some(DxgiFormat::R8G8B8A8_UNorm),
+        SurfaceFormat::R8G8B8A8Uint => Some(DxgiFormat::R8G8B8A8_UInt),
+        SurfaceFormat::R8G8B8A8Snorm => Some(DxgiFormat::R8G8B8A8_SNorm),
+        SurfaceFormat::R8G8B8A8Sint => Some(DxgiFormat::R8G8B8A8_SInt),
+        SurfaceFormat::R8G8B8A8Srgb => Some(DxgiFormat::R8G8B8A8_UNorm_sRGB),
+        SurfaceFormat::R32G32Uint => Some(DxgiFormat::R32G32_UInt),
+        SurfaceFormat::R32G32Sint => Some(DxgiFormat::R32G32_SInt),
+        SurfaceFormat::R32G32Float => Some(DxgiFormat::R32G32_Float),
+        SurfaceFormat::R16G16B16A16Unorm => Some(DxgiFormat::R16G16B16A16_UNorm),
+        SurfaceFormat::R16G16B16A16Uint => Some(DxgiFormat::R16G16B16A16_UInt),
+        SurfaceFormat::R16G16B16A16Snorm => Some(DxgiFormat::R16G16B16A16_SNorm),
+        SurfaceFormat::R16G16B16A16Sint => Some(DxgiFormat::R16G16B16A16_SInt),
+        SurfaceFormat::R16G16B16A16Float => Some(DxgiFormat::R16G16B16A16_Float),
+        SurfaceFormat::R32G32B32A32Uint => Some(DxgiFormat::R32G32B32A32_UInt),
+        SurfaceFormat::R32G32B32A32Sint => Some(DxgiFormat::R32G32B32A32_SInt),
+        SurfaceFormat::R32G32B32A32Float => Some(DxgiFormat::R32G32B32A32_Float),
+        SurfaceFormat::Bc1Unorm => Some(DxgiFormat::BC1_UNorm),
+        SurfaceFormat::Bc1Srgb => Some(DxgiFormat::BC1_UNorm_sRGB),
+        SurfaceFormat::Bc2Unorm => Some(DxgiFormat::BC2_UNorm),
+        SurfaceFormat::Bc2Srgb => Some(DxgiFormat::BC2_UNorm_sRGB),
+        SurfaceFormat::Bc3Unorm => Some(DxgiFormat::BC3_UNorm),
+        SurfaceFormat::Bc3Srgb => Some(DxgiFormat::BC3_UNorm_sRGB),
+        SurfaceFormat::Bc4Unorm => Some(DxgiFormat::BC4_UNorm),
+        SurfaceFormat::Bc4Snorm => Some(DxgiFormat::BC4_SNorm),
+        SurfaceFormat::Bc5Unorm => Some(DxgiFormat::BC5_UNorm),
+        SurfaceFormat::Bc5Snorm => Some(DxgiFormat::BC5_SNorm),
+        SurfaceFormat::R4G4Unorm
+        | SurfaceFormat::R5G6B5Unorm
+        | SurfaceFormat::R5G5B5A1Unorm
+        | SurfaceFormat::R4G4B4A4Unorm
+        | SurfaceFormat::D24S8Float
+        | SurfaceFormat::R10G10B10A2Sint => None,
+    }
+}
@@ -0,0 +1,386 @@
+use std::error::Error;
+
+use wiiu_swizzle::{AaMode, SurfaceDim, SurfaceFormat, SwizzledSurface, TileMode};
+
+use crate::{read_u32, write_u32};
+
+const FILE_MAGIC: &[u8; 4] = b"Gfx2";
+const BLOCK_MAGIC: &[u8; 4] = b"BLK{";
+
+// The size of the GX2Texture struct including the GX2Surface.
+const TEXTURE_SIZE: usize = 0x9C;
+
+// The block header fields up to and including the data size.
+const MIN_BLOCK_HEADER_SIZE: usize = 24;
+
+/// A GFX2 (.gtx) file containing one or more textures.
+pub struct Gtx {
+    header: Vec<u8>,
+    blocks: Vec<Block>,
+    block_types: BlockTypes,
+}
+
+struct Block {
+    header: Vec<u8>,
+    block_type: u32,
+    data: Vec<u8>,
+}
+
+#[derive(Clone, Copy)]
+struct BlockTypes {
+    surface: u32,
+    image: u32,
+    mipmap: u32,
+}
+
+/// The block indices for a single texture.
+struct TextureBlocks {
+    surface: usize,
+    image: Option<usize>,
+    mipmap: Option<usize>,
+}
+
+impl Gtx {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.get(..4) != Some(FILE_MAGIC) {
+            return Err("invalid GFX2 file magic".into());
+        }
+        let header_size = read_u32(bytes, 4)? as usize;
+        let major_version = read_u32(bytes, 8)?;
+        let header = bytes
+            .get(..header_size)
+            .ok_or("GFX2 header out of range")?
+            .to_vec();
+
+        // The block type values changed between versions.
+        let block_types = if major_version == 6 {
+            BlockTypes {
+                surface: 0x0A,
+                image: 0x0B,
+                mipmap: 0x0C,
+            }
+        } else {
+            BlockTypes {
+                surface: 0x0B,
+                image: 0x0C,
+                mipmap: 0x0D,
+            }
+        };
+
+        let mut blocks = Vec::new();
+        let mut offset = header_size;
+        while offset < bytes.len() {
+            if bytes.get(offset..offset + 4) != Some(BLOCK_MAGIC) {
+                return Err(format!("invalid block magic at offset {offset}").into());
+            }
+            let block_header_size = read_u32(bytes, offset + 4)? as usize;
+            // This also ensures each block advances the offset.
+            if block_header_size < MIN_BLOCK_HEADER_SIZE {
+                return Err(format!("block header at offset {offset} is too small").into());
+            }
+            let block_type = read_u32(bytes, offset + 16)?;
+            let data_size = read_u32(bytes, offset + 20)? as usize;
+
+            let data_start = offset + block_header_size;
+            let header = bytes
+                .get(offset..data_start)
+                .ok_or("block header out of range")?
+                .to_vec();
+            let data = bytes
+                .get(data_start..data_start + data_size)
+                .ok_or("block data out of range")?
+                .to_vec();
+            blocks.push(Block {
+                header,
+                block_type,
+                data,
+            });
+
+            offset = data_start + data_size;
+        }
+
+        Ok(Self {
+            header,
+            blocks,
+            block_types,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.clone();
+        for block in &self.blocks {
+            let mut header = block.header.clone();
+            write_u32(&mut header, 20, block.data.len() as u32);
+            bytes.extend_from_slice(&header);
+            bytes.extend_from_slice(&block.data);
+        }
+        bytes
+    }
+
+    fn textures(&self) -> Vec<TextureBlocks> {
+        let mut textures: Vec<TextureBlocks> = Vec::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            if block.block_type == self.block_types.surface {
+                textures.push(TextureBlocks {
+                    surface: i,
+                    image: None,
+                    mipmap: None,
+                });
+            } else if let Some(texture) = textures.last_mut() {
+                if block.block_type == self.block_types.image {
+                    texture.image = Some(i);
+                } else if block.block_type == self.block_types.mipmap {
+                    texture.mipmap = Some(i);
+                }
+            }
+        }
+        textures
+    }
+
+    /// The surfaces for each texture in the file.
+    pub fn surfaces(&self) -> Result<Vec<SwizzledSurface>, Box<dyn Error>> {
+        self.textures()
+            .iter()
+            .map(|texture| {
+                let data =
+                    |i: Option<usize>| i.map(|i| self.blocks[i].data.clone()).unwrap_or_default();
+                read_surface(
+                    &self.blocks[texture.surface].data,
+                    data(texture.image),
+                    data(texture.mipmap),
+                )
+            })
+            .collect()
+    }
+
+    /// Replace the surface for the texture at `index` and update the surface fields.
+    ///
+    /// The texture registers and padding blocks are not updated,
+    /// so the new surface must have the same dimensions, format, and mipmap count.
+    pub fn replace_surface(
+        &mut self,
+        index: usize,
+        surface: &SwizzledSurface,
+    ) -> Result<(), Box<dyn Error>> {
+        let texture = self
+            .textures()
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| format!("texture index {index} out of range"))?;
+
+        let original = read_surface(&self.blocks[texture.surface].data, Vec::new(), Vec::new())?;
+        if (
+            original.dim,
+            original.width,
+            original.height,
+            original.depth_or_array_layers,
+            original.mipmap_count,
+            original.format,
+            original.aa,
+        ) != (
+            surface.dim,
+            surface.width,
+            surface.height,
+            surface.depth_or_array_layers,
+            surface.mipmap_count,
+            surface.format,
+            surface.aa,
+        ) {
+            return Err(format!(
+                "expected a {}x{}x{} {:?} surface with {} mipmaps but found {}x{}x{} {:?} with {} mipmaps",
+                original.width,
+                original.height,
+                original.depth_or_array_layers,
+                original.format,
+                original.mipmap_count,
+                surface.width,
+                surface.height,
+                surface.depth_or_array_layers,
+                surface.format,
+                surface.mipmap_count,
+            )
+            .into());
+        }
+
+        write_surface(&mut self.blocks[texture.surface].data, surface)?;
+
+        let image = texture.image.ok_or("missing image block")?;
+        self.blocks[image].data = surface.image_data.clone();
+
+        // The mipmap count is unchanged, so the mipmap block is present if needed.
+        if let Some(i) = texture.mipmap {
+            self.blocks[i].data = surface.mipmap_data.clone();
+        }
+
+        Ok(())
+    }
+}
+
+fn read_surface(
+    data: &[u8],
+    image_data: Vec<u8>,
+    mipmap_data: Vec<u8>,
+) -> Result<SwizzledSurface, Box<dyn Error>> {
+    if data.len() < TEXTURE_SIZE {
+        return Err("surface block is too small".into());
+    }
+    let field = |i: usize| read_u32(data, i * 4);
+
+    let dim = field(0)?;
+    let format = field(5)?;
+    let aa = field(6)?;
+    let tile_mode = field(12)?;
+
+    let mut mipmap_offsets = [0; 13];
+    for (i, offset) in mipmap_offsets.iter_mut().enumerate() {
+        *offset = field(16 + i)?;
+    }
+
+    Ok(SwizzledSurface {
+        dim: SurfaceDim::from_repr(dim).ok_or_else(|| format!("unknown dim {dim}"))?,
+        width: field(1)?,
+        height: field(2)?,
+        depth_or_array_layers: field(3)?,
+        mipmap_count: field(4)?,
+        format: SurfaceFormat::from_repr(format)
+            .ok_or_else(|| format!("unknown format 0x{format:X}"))?,
+        aa: AaMode::from_repr(aa).ok_or_else(|| format!("unknown aa mode {aa}"))?,
+        usage: field(7)?,
+        image_data,
+        mipmap_data,
+        tile_mode: TileMode::from_repr(tile_mode)
+            .ok_or_else(|| format!("unknown tile mode {tile_mode}"))?,
+        swizzle: field(13)?,
+        alignment: field(14)?,
+        pitch: field(15)?,
+        mipmap_offsets,
+    })
+}
+
+fn write_surface(data: &mut [u8], surface: &SwizzledSurface) -> Result<(), Box<dyn Error>> {
+    if data.len() < TEXTURE_SIZE {
+        return Err("surface block is too small".into());
+    }
+    let mut field = |i: usize, value: u32| write_u32(data, i * 4, value);
+
+    field(0, surface.dim as u32);
+    field(1, surface.width);
+    field(2, surface.height);
+    field(3, surface.depth_or_array_layers);
+    field(4, surface.mipmap_count);
+    field(5, surface.format as u32);
+    field(6, surface.aa as u32);
+    field(7, surface.usage);
+    field(8, surface.image_data.len() as u32);
+    field(10, surface.mipmap_data.len() as u32);
+    field(12, surface.tile_mode as u32);
+    field(13, surface.swizzle);
+    field(14, surface.alignment);
+    field(15, surface.pitch);
+    for (i, offset) in surface.mipmap_offsets.iter().enumerate() {
+        field(16 + i, *offset);
+    }
+
+    // Update the texture view to include all mipmaps and layers.
+    field(30, surface.mipmap_count);
+    let slices = match surface.dim {
+        SurfaceDim::D3 => 1,
+        _ => surface.depth_or_array_layers,
+    };
+    field(32, slices);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(block_type: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = BLOCK_MAGIC.to_vec();
+        for value in [32, 1, 0, block_type, data.len() as u32, 0, 0] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn surface(width: u32, height: u32) -> SwizzledSurface {
+        SwizzledSurface {
+            dim: SurfaceDim::D2,
+            width,
+            height,
+            depth_or_array_layers: 1,
+            mipmap_count: 1,
+            format: SurfaceFormat::R8G8B8A8Unorm,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: vec![1; 64],
+            mipmap_data: Vec::new(),
+            tile_mode: TileMode::D1TiledThin1,
+            swizzle: 0,
+            alignment: 512,
+            pitch: 8,
+            mipmap_offsets: [0; 13],
+        }
+    }
+
+    fn gtx_bytes() -> Vec<u8> {
+        let mut bytes = FILE_MAGIC.to_vec();
+        for value in [32u32, 7, 1, 2, 0, 0, 0] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let mut texture = vec![0u8; TEXTURE_SIZE];
+        write_surface(&mut texture, &surface(4, 4)).unwrap();
+        bytes.extend_from_slice(&block(0x0B, &texture));
+        bytes.extend_from_slice(&block(0x0C, &[0; 64]));
+        bytes.extend_from_slice(&block(0x01, &[]));
+        bytes
+    }
+
+    #[test]
+    fn gtx_round_trip() {
+        let bytes = gtx_bytes();
+        let gtx = Gtx::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, gtx.to_bytes());
+
+        let surfaces = gtx.surfaces().unwrap();
+        assert_eq!(1, surfaces.len());
+        assert_eq!((4, 4), (surfaces[0].width, surfaces[0].height));
+        assert_eq!(vec![0; 64], surfaces[0].image_data);
+    }
+
+    #[test]
+    fn gtx_truncated() {
+        let bytes = gtx_bytes();
+        assert!(Gtx::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Gtx::from_bytes(&bytes[..40]).is_err());
+        assert!(Gtx::from_bytes(&bytes[..3]).is_err());
+    }
+
+    #[test]
+    fn gtx_zero_size_block() {
+        let mut bytes = gtx_bytes();
+        let mut empty = BLOCK_MAGIC.to_vec();
+        empty.extend_from_slice(&[0; 20]);
+        bytes.extend_from_slice(&empty);
+        assert!(Gtx::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn gtx_replace_surface() {
+        let mut gtx = Gtx::from_bytes(&gtx_bytes()).unwrap();
+        gtx.replace_surface(0, &surface(4, 4)).unwrap();
+
+        let gtx = Gtx::from_bytes(&gtx.to_bytes()).unwrap();
+        assert_eq!(vec![1; 64], gtx.surfaces().unwrap()[0].image_data);
+    }
+
+    #[test]
+    fn gtx_replace_surface_dimensions() {
+        let mut gtx = Gtx::from_bytes(&gtx_bytes()).unwrap();
+        assert!(gtx.replace_surface(0, &surface(8, 8)).is_err());
+        assert!(gtx.replace_surface(1, &surface(4, 4)).is_err());
+    }
+}
@@ -0,0 +1,591 @@
+use std::{
+    error::Error,
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use bflim::Bflim;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dds::LinearData;
+use gtx::Gtx;
+use image::DynamicImage;
+use wiiu_swizzle::{
+    encode_surface_rgba32f, encode_surface_rgba8, max_mipmap_count, surface_layout,
+    swizzle_surface, AaMode, EncodeQuality, MipmapFilter, SurfaceDim, SurfaceFormat,
+    SwizzledSurface, TileMode,
+};
+
+mod bflim;
+mod dds;
+mod gtx;
+
+/// Inspect, extract, and inject Wii U GX2 surfaces.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Print the surface fields and memory layout of each mipmap
+    Info {
+        /// The input .gtx, .bflim, or raw surface file
+        input: PathBuf,
+
+        #[command(flatten)]
+        raw: RawArgs,
+    },
+    /// Deswizzle a surface and save it as .dds or .png
+    Extract {
+        /// The input .gtx, .bflim, or raw surface file
+        input: PathBuf,
+
+        /// The output .dds or .png file
+        output: PathBuf,
+
+        /// The index of the surface for files with multiple surfaces
+        #[arg(long, default_value_t = 0)]
+        surface: usize,
+
+        /// The array layer or cube face to save for image outputs
+        #[arg(long, default_value_t = 0)]
+        layer: usize,
+
+        /// The mipmap to save for image outputs
+        #[arg(long, default_value_t = 0)]
+        mip: usize,
+
+        #[command(flatten)]
+        raw: RawArgs,
+    },
+    /// Swizzle a .dds or .png and replace the surface data
+    Inject {
+        /// The input .gtx, .bflim, or raw surface file
+        input: PathBuf,
+
+        /// The replacement .dds or .png file
+        image: PathBuf,
+
+        /// The output file with the same type as the input
+        output: PathBuf,
+
+        /// The index of the surface for files with multiple surfaces
+        #[arg(long, default_value_t = 0)]
+        surface: usize,
+
+        /// The number of mipmaps to generate for images instead of the original count
+        #[arg(long)]
+        mipmaps: Option<u32>,
+
+        /// The filter used to generate mipmaps for images
+        #[arg(long, value_enum, default_value_t = Filter::Box)]
+        filter: Filter,
+
+        /// The quality used to encode uncompressed data
+        #[arg(long, value_enum, default_value_t = Quality::Normal)]
+        quality: Quality,
+
+        #[command(flatten)]
+        raw: RawArgs,
+    },
+}
+
+/// The surface fields for raw files containing the image data followed by the mipmap data.
+/// Values not provided are calculated from the other fields.
+#[derive(Args)]
+struct RawArgs {
+    /// The surface format as a GX2SurfaceFormat value or name like "Bc1Unorm"
+    #[arg(long, value_parser = parse_format)]
+    format: Option<SurfaceFormat>,
+
+    #[arg(long, value_parser = parse_dim, default_value = "D2")]
+    dim: SurfaceDim,
+
+    #[arg(long)]
+    width: Option<u32>,
+
+    #[arg(long)]
+    height: Option<u32>,
+
+    #[arg(long, default_value_t = 1)]
+    depth: u32,
+
+    #[arg(long = "mipmap-count", default_value_t = 1)]
+    mipmap_count: u32,
+
+    /// The tile mode as a GX2TileMode value or name like "D2TiledThin1"
+    #[arg(long, value_parser = parse_tile_mode, default_value = "D2TiledThin1")]
+    tile_mode: TileMode,
+
+    #[arg(long, value_parser = parse_aa, default_value = "X1")]
+    aa: AaMode,
+
+    #[arg(long, value_parser = parse_u32, default_value = "0")]
+    swizzle: u32,
+
+    #[arg(long, value_parser = parse_u32)]
+    pitch: Option<u32>,
+
+    #[arg(long, value_parser = parse_u32)]
+    alignment: Option<u32>,
+
+    #[arg(long, value_parser = parse_u32)]
+    image_size: Option<u32>,
+
+    /// Comma separated mipmap offsets using the same conventions as GX2
+    #[arg(long, value_parser = parse_u32, value_delimiter = ',')]
+    mipmap_offsets: Option<Vec<u32>>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Filter {
+    Box,
+    Kaiser,
+    Lanczos3,
+}
+
+impl From<Filter> for MipmapFilter {
+    fn from(value: Filter) -> Self {
+        match value {
+            Filter::Box => MipmapFilter::Box,
+            Filter::Kaiser => MipmapFilter::Kaiser,
+            Filter::Lanczos3 => MipmapFilter::Lanczos3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Quality {
+    Fast,
+    Normal,
+    Slow,
+}
+
+impl From<Quality> for EncodeQuality {
+    fn from(value: Quality) -> Self {
+        match value {
+            Quality::Fast => EncodeQuality::Fast,
+            Quality::Normal => EncodeQuality::Normal,
+            Quality::Slow => EncodeQuality::Slow,
+        }
+    }
+}
+
+enum Container {
+    Gtx(Gtx),
+    Bflim(Bflim),
+    Raw(SwizzledSurface),
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Commands::Info { input, raw } => {
+            let container = load(&input, &raw)?;
+            for (i, surface) in surfaces(&container)?.iter().enumerate() {
+                print_info(i, surface)?;
+            }
+        }
+        Commands::Extract {
+            input,
+            output,
+            surface,
+            layer,
+            mip,
+            raw,
+        } => {
+            // The cli feature only enables the PNG codec for images.
+            if !has_extension(&output, "dds") && !has_extension(&output, "png") {
+                return Err(format!(
+                    "unsupported output file {output:?}; expected a .dds or .png file"
+                )
+                .into());
+            }
+
+            let container = load(&input, &raw)?;
+            let surface = select_surface(&container, surface)?;
+            let surface = surface.as_gx2_surface();
+
+            if has_extension(&output, "dds") {
+                let dds = dds::create_dds(&surface)?;
+                let mut writer = std::io::BufWriter::new(std::fs::File::create(&output)?);
+                dds.write(&mut writer)?;
+            } else {
+                let images = surface.to_images()?;
+                let image = images
+                    .get(layer)
+                    .and_then(|mips| mips.get(mip))
+                    .ok_or_else(|| format!("layer {layer} mip {mip} out of range"))?;
+                match image {
+                    // PNG doesn't support floating point data.
+                    DynamicImage::ImageRgba32F(_) => {
+                        DynamicImage::ImageRgba16(image.to_rgba16()).save(&output)?
+                    }
+                    _ => image.save(&output)?,
+                }
+            }
+        }
+        Commands::Inject {
+            input,
+            image,
+            output,
+            surface,
+            mipmaps,
+            filter,
+            quality,
+            raw,
+        } => {
+            let mut container = load(&input, &raw)?;
+            let original = select_surface(&container, surface)?;
+            let mut new_surface =
+                load_surface(&image, &original, mipmaps, filter.into(), quality.into())?;
+            new_surface.usage = original.usage;
+
+            let bytes = match &mut container {
+                Container::Gtx(gtx) => {
+                    gtx.replace_surface(surface, &new_surface)?;
+                    gtx.to_bytes()
+                }
+                Container::Bflim(bflim) => {
+                    bflim.replace_surface(&new_surface)?;
+                    bflim.to_bytes()
+                }
+                Container::Raw(_) => {
+                    println!("{}", surface_info(&new_surface)?);
+                    raw_bytes(&new_surface)
+                }
+            };
+            std::fs::write(output, bytes)?;
+        }
+    }
+    Ok(())
+}
+
+fn load(path: &Path, raw: &RawArgs) -> Result<Container, Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    if raw.format.is_some() {
+        raw_surface(bytes, raw).map(Container::Raw)
+    } else if has_extension(path, "gtx") {
+        Gtx::from_bytes(&bytes).map(Container::Gtx)
+    } else if has_extension(path, "bflim") {
+        Bflim::from_bytes(&bytes).map(Container::Bflim)
+    } else {
+        Err(
+            "unrecognized file type; specify --format and other surface fields for raw files"
+                .into(),
+        )
+    }
+}
+
+fn surfaces(container: &Container) -> Result<Vec<SwizzledSurface>, Box<dyn Error>> {
+    match container {
+        Container::Gtx(gtx) => gtx.surfaces(),
+        Container::Bflim(bflim) => Ok(vec![bflim.surface()?]),
+        Container::Raw(surface) => Ok(vec![surface.clone()]),
+    }
+}
+
+fn select_surface(container: &Container, index: usize) -> Result<SwizzledSurface, Box<dyn Error>> {
+    surfaces(container)?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("surface index {index} out of range").into())
+}
+
+fn raw_surface(bytes: Vec<u8>, raw: &RawArgs) -> Result<SwizzledSurface, Box<dyn Error>> {
+    let format = raw.format.ok_or("missing --format")?;
+    let width = raw.width.ok_or("missing --width")?;
+    let height = raw.height.ok_or("missing --height")?;
+
+    let layout = surface_layout(
+        raw.dim,
+        width,
+        height,
+        raw.depth,
+        raw.mipmap_count,
+        format,
+        raw.aa,
+        raw.tile_mode,
+        raw.swizzle,
+    )?;
+
+    let mut mipmap_offsets = layout.mipmap_offsets;
+    if let Some(offsets) = &raw.mipmap_offsets {
+        if offsets.len() > mipmap_offsets.len() {
+            return Err("expected at most 13 mipmap offsets".into());
+        }
+        mipmap_offsets = [0; 13];
+        mipmap_offsets[..offsets.len()].copy_from_slice(offsets);
+    }
+
+    // The mipmap data starts at the mip 1 offset.
+    let image_size = raw.image_size.unwrap_or(layout.image_size) as usize;
+    let image_data = bytes[..image_size.min(bytes.len())].to_vec();
+    let mipmap_data = if raw.mipmap_count > 1 {
+        bytes
+            .get(mipmap_offsets[0] as usize..)
+            .unwrap_or_default()
+            .to_vec()
+    } else {
+        Vec::new()
+    };
+
+    Ok(SwizzledSurface {
+        dim: raw.dim,
+        width,
+        height,
+        depth_or_array_layers: raw.depth,
+        mipmap_count: raw.mipmap_count,
+        format,
+        aa: raw.aa,
+        usage: 1,
+        image_data,
+        mipmap_data,
+        tile_mode: raw.tile_mode,
+        swizzle: raw.swizzle,
+        alignment: raw.alignment.unwrap_or(layout.alignment),
+        pitch: raw.pitch.unwrap_or(layout.pitch),
+        mipmap_offsets,
+    })
+}
+
+fn raw_bytes(surface: &SwizzledSurface) -> Vec<u8> {
+    let mut bytes = surface.image_data.clone();
+    if !surface.mipmap_data.is_empty() {
+        bytes.resize(surface.mipmap_offsets[0] as usize, 0);
+        bytes.extend_from_slice(&surface.mipmap_data);
+    }
+    bytes
+}
+
+fn load_surface(
+    path: &Path,
+    original: &SwizzledSurface,
+    mipmap_count: Option<u32>,
+    filter: MipmapFilter,
+    quality: EncodeQuality,
+) -> Result<SwizzledSurface, Box<dyn Error>> {
+    if has_extension(path, "dds") {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let dds = ddsfile::Dds::read(&mut reader)?;
+        let linear = dds::read_dds(&dds, original.format)?;
+
+        // DDS files don't distinguish between 1D and 2D surfaces.
+        let dim = match (original.dim, linear.dim) {
+            (SurfaceDim::D1, SurfaceDim::D2) => SurfaceDim::D1,
+            (_, dim) => dim,
+        };
+
+        let data = match linear.data {
+            LinearData::Encoded(data) => data,
+            LinearData::Rgba8(data) => encode_surface_rgba8(
+                dim,
+                linear.width,
+                linear.height,
+                linear.depth_or_array_layers,
+                linear.mipmap_count,
+                original.format,
+                &data,
+                quality,
+            )?,
+            LinearData::Rgba32Float(data) => encode_surface_rgba32f(
+                dim,
+                linear.width,
+                linear.height,
+                linear.depth_or_array_layers,
+                linear.mipmap_count,
+                original.format,
+                &data,
+                quality,
+            )?,
+        };
+
+        Ok(swizzle_surface(
+            dim,
+            linear.width,
+            linear.height,
+            linear.depth_or_array_layers,
+            linear.mipmap_count,
+            original.format,
+            original.aa,
+            original.tile_mode,
+            original.swizzle,
+            &data,
+        )?)
+    } else {
+        if original.dim != SurfaceDim::D2 || original.depth_or_array_layers != 1 {
+            return Err("images can only replace 2D surfaces with a single layer; use DDS for arrays, cube maps, and 3D surfaces".into());
+        }
+
+        let image = image::open(path)?;
+        let mipmap_count = mipmap_count
+            .unwrap_or(original.mipmap_count)
+            .min(max_mipmap_count(
+                SurfaceDim::D2,
+                image.width(),
+                image.height(),
+                1,
+            ));
+        Ok(SwizzledSurface::from_image(
+            &image,
+            original.format,
+            original.tile_mode,
+            original.swizzle,
+            mipmap_count,
+            filter,
+            quality,
+        )?)
+    }
+}
+
+fn print_info(index: usize, surface: &SwizzledSurface) -> Result<(), Box<dyn Error>> {
+    println!("Surface {index}");
+    println!("{}", surface_info(surface)?);
+    Ok(())
+}
+
+fn surface_info(surface: &SwizzledSurface) -> Result<String, Box<dyn Error>> {
+    let layout = surface_layout(
+        surface.dim,
+        surface.width,
+        surface.height,
+        surface.depth_or_array_layers,
+        surface.mipmap_count,
+        surface.format,
+        surface.aa,
+        surface.tile_mode,
+        surface.swizzle,
+    )?;
+
+    let mut lines = vec![
+        format!("  dim: {:?}", surface.dim),
+        format!(
+            "  size: {}x{}x{}",
+            surface.width, surface.height, surface.depth_or_array_layers
+        ),
+        format!("  mipmap count: {}", surface.mipmap_count),
+        format!(
+            "  format: {:?} (0x{:X})",
+            surface.format, surface.format as u32
+        ),
+        format!("  aa: {:?}", surface.aa),
+        format!("  usage: {}", surface.usage),
+        format!(
+            "  tile mode: {:?} ({})",
+            surface.tile_mode, surface.tile_mode as u32
+        ),
+        format!("  swizzle: 0x{:X}", surface.swizzle),
+        field_info("alignment", surface.alignment, layout.alignment),
+        field_info("pitch", surface.pitch, layout.pitch),
+        field_info(
+            "image size",
+            surface.image_data.len() as u32,
+            layout.image_size,
+        ),
+        field_info(
+            "mipmap size",
+            surface.mipmap_data.len() as u32,
+            layout.mipmap_size,
+        ),
+        format!("  mipmap offsets: {:?}", surface.mipmap_offsets),
+    ];
+    if surface.mipmap_offsets != layout.mipmap_offsets {
+        lines.push(format!(
+            "  calculated mipmap offsets: {:?}",
+            layout.mipmap_offsets
+        ));
+    }
+
+    lines.push(format!(
+        "  {:>4} {:>12} {:>6} {:>14} {:>6} {:>8} {:>8}",
+        "mip", "blocks", "depth", "tile mode", "pitch", "offset", "size"
+    ));
+    for (i, mip) in layout.mipmaps.iter().enumerate() {
+        lines.push(format!(
+            "  {:>4} {:>12} {:>6} {:>14} {:>6} {:>8} {:>8}",
+            i,
+            format!("{}x{}", mip.width, mip.height),
+            mip.depth_or_array_layers,
+            format!("{:?}", mip.tile_mode),
+            mip.pitch,
+            mip.offset,
+            mip.size
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn field_info(name: &str, value: u32, calculated: u32) -> String {
+    if value == calculated {
+        format!("  {name}: {value}")
+    } else {
+        format!("  {name}: {value} (calculated {calculated})")
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+fn parse_u32(s: &str) -> Result<u32, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// Parse an enum from its numeric value or case insensitive variant name.
+fn parse_enum<T: Debug>(s: &str, from_repr: fn(u32) -> Option<T>, max: u32) -> Result<T, String> {
+    if let Ok(value) = parse_u32(s) {
+        return from_repr(value).ok_or_else(|| format!("unknown value {value}"));
+    }
+    (0..=max)
+        .filter_map(from_repr)
+        .find(|v| format!("{v:?}").eq_ignore_ascii_case(s))
+        .ok_or_else(|| format!("unknown name {s:?}"))
+}
+
+fn parse_format(s: &str) -> Result<SurfaceFormat, String> {
+    parse_enum(s, SurfaceFormat::from_repr, 0xFFF)
+}
+
+fn parse_dim(s: &str) -> Result<SurfaceDim, String> {
+    parse_enum(s, SurfaceDim::from_repr, 0xF)
+}
+
+fn parse_tile_mode(s: &str) -> Result<TileMode, String> {
+    parse_enum(s, TileMode::from_repr, 0xFF)
+}
+
+fn parse_aa(s: &str) -> Result<AaMode, String> {
+    parse_enum(s, AaMode::from_repr, 0xF)
+}
+
+pub fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, Box<dyn Error>> {
+    let bytes = bytes.get(offset..offset + 2).ok_or("read out of range")?;
+    Ok(u16::from_be_bytes(bytes.try_into()?))
+}
+
+pub fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Box<dyn Error>> {
+    let bytes = bytes.get(offset..offset + 4).ok_or("read out of range")?;
+    Ok(u32::from_be_bytes(bytes.try_into()?))
+}
+
+pub fn write_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+pub fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
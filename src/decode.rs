@@ -0,0 +1,741 @@
+use crate::{div_round_up, SurfaceFormat, SwizzleError};
+use alloc::{vec, vec::Vec};
+
+// Decoding works on the deswizzled data for a single mipmap.
+// Multi-byte values are little-endian like the GPU memory layout.
+// Packed formats store the first component in the least significant bits.
+
+/// Decode the deswizzled data for a single mipmap to RGBA8.
+///
+/// The `width` and `height` are the dimensions of the mipmap in pixels
+/// rather than blocks, so block compressed formats can have any size.
+/// Pixels from partial blocks on the right and bottom edges are discarded.
+/// The `data` should contain `depth_or_array_layers` slices
+/// like the output of [deswizzle_mipmap](crate::deswizzle_mipmap).
+///
+/// Signed normalized values are remapped from `-1.0..=1.0` to `0..=255`.
+/// Integer and float values are clamped to the range of the output.
+/// Use [decode_rgba32f] to preserve the full range of HDR or integer formats.
+pub fn decode_rgba8(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+) -> Result<Vec<u8>, SwizzleError> {
+    let size = decoded_size(width, height, depth_or_array_layers);
+    let mut output = vec![0u8; size * 4];
+
+    let kind = format.kind();
+    let channels = format.channel_count();
+    decode(
+        width,
+        height,
+        depth_or_array_layers,
+        format,
+        data,
+        |i, pixel| {
+            let mut rgba = [0, 0, 0, u8::MAX];
+            for c in 0..channels {
+                rgba[c] = kind.to_u8(pixel[c]);
+            }
+            output[i * 4..i * 4 + 4].copy_from_slice(&rgba);
+        },
+    )?;
+
+    Ok(output)
+}
+
+/// Decode the deswizzled data for a single mipmap to RGBA32F.
+///
+/// Unsigned and signed normalized values are in the range `0.0..=1.0` and `-1.0..=1.0`.
+/// Integer values are converted to the nearest float.
+/// sRGB formats are not converted to linear.
+///
+/// See [decode_rgba8] for details on how the dimensions and data are handled.
+pub fn decode_rgba32f(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+) -> Result<Vec<f32>, SwizzleError> {
+    let size = decoded_size(width, height, depth_or_array_layers);
+    let mut output = vec![0.0f32; size * 4];
+
+    decode(
+        width,
+        height,
+        depth_or_array_layers,
+        format,
+        data,
+        |i, pixel| {
+            output[i * 4..i * 4 + 4].copy_from_slice(&pixel);
+        },
+    )?;
+
+    Ok(output)
+}
+
+fn decoded_size(width: u32, height: u32, depth_or_array_layers: u32) -> usize {
+    width as usize * height as usize * depth_or_array_layers as usize
+}
+
+/// The numeric interpretation of the components for a format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Unorm,
+    Snorm,
+    Uint,
+    Sint,
+    Float,
+}
+
+impl Kind {
+    fn to_u8(self, value: f32) -> u8 {
+        match self {
+            Kind::Unorm | Kind::Float => unorm_to_u8(value),
+            Kind::Snorm => unorm_to_u8(value * 0.5 + 0.5),
+            // Values are already integers, so truncating is exact.
+            Kind::Uint => value.clamp(0.0, 255.0) as u8,
+            // Keep the two's complement bits so 8-bit signed values are preserved.
+            Kind::Sint => value.clamp(i8::MIN as f32, i8::MAX as f32) as i8 as u8,
+        }
+    }
+}
+
+pub(crate) fn unorm_to_u8(value: f32) -> u8 {
+    // Adding 0.5 and truncating rounds non negative values.
+    // NaN converts to 0.
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+impl SurfaceFormat {
+    pub(crate) fn kind(&self) -> Kind {
+        match self {
+            SurfaceFormat::R8Unorm
+            | SurfaceFormat::R4G4Unorm
+            | SurfaceFormat::R16Unorm
+            | SurfaceFormat::R8G8Unorm
+            | SurfaceFormat::R5G6B5Unorm
+            | SurfaceFormat::R5G5B5A1Unorm
+            | SurfaceFormat::R4G4B4A4Unorm
+            | SurfaceFormat::R16G16Unorm
+            | SurfaceFormat::D24S8Float
+            | SurfaceFormat::R10G10B10A2Unorm
+            | SurfaceFormat::R8G8B8A8Unorm
+            | SurfaceFormat::R8G8B8A8Srgb
+            | SurfaceFormat::R16G16B16A16Unorm
+            | SurfaceFormat::Bc1Unorm
+            | SurfaceFormat::Bc1Srgb
+            | SurfaceFormat::Bc2Unorm
+            | SurfaceFormat::Bc2Srgb
+            | SurfaceFormat::Bc3Unorm
+            | SurfaceFormat::Bc3Srgb
+            | SurfaceFormat::Bc4Unorm
+            | SurfaceFormat::Bc5Unorm => Kind::Unorm,
+            SurfaceFormat::R8Snorm
+            | SurfaceFormat::R16Snorm
+            | SurfaceFormat::R8G8Snorm
+            | SurfaceFormat::R16G16Snorm
+            | SurfaceFormat::R8G8B8A8Snorm
+            | SurfaceFormat::R16G16B16A16Snorm
+            | SurfaceFormat::Bc4Snorm
+            | SurfaceFormat::Bc5Snorm => Kind::Snorm,
+            SurfaceFormat::R8Uint
+            | SurfaceFormat::R16Uint
+            | SurfaceFormat::R8G8Uint
+            | SurfaceFormat::R32Uint
+            | SurfaceFormat::R16G16Uint
+            | SurfaceFormat::R10G10B10A2Uint
+            | SurfaceFormat::R8G8B8A8Uint
+            | SurfaceFormat::R32G32Uint
+            | SurfaceFormat::R16G16B16A16Uint
+            | SurfaceFormat::R32G32B32A32Uint => Kind::Uint,
+            SurfaceFormat::R8Sint
+            | SurfaceFormat::R16Sint
+            | SurfaceFormat::R8G8Sint
+            | SurfaceFormat::R32Sint
+            | SurfaceFormat::R16G16Sint
+            | SurfaceFormat::R10G10B10A2Sint
+            | SurfaceFormat::R8G8B8A8Sint
+            | SurfaceFormat::R32G32Sint
+            | SurfaceFormat::R16G16B16A16Sint
+            | SurfaceFormat::R32G32B32A32Sint => Kind::Sint,
+            SurfaceFormat::R16Float
+            | SurfaceFormat::R32Float
+            | SurfaceFormat::R16G16Float
+            | SurfaceFormat::R11G11B10Float
+            | SurfaceFormat::R32G32Float
+            | SurfaceFormat::R16G16B16A16Float
+            | SurfaceFormat::R32G32B32A32Float => Kind::Float,
+        }
+    }
+
+    /// The number of components stored in the format.
+    /// Missing components decode to 0 for RGB and 1 for alpha.
+    pub(crate) fn channel_count(&self) -> usize {
+        match self {
+            SurfaceFormat::R8Unorm
+            | SurfaceFormat::R8Uint
+            | SurfaceFormat::R8Snorm
+            | SurfaceFormat::R8Sint
+            | SurfaceFormat::R16Unorm
+            | SurfaceFormat::R16Uint
+            | SurfaceFormat::R16Snorm
+            | SurfaceFormat::R16Sint
+            | SurfaceFormat::R16Float
+            | SurfaceFormat::R32Uint
+            | SurfaceFormat::R32Sint
+            | SurfaceFormat::R32Float
+            | SurfaceFormat::Bc4Unorm
+            | SurfaceFormat::Bc4Snorm => 1,
+            SurfaceFormat::R4G4Unorm
+            | SurfaceFormat::R8G8Unorm
+            | SurfaceFormat::R8G8Uint
+            | SurfaceFormat::R8G8Snorm
+            | SurfaceFormat::R8G8Sint
+            | SurfaceFormat::R16G16Unorm
+            | SurfaceFormat::R16G16Uint
+            | SurfaceFormat::R16G16Snorm
+            | SurfaceFormat::R16G16Sint
+            | SurfaceFormat::R16G16Float
+            | SurfaceFormat::D24S8Float
+            | SurfaceFormat::R32G32Uint
+            | SurfaceFormat::R32G32Sint
+            | SurfaceFormat::R32G32Float
+            | SurfaceFormat::Bc5Unorm
+            | SurfaceFormat::Bc5Snorm => 2,
+            SurfaceFormat::R5G6B5Unorm | SurfaceFormat::R11G11B10Float => 3,
+            _ => 4,
+        }
+    }
+
+    /// Returns `true` if the format has components outside the range of RGBA8.
+    ///
+    /// These formats should be decoded with [decode_rgba32f] to avoid losing precision.
+    /// Formats with 8-bit integer channels store the channel bytes unchanged in RGBA8.
+    pub fn is_hdr(&self) -> bool {
+        match self.kind() {
+            Kind::Float => true,
+            Kind::Uint | Kind::Sint => !matches!(
+                self,
+                SurfaceFormat::R8Uint
+                    | SurfaceFormat::R8G8Uint
+                    | SurfaceFormat::R8G8B8A8Uint
+                    | SurfaceFormat::R8Sint
+                    | SurfaceFormat::R8G8Sint
+                    | SurfaceFormat::R8G8B8A8Sint
+            ),
+            Kind::Unorm | Kind::Snorm => matches!(
+                self,
+                SurfaceFormat::R16Unorm
+                    | SurfaceFormat::R16Snorm
+                    | SurfaceFormat::R16G16Unorm
+                    | SurfaceFormat::R16G16Snorm
+                    | SurfaceFormat::R16G16B16A16Unorm
+                    | SurfaceFormat::R16G16B16A16Snorm
+                    | SurfaceFormat::R10G10B10A2Unorm
+                    | SurfaceFormat::D24S8Float
+            ),
+        }
+    }
+}
+
+fn decode(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+    mut write_pixel: impl FnMut(usize, [f32; 4]),
+) -> Result<(), SwizzleError> {
+    let (block_width, block_height) = format.block_dim();
+    let bytes_per_block = format.bytes_per_pixel() as usize;
+
+    let width_blocks = div_round_up(width, block_width) as usize;
+    let height_blocks = div_round_up(height, block_height) as usize;
+
+    let expected_size =
+        width_blocks * height_blocks * depth_or_array_layers as usize * bytes_per_block;
+    if data.len() < expected_size {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size,
+            actual_size: data.len(),
+        });
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let (block_width, block_height) = (block_width as usize, block_height as usize);
+
+    for z in 0..depth_or_array_layers as usize {
+        for by in 0..height_blocks {
+            for bx in 0..width_blocks {
+                let offset = ((z * height_blocks + by) * width_blocks + bx) * bytes_per_block;
+                let block = &data[offset..offset + bytes_per_block];
+
+                if block_width == 1 && block_height == 1 {
+                    let i = (z * height + by) * width + bx;
+                    write_pixel(i, decode_pixel(format, block));
+                } else {
+                    let pixels = decode_block(format, block);
+
+                    // Skip any pixels in partial blocks outside the image.
+                    for y in 0..block_height.min(height - by * block_height) {
+                        for x in 0..block_width.min(width - bx * block_width) {
+                            let i =
+                                (z * height + by * block_height + y) * width + bx * block_width + x;
+                            write_pixel(i, pixels[y * block_width + x]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_pixel(format: SurfaceFormat, bytes: &[u8]) -> [f32; 4] {
+    let u8_at = |i: usize| bytes[i];
+    let u16_at = |i: usize| u16::from_le_bytes([bytes[i * 2], bytes[i * 2 + 1]]);
+    let u32_at = |i: usize| {
+        u32::from_le_bytes([
+            bytes[i * 4],
+            bytes[i * 4 + 1],
+            bytes[i * 4 + 2],
+            bytes[i * 4 + 3],
+        ])
+    };
+
+    let mut rgba = [0.0, 0.0, 0.0, 1.0];
+    let channels = format.channel_count();
+
+    match format {
+        SurfaceFormat::R8Unorm
+        | SurfaceFormat::R8G8Unorm
+        | SurfaceFormat::R8G8B8A8Unorm
+        | SurfaceFormat::R8G8B8A8Srgb => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = unorm(u8_at(c) as u32, 8);
+            }
+        }
+        SurfaceFormat::R8Snorm | SurfaceFormat::R8G8Snorm | SurfaceFormat::R8G8B8A8Snorm => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = snorm(u8_at(c) as u32, 8);
+            }
+        }
+        SurfaceFormat::R8Uint | SurfaceFormat::R8G8Uint | SurfaceFormat::R8G8B8A8Uint => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = u8_at(c) as f32;
+            }
+        }
+        SurfaceFormat::R8Sint | SurfaceFormat::R8G8Sint | SurfaceFormat::R8G8B8A8Sint => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = u8_at(c) as i8 as f32;
+            }
+        }
+        SurfaceFormat::R16Unorm | SurfaceFormat::R16G16Unorm | SurfaceFormat::R16G16B16A16Unorm => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = unorm(u16_at(c) as u32, 16);
+            }
+        }
+        SurfaceFormat::R16Snorm | SurfaceFormat::R16G16Snorm | SurfaceFormat::R16G16B16A16Snorm => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = snorm(u16_at(c) as u32, 16);
+            }
+        }
+        SurfaceFormat::R16Uint | SurfaceFormat::R16G16Uint | SurfaceFormat::R16G16B16A16Uint => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = u16_at(c) as f32;
+            }
+        }
+        SurfaceFormat::R16Sint | SurfaceFormat::R16G16Sint | SurfaceFormat::R16G16B16A16Sint => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = u16_at(c) as i16 as f32;
+            }
+        }
+        SurfaceFormat::R16Float | SurfaceFormat::R16G16Float | SurfaceFormat::R16G16B16A16Float => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = f16_to_f32(u16_at(c));
+            }
+        }
+        SurfaceFormat::R32Uint | SurfaceFormat::R32G32Uint | SurfaceFormat::R32G32B32A32Uint => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = u32_at(c) as f32;
+            }
+        }
+        SurfaceFormat::R32Sint | SurfaceFormat::R32G32Sint | SurfaceFormat::R32G32B32A32Sint => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = u32_at(c) as i32 as f32;
+            }
+        }
+        SurfaceFormat::R32Float | SurfaceFormat::R32G32Float | SurfaceFormat::R32G32B32A32Float => {
+            for (c, value) in rgba.iter_mut().take(channels).enumerate() {
+                *value = f32::from_bits(u32_at(c));
+            }
+        }
+        SurfaceFormat::R4G4Unorm => {
+            let v = u8_at(0) as u32;
+            rgba[0] = unorm(v & 0xF, 4);
+            rgba[1] = unorm(v >> 4, 4);
+        }
+        SurfaceFormat::R5G6B5Unorm => {
+            let v = u16_at(0) as u32;
+            rgba[0] = unorm(v & 0x1F, 5);
+            rgba[1] = unorm((v >> 5) & 0x3F, 6);
+            rgba[2] = unorm(v >> 11, 5);
+        }
+        SurfaceFormat::R5G5B5A1Unorm => {
+            let v = u16_at(0) as u32;
+            rgba[0] = unorm(v & 0x1F, 5);
+            rgba[1] = unorm((v >> 5) & 0x1F, 5);
+            rgba[2] = unorm((v >> 10) & 0x1F, 5);
+            rgba[3] = unorm(v >> 15, 1);
+        }
+        SurfaceFormat::R4G4B4A4Unorm => {
+            let v = u16_at(0) as u32;
+            for (c, value) in rgba.iter_mut().enumerate() {
+                *value = unorm((v >> (c * 4)) & 0xF, 4);
+            }
+        }
+        SurfaceFormat::D24S8Float => {
+            // Depth is treated as 24-bit normalized with stencil in the upper bits.
+            let v = u32_at(0);
+            rgba[0] = unorm(v & 0xFFFFFF, 24);
+            rgba[1] = unorm(v >> 24, 8);
+        }
+        SurfaceFormat::R11G11B10Float => {
+            let v = u32_at(0);
+            rgba[0] = unsigned_float_to_f32(v & 0x7FF, 6);
+            rgba[1] = unsigned_float_to_f32((v >> 11) & 0x7FF, 6);
+            rgba[2] = unsigned_float_to_f32(v >> 22, 5);
+        }
+        SurfaceFormat::R10G10B10A2Unorm => {
+            let v = u32_at(0);
+            rgba[0] = unorm(v & 0x3FF, 10);
+            rgba[1] = unorm((v >> 10) & 0x3FF, 10);
+            rgba[2] = unorm((v >> 20) & 0x3FF, 10);
+            rgba[3] = unorm(v >> 30, 2);
+        }
+        SurfaceFormat::R10G10B10A2Uint => {
+            let v = u32_at(0);
+            rgba[0] = (v & 0x3FF) as f32;
+            rgba[1] = ((v >> 10) & 0x3FF) as f32;
+            rgba[2] = ((v >> 20) & 0x3FF) as f32;
+            rgba[3] = (v >> 30) as f32;
+        }
+        SurfaceFormat::R10G10B10A2Sint => {
+            let v = u32_at(0);
+            rgba[0] = sign_extend(v & 0x3FF, 10) as f32;
+            rgba[1] = sign_extend((v >> 10) & 0x3FF, 10) as f32;
+            rgba[2] = sign_extend((v >> 20) & 0x3FF, 10) as f32;
+            rgba[3] = sign_extend(v >> 30, 2) as f32;
+        }
+        SurfaceFormat::Bc1Unorm
+        | SurfaceFormat::Bc1Srgb
+        | SurfaceFormat::Bc2Unorm
+        | SurfaceFormat::Bc2Srgb
+        | SurfaceFormat::Bc3Unorm
+        | SurfaceFormat::Bc3Srgb
+        | SurfaceFormat::Bc4Unorm
+        | SurfaceFormat::Bc4Snorm
+        | SurfaceFormat::Bc5Unorm
+        | SurfaceFormat::Bc5Snorm => unreachable!("block compressed formats use decode_block"),
+    }
+
+    rgba
+}
+
+fn unorm(value: u32, bits: u32) -> f32 {
+    value as f32 / ((1u32 << bits) - 1) as f32
+}
+
+fn snorm(value: u32, bits: u32) -> f32 {
+    // The most negative value also maps to -1.0.
+    let max = ((1u32 << (bits - 1)) - 1) as f32;
+    (sign_extend(value, bits) as f32 / max).max(-1.0)
+}
+
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((value << shift) as i32) >> shift
+}
+
+pub(crate) fn f16_to_f32(value: u16) -> f32 {
+    let sign = if value & 0x8000 != 0 { -1.0 } else { 1.0 };
+    sign * unsigned_float_to_f32(value as u32 & 0x7FFF, 10)
+}
+
+/// Convert a float with a 5-bit exponent and no sign bit.
+fn unsigned_float_to_f32(value: u32, mantissa_bits: u32) -> f32 {
+    let exponent = value >> mantissa_bits;
+    let mantissa = value & ((1 << mantissa_bits) - 1);
+    match exponent {
+        // Denormals are scaled by 2^(-14 - mantissa_bits).
+        0 => mantissa as f32 * f32::from_bits((127 - 14 - mantissa_bits) << 23),
+        // Infinity or NaN.
+        0x1F => f32::from_bits(0x7F800000 | (mantissa << (23 - mantissa_bits))),
+        _ => f32::from_bits(((exponent + 127 - 15) << 23) | (mantissa << (23 - mantissa_bits))),
+    }
+}
+
+fn decode_block(format: SurfaceFormat, block: &[u8]) -> [[f32; 4]; 16] {
+    let mut pixels = [[0.0, 0.0, 0.0, 1.0]; 16];
+    match format {
+        SurfaceFormat::Bc1Unorm | SurfaceFormat::Bc1Srgb => {
+            let colors = bc1_colors(block[..8].try_into().unwrap(), true);
+            for (pixel, color) in pixels.iter_mut().zip(colors) {
+                *pixel = color.map(|c| unorm(c as u32, 8));
+            }
+        }
+        SurfaceFormat::Bc2Unorm | SurfaceFormat::Bc2Srgb => {
+            let colors = bc1_colors(block[8..16].try_into().unwrap(), false);
+            let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+            for (i, (pixel, color)) in pixels.iter_mut().zip(colors).enumerate() {
+                *pixel = color.map(|c| unorm(c as u32, 8));
+                pixel[3] = unorm(((alpha >> (i * 4)) & 0xF) as u32, 4);
+            }
+        }
+        SurfaceFormat::Bc3Unorm | SurfaceFormat::Bc3Srgb => {
+            let colors = bc1_colors(block[8..16].try_into().unwrap(), false);
+            let alpha = bc4_values(block[..8].try_into().unwrap(), false);
+            for ((pixel, color), a) in pixels.iter_mut().zip(colors).zip(alpha) {
+                *pixel = color.map(|c| unorm(c as u32, 8));
+                pixel[3] = a;
+            }
+        }
+        SurfaceFormat::Bc4Unorm | SurfaceFormat::Bc4Snorm => {
+            let snorm = format == SurfaceFormat::Bc4Snorm;
+            let red = bc4_values(block[..8].try_into().unwrap(), snorm);
+            for (pixel, r) in pixels.iter_mut().zip(red) {
+                pixel[0] = r;
+            }
+        }
+        SurfaceFormat::Bc5Unorm | SurfaceFormat::Bc5Snorm => {
+            let snorm = format == SurfaceFormat::Bc5Snorm;
+            let red = bc4_values(block[..8].try_into().unwrap(), snorm);
+            let green = bc4_values(block[8..16].try_into().unwrap(), snorm);
+            for ((pixel, r), g) in pixels.iter_mut().zip(red).zip(green) {
+                pixel[0] = r;
+                pixel[1] = g;
+            }
+        }
+        _ => unreachable!("uncompressed formats use decode_pixel"),
+    }
+    pixels
+}
+
+pub(crate) fn rgb565_to_rgb8(value: u16) -> [u8; 3] {
+    // BC color endpoints store red in the most significant bits.
+    let r = ((value >> 11) & 0x1F) as u8;
+    let g = ((value >> 5) & 0x3F) as u8;
+    let b = (value & 0x1F) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// Compute the 4 palette colors for a BC1 color block.
+///
+/// BC2 and BC3 always use the 4 color mode without punch through alpha.
+pub(crate) fn bc1_palette(color0: u16, color1: u16, allow_alpha: bool) -> [[u8; 4]; 4] {
+    let c0 = rgb565_to_rgb8(color0);
+    let c1 = rgb565_to_rgb8(color1);
+
+    let mut palette = [[0u8; 4]; 4];
+    for c in 0..3 {
+        let (a, b) = (c0[c] as u32, c1[c] as u32);
+        palette[0][c] = a as u8;
+        palette[1][c] = b as u8;
+        if color0 > color1 || !allow_alpha {
+            palette[2][c] = ((2 * a + b + 1) / 3) as u8;
+            palette[3][c] = ((a + 2 * b + 1) / 3) as u8;
+        } else {
+            palette[2][c] = (a + b).div_ceil(2) as u8;
+            palette[3][c] = 0;
+        }
+    }
+    palette[0][3] = 255;
+    palette[1][3] = 255;
+    palette[2][3] = 255;
+    palette[3][3] = if color0 > color1 || !allow_alpha {
+        255
+    } else {
+        0
+    };
+    palette
+}
+
+fn bc1_colors(block: [u8; 8], allow_alpha: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let palette = bc1_palette(color0, color1, allow_alpha);
+    core::array::from_fn(|i| palette[((indices >> (i * 2)) & 0x3) as usize])
+}
+
+/// Compute the 8 palette values for a BC4 block normalized to `0.0..=1.0` or `-1.0..=1.0`.
+pub(crate) fn bc4_palette(endpoint0: u8, endpoint1: u8, snorm: bool) -> [f32; 8] {
+    let (a, b, min, max) = if snorm {
+        // Both -128 and -127 represent -1.0.
+        let a = (endpoint0 as i8).max(-127) as f32 / 127.0;
+        let b = (endpoint1 as i8).max(-127) as f32 / 127.0;
+        (a, b, -1.0, 1.0)
+    } else {
+        (endpoint0 as f32 / 255.0, endpoint1 as f32 / 255.0, 0.0, 1.0)
+    };
+
+    let mut palette = [a, b, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+    let eight_values = if snorm {
+        endpoint0 as i8 > endpoint1 as i8
+    } else {
+        endpoint0 > endpoint1
+    };
+    if eight_values {
+        for (i, value) in palette.iter_mut().enumerate().skip(2) {
+            let i = i as f32 - 1.0;
+            *value = (a * (7.0 - i) + b * i) / 7.0;
+        }
+    } else {
+        for (i, value) in palette.iter_mut().enumerate().skip(2).take(4) {
+            let i = i as f32 - 1.0;
+            *value = (a * (5.0 - i) + b * i) / 5.0;
+        }
+        palette[6] = min;
+        palette[7] = max;
+    }
+    palette
+}
+
+fn bc4_values(block: [u8; 8], snorm: bool) -> [f32; 16] {
+    let palette = bc4_palette(block[0], block[1], snorm);
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&block[2..]);
+    let indices = u64::from_le_bytes(bits);
+    core::array::from_fn(|i| palette[((indices >> (i * 3)) & 0x7) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_rgba8_r8g8b8a8_unorm() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            data.to_vec(),
+            decode_rgba8(2, 1, 1, SurfaceFormat::R8G8B8A8Unorm, &data).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_rgba8_8bit_integer_formats() {
+        // All 8-bit integer formats store the channel bytes unchanged.
+        let data = [0, 127, 128, 255];
+        for format in [SurfaceFormat::R8G8B8A8Uint, SurfaceFormat::R8G8B8A8Sint] {
+            assert!(!format.is_hdr(), "{format:?}");
+            assert_eq!(data.to_vec(), decode_rgba8(1, 1, 1, format, &data).unwrap());
+        }
+        for format in [
+            SurfaceFormat::R8Uint,
+            SurfaceFormat::R8Sint,
+            SurfaceFormat::R8G8Uint,
+            SurfaceFormat::R8G8Sint,
+        ] {
+            assert!(!format.is_hdr(), "{format:?}");
+        }
+        assert!(SurfaceFormat::R16Uint.is_hdr());
+        assert!(SurfaceFormat::R16G16B16A16Sint.is_hdr());
+    }
+
+    #[test]
+    fn decode_rgba8_packed_formats() {
+        assert_eq!(
+            vec![255, 0, 255, 255],
+            decode_rgba8(
+                1,
+                1,
+                1,
+                SurfaceFormat::R5G6B5Unorm,
+                &0xF81Fu16.to_le_bytes()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            vec![255, 0, 0, 170],
+            decode_rgba8(
+                1,
+                1,
+                1,
+                SurfaceFormat::R10G10B10A2Unorm,
+                &0x800003FFu32.to_le_bytes()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            vec![0, 128, 0, 255],
+            decode_rgba8(1, 1, 1, SurfaceFormat::R8G8Snorm, &[0x81, 0x00]).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_rgba32f_float_formats() {
+        // 1.0, -2.0, 0.5, and 65504.0 as half floats.
+        let data = [0x00, 0x3C, 0x00, 0xC0, 0x00, 0x38, 0xFF, 0x7B];
+        assert_eq!(
+            vec![1.0, -2.0, 0.5, 65504.0],
+            decode_rgba32f(1, 1, 1, SurfaceFormat::R16G16B16A16Float, &data).unwrap()
+        );
+
+        // 1.0 for red and green and 0.5 for blue.
+        let value = 0x3C0 | (0x3C0 << 11) | (0x1C0 << 22);
+        assert_eq!(
+            vec![1.0, 1.0, 0.5, 1.0],
+            decode_rgba32f(
+                1,
+                1,
+                1,
+                SurfaceFormat::R11G11B10Float,
+                &(value as u32).to_le_bytes()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_rgba8_bc1_partial_block() {
+        // Red and blue endpoints with alternating indices.
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0x44, 0x44, 0x44, 0x44];
+        let rgba = decode_rgba8(3, 2, 1, SurfaceFormat::Bc1Unorm, &block).unwrap();
+        assert_eq!(
+            vec![
+                255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, // row 0
+                255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, // row 1
+            ],
+            rgba
+        );
+    }
+
+    #[test]
+    fn decode_rgba32f_bc4_snorm() {
+        // Endpoints -1.0 and 1.0 using the 6 value mode.
+        let block = [0x80, 0x7F, 0b00_111_110, 0, 0, 0, 0, 0];
+        let values = decode_rgba32f(2, 1, 1, SurfaceFormat::Bc4Snorm, &block).unwrap();
+        assert_eq!(vec![-1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0], values);
+    }
+
+    #[test]
+    fn decode_rgba8_not_enough_data() {
+        assert_eq!(
+            Err(SwizzleError::NotEnoughData {
+                expected_size: 16,
+                actual_size: 8
+            }),
+            decode_rgba8(5, 4, 1, SurfaceFormat::Bc1Unorm, &[0u8; 8])
+        );
+    }
+}
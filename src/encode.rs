@@ -0,0 +1,1045 @@
+use crate::decode::{bc1_palette, bc4_palette, unorm_to_u8, Kind};
+use crate::{
+    deswizzled_surface_mipmaps, div_round_up, validate_dimensions, AaMode, SurfaceDim,
+    SurfaceFormat, SwizzleError,
+};
+use alloc::{vec, vec::Vec};
+
+// Encoding produces the deswizzled data for a single mipmap.
+// The output uses the same layout and conventions as decoding.
+
+/// The tradeoff between speed and quality for block compressed formats.
+///
+/// Uncompressed formats always use the nearest value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeQuality {
+    /// Use the bounding box of the block as endpoints.
+    Fast,
+    /// Fit endpoints to the principal axis of the block.
+    #[default]
+    Normal,
+    /// Refine the endpoints and try all block modes.
+    Slow,
+}
+
+/// Encode RGBA8 pixels for a single mipmap to `format`.
+///
+/// The `width` and `height` are the dimensions of the mipmap in pixels.
+/// Partial blocks on the right and bottom edges repeat the edge pixels.
+/// The output contains `depth_or_array_layers` slices
+/// and can be used as input for [swizzle_mipmap](crate::swizzle_mipmap).
+///
+/// This is the inverse of [decode_rgba8](crate::decode_rgba8).
+/// Signed normalized values are remapped from `0..=255` to `-1.0..=1.0`.
+/// Integer formats use the values as is.
+pub fn encode_rgba8(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+    quality: EncodeQuality,
+) -> Result<Vec<u8>, SwizzleError> {
+    let size = encoded_pixel_count(width, height, depth_or_array_layers);
+    if data.len() < size * 4 {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size: size * 4,
+            actual_size: data.len(),
+        });
+    }
+
+    let kind = format.kind();
+    Ok(encode(
+        width,
+        height,
+        depth_or_array_layers,
+        format,
+        quality,
+        |i| core::array::from_fn(|c| kind.u8_to_f32(data[i * 4 + c])),
+    ))
+}
+
+/// Encode RGBA32F pixels for a single mipmap to `format`.
+///
+/// Values outside the range of the format are clamped.
+/// Integer formats round to the nearest integer.
+/// sRGB formats are not converted from linear.
+///
+/// See [encode_rgba8] for details on how the dimensions and data are handled.
+pub fn encode_rgba32f(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    data: &[f32],
+    quality: EncodeQuality,
+) -> Result<Vec<u8>, SwizzleError> {
+    let size = encoded_pixel_count(width, height, depth_or_array_layers);
+    if data.len() < size * 4 {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size: size * 4 * core::mem::size_of::<f32>(),
+            actual_size: core::mem::size_of_val(data),
+        });
+    }
+
+    Ok(encode(
+        width,
+        height,
+        depth_or_array_layers,
+        format,
+        quality,
+        |i| data[i * 4..i * 4 + 4].try_into().unwrap(),
+    ))
+}
+
+/// Encode RGBA8 pixels for all layers and mipmaps of a surface to `format`.
+///
+/// The `data` should use the same layout as [Gx2Surface::deswizzle](crate::Gx2Surface::deswizzle)
+/// like the output of `generate_mipmaps_rgba8`.
+/// The output can be used as input for [swizzle_surface](crate::swizzle_surface).
+///
+/// See [encode_rgba8] for details on how each mipmap is encoded.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_surface_rgba8(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+    quality: EncodeQuality,
+) -> Result<Vec<u8>, SwizzleError> {
+    encode_surface(
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        data,
+        quality,
+        encode_rgba8,
+    )
+}
+
+/// Encode RGBA32F pixels for all layers and mipmaps of a surface to `format`.
+///
+/// See [encode_surface_rgba8] and [encode_rgba32f] for details.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_surface_rgba32f(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    data: &[f32],
+    quality: EncodeQuality,
+) -> Result<Vec<u8>, SwizzleError> {
+    encode_surface(
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        data,
+        quality,
+        encode_rgba32f,
+    )
+}
+
+type EncodeFn<T> =
+    fn(u32, u32, u32, SurfaceFormat, &[T], EncodeQuality) -> Result<Vec<u8>, SwizzleError>;
+
+#[allow(clippy::too_many_arguments)]
+fn encode_surface<T>(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    data: &[T],
+    quality: EncodeQuality,
+    encode_mipmap: EncodeFn<T>,
+) -> Result<Vec<u8>, SwizzleError> {
+    validate_dimensions(
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        AaMode::X1,
+        1,
+    )?;
+    if width == 0 || height == 0 || depth_or_array_layers == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mipmaps =
+        deswizzled_surface_mipmaps(dim, width, height, depth_or_array_layers, mipmap_count);
+    let expected_len: usize = mipmaps
+        .iter()
+        .map(|(w, h, d)| encoded_pixel_count(*w, *h, *d) * 4)
+        .sum();
+    if data.len() < expected_len {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size: expected_len * core::mem::size_of::<T>(),
+            actual_size: core::mem::size_of_val(data),
+        });
+    }
+
+    let mut output = Vec::new();
+    let mut offset = 0;
+    for (width, height, depth) in mipmaps {
+        let len = encoded_pixel_count(width, height, depth) * 4;
+        let encoded = encode_mipmap(
+            width,
+            height,
+            depth,
+            format,
+            &data[offset..offset + len],
+            quality,
+        )?;
+        output.extend_from_slice(&encoded);
+        offset += len;
+    }
+    Ok(output)
+}
+
+fn encoded_pixel_count(width: u32, height: u32, depth_or_array_layers: u32) -> usize {
+    width as usize * height as usize * depth_or_array_layers as usize
+}
+
+impl Kind {
+    fn u8_to_f32(self, value: u8) -> f32 {
+        match self {
+            Kind::Unorm | Kind::Float => value as f32 / 255.0,
+            Kind::Snorm => value as f32 / 255.0 * 2.0 - 1.0,
+            Kind::Uint => value as f32,
+            // Decoding to RGBA8 keeps the two's complement bits for signed values.
+            Kind::Sint => value as i8 as f32,
+        }
+    }
+}
+
+fn encode(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    quality: EncodeQuality,
+    read_pixel: impl Fn(usize) -> [f32; 4],
+) -> Vec<u8> {
+    let (block_width, block_height) = format.block_dim();
+    let bytes_per_block = format.bytes_per_pixel() as usize;
+
+    let width_blocks = div_round_up(width, block_width) as usize;
+    let height_blocks = div_round_up(height, block_height) as usize;
+
+    let mut output =
+        vec![0u8; width_blocks * height_blocks * depth_or_array_layers as usize * bytes_per_block];
+
+    let (width, height) = (width as usize, height as usize);
+    let (block_width, block_height) = (block_width as usize, block_height as usize);
+
+    for z in 0..depth_or_array_layers as usize {
+        for by in 0..height_blocks {
+            for bx in 0..width_blocks {
+                let offset = ((z * height_blocks + by) * width_blocks + bx) * bytes_per_block;
+                let block = &mut output[offset..offset + bytes_per_block];
+
+                if block_width == 1 && block_height == 1 {
+                    let i = (z * height + by) * width + bx;
+                    encode_pixel(format, read_pixel(i), block);
+                } else {
+                    // Clamp coordinates to repeat edge pixels for partial blocks.
+                    let pixels = core::array::from_fn(|i| {
+                        let x = (bx * block_width + i % block_width).min(width - 1);
+                        let y = (by * block_height + i / block_width).min(height - 1);
+                        read_pixel((z * height + y) * width + x)
+                    });
+                    encode_block(format, &pixels, quality, block);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn encode_pixel(format: SurfaceFormat, rgba: [f32; 4], bytes: &mut [u8]) {
+    let channels = format.channel_count();
+
+    match format {
+        SurfaceFormat::R8Unorm
+        | SurfaceFormat::R8G8Unorm
+        | SurfaceFormat::R8G8B8A8Unorm
+        | SurfaceFormat::R8G8B8A8Srgb => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                bytes[c] = unorm(*value, 8) as u8;
+            }
+        }
+        SurfaceFormat::R8Snorm | SurfaceFormat::R8G8Snorm | SurfaceFormat::R8G8B8A8Snorm => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                bytes[c] = snorm(*value, 8) as u8;
+            }
+        }
+        SurfaceFormat::R8Uint | SurfaceFormat::R8G8Uint | SurfaceFormat::R8G8B8A8Uint => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                bytes[c] = integer(*value, 0.0, u8::MAX as f32) as u8;
+            }
+        }
+        SurfaceFormat::R8Sint | SurfaceFormat::R8G8Sint | SurfaceFormat::R8G8B8A8Sint => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                bytes[c] = integer(*value, i8::MIN as f32, i8::MAX as f32) as u8;
+            }
+        }
+        SurfaceFormat::R16Unorm | SurfaceFormat::R16G16Unorm | SurfaceFormat::R16G16B16A16Unorm => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                write_u16(bytes, c, unorm(*value, 16) as u16);
+            }
+        }
+        SurfaceFormat::R16Snorm | SurfaceFormat::R16G16Snorm | SurfaceFormat::R16G16B16A16Snorm => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                write_u16(bytes, c, snorm(*value, 16) as u16);
+            }
+        }
+        SurfaceFormat::R16Uint | SurfaceFormat::R16G16Uint | SurfaceFormat::R16G16B16A16Uint => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                write_u16(bytes, c, integer(*value, 0.0, u16::MAX as f32) as u16);
+            }
+        }
+        SurfaceFormat::R16Sint | SurfaceFormat::R16G16Sint | SurfaceFormat::R16G16B16A16Sint => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                let value = integer(*value, i16::MIN as f32, i16::MAX as f32);
+                write_u16(bytes, c, value as u16);
+            }
+        }
+        SurfaceFormat::R16Float | SurfaceFormat::R16G16Float | SurfaceFormat::R16G16B16A16Float => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                write_u16(bytes, c, f32_to_f16(*value));
+            }
+        }
+        SurfaceFormat::R32Uint | SurfaceFormat::R32G32Uint | SurfaceFormat::R32G32B32A32Uint => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                // Float to int casts saturate, so only negative values need clamping.
+                write_u32(bytes, c, (value.max(0.0) + 0.5) as u32);
+            }
+        }
+        SurfaceFormat::R32Sint | SurfaceFormat::R32G32Sint | SurfaceFormat::R32G32B32A32Sint => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                write_u32(bytes, c, round(*value) as u32);
+            }
+        }
+        SurfaceFormat::R32Float | SurfaceFormat::R32G32Float | SurfaceFormat::R32G32B32A32Float => {
+            for (c, value) in rgba.iter().take(channels).enumerate() {
+                write_u32(bytes, c, value.to_bits());
+            }
+        }
+        SurfaceFormat::R4G4Unorm => {
+            bytes[0] = (unorm(rgba[0], 4) | (unorm(rgba[1], 4) << 4)) as u8;
+        }
+        SurfaceFormat::R5G6B5Unorm => {
+            let v = unorm(rgba[0], 5) | (unorm(rgba[1], 6) << 5) | (unorm(rgba[2], 5) << 11);
+            write_u16(bytes, 0, v as u16);
+        }
+        SurfaceFormat::R5G5B5A1Unorm => {
+            let v = unorm(rgba[0], 5)
+                | (unorm(rgba[1], 5) << 5)
+                | (unorm(rgba[2], 5) << 10)
+                | (unorm(rgba[3], 1) << 15);
+            write_u16(bytes, 0, v as u16);
+        }
+        SurfaceFormat::R4G4B4A4Unorm => {
+            let v = (0..4).fold(0, |v, c| v | (unorm(rgba[c], 4) << (c * 4)));
+            write_u16(bytes, 0, v as u16);
+        }
+        SurfaceFormat::D24S8Float => {
+            // Depth is treated as 24-bit normalized with stencil in the upper bits.
+            write_u32(bytes, 0, unorm(rgba[0], 24) | (unorm(rgba[1], 8) << 24));
+        }
+        SurfaceFormat::R11G11B10Float => {
+            let v = f32_to_unsigned_float(rgba[0], 6)
+                | (f32_to_unsigned_float(rgba[1], 6) << 11)
+                | (f32_to_unsigned_float(rgba[2], 5) << 22);
+            write_u32(bytes, 0, v);
+        }
+        SurfaceFormat::R10G10B10A2Unorm => {
+            let v = unorm(rgba[0], 10)
+                | (unorm(rgba[1], 10) << 10)
+                | (unorm(rgba[2], 10) << 20)
+                | (unorm(rgba[3], 2) << 30);
+            write_u32(bytes, 0, v);
+        }
+        SurfaceFormat::R10G10B10A2Uint => {
+            let v = integer(rgba[0], 0.0, 1023.0) as u32
+                | ((integer(rgba[1], 0.0, 1023.0) as u32) << 10)
+                | ((integer(rgba[2], 0.0, 1023.0) as u32) << 20)
+                | ((integer(rgba[3], 0.0, 3.0) as u32) << 30);
+            write_u32(bytes, 0, v);
+        }
+        SurfaceFormat::R10G10B10A2Sint => {
+            let bits = |value: f32, bits: u32| {
+                let max = ((1 << (bits - 1)) - 1) as f32;
+                (integer(value, -max - 1.0, max) as u32) & ((1 << bits) - 1)
+            };
+            let v = bits(rgba[0], 10)
+                | (bits(rgba[1], 10) << 10)
+                | (bits(rgba[2], 10) << 20)
+                | (bits(rgba[3], 2) << 30);
+            write_u32(bytes, 0, v);
+        }
+        SurfaceFormat::Bc1Unorm
+        | SurfaceFormat::Bc1Srgb
+        | SurfaceFormat::Bc2Unorm
+        | SurfaceFormat::Bc2Srgb
+        | SurfaceFormat::Bc3Unorm
+        | SurfaceFormat::Bc3Srgb
+        | SurfaceFormat::Bc4Unorm
+        | SurfaceFormat::Bc4Snorm
+        | SurfaceFormat::Bc5Unorm
+        | SurfaceFormat::Bc5Snorm => unreachable!("block compressed formats use encode_block"),
+    }
+}
+
+fn write_u16(bytes: &mut [u8], i: usize, value: u16) {
+    bytes[i * 2..i * 2 + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(bytes: &mut [u8], i: usize, value: u32) {
+    bytes[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Round to the nearest integer with ties away from zero.
+fn round(value: f32) -> i32 {
+    // Float to int casts truncate and convert NaN to 0.
+    if value >= 0.0 {
+        (value + 0.5) as i32
+    } else {
+        (value - 0.5) as i32
+    }
+}
+
+fn unorm(value: f32, bits: u32) -> u32 {
+    let max = ((1u64 << bits) - 1) as f32;
+    round(value.clamp(0.0, 1.0) * max) as u32
+}
+
+fn snorm(value: f32, bits: u32) -> i32 {
+    let max = ((1u32 << (bits - 1)) - 1) as f32;
+    round(value.clamp(-1.0, 1.0) * max)
+}
+
+fn integer(value: f32, min: f32, max: f32) -> i32 {
+    round(value.clamp(min, max))
+}
+
+fn f32_to_f16(value: f32) -> u16 {
+    let sign = ((value.to_bits() >> 16) & 0x8000) as u16;
+    let magnitude = f32::from_bits(value.to_bits() & 0x7FFFFFFF);
+    sign | f32_to_unsigned_float(magnitude, 10) as u16
+}
+
+/// Convert to a float with a 5-bit exponent and no sign bit.
+/// Negative values are clamped to 0.
+fn f32_to_unsigned_float(value: f32, mantissa_bits: u32) -> u32 {
+    if value.is_nan() {
+        return (0x1F << mantissa_bits) | (1 << (mantissa_bits - 1));
+    }
+    if value <= 0.0 {
+        return 0;
+    }
+
+    let bits = value.to_bits();
+    let exponent = (bits >> 23) as i32 - 127 + 15;
+    let mantissa = bits & 0x7FFFFF;
+    if exponent >= 0x1F {
+        // Infinity.
+        0x1F << mantissa_bits
+    } else if exponent <= 0 {
+        // Denormals include the implicit leading 1 in the mantissa.
+        let shift = (24 - mantissa_bits) as i32 - exponent;
+        if shift >= 32 {
+            0
+        } else {
+            round_shift(mantissa | 0x800000, shift as u32)
+        }
+    } else {
+        // Rounding may carry into the exponent as expected.
+        round_shift(((exponent as u32) << 23) | mantissa, 23 - mantissa_bits)
+    }
+}
+
+/// Shift right with round to nearest and ties to even.
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let half = 1 << (shift - 1);
+    let remainder = value & ((1 << shift) - 1);
+    let result = value >> shift;
+    if remainder > half || (remainder == half && result & 1 == 1) {
+        result + 1
+    } else {
+        result
+    }
+}
+
+fn encode_block(
+    format: SurfaceFormat,
+    pixels: &[[f32; 4]; 16],
+    quality: EncodeQuality,
+    block: &mut [u8],
+) {
+    let colors = || pixels.map(|p| p.map(unorm_to_u8));
+    let channel = |c: usize| pixels.map(|p| p[c]);
+
+    match format {
+        SurfaceFormat::Bc1Unorm | SurfaceFormat::Bc1Srgb => {
+            block.copy_from_slice(&bc1_block(&colors(), true, quality));
+        }
+        SurfaceFormat::Bc2Unorm | SurfaceFormat::Bc2Srgb => {
+            let alpha = pixels
+                .iter()
+                .enumerate()
+                .fold(0u64, |a, (i, p)| a | ((unorm(p[3], 4) as u64) << (i * 4)));
+            block[..8].copy_from_slice(&alpha.to_le_bytes());
+            block[8..].copy_from_slice(&bc1_block(&colors(), false, quality));
+        }
+        SurfaceFormat::Bc3Unorm | SurfaceFormat::Bc3Srgb => {
+            block[..8].copy_from_slice(&bc4_block(&channel(3), false, quality));
+            block[8..].copy_from_slice(&bc1_block(&colors(), false, quality));
+        }
+        SurfaceFormat::Bc4Unorm | SurfaceFormat::Bc4Snorm => {
+            let snorm = format == SurfaceFormat::Bc4Snorm;
+            block.copy_from_slice(&bc4_block(&channel(0), snorm, quality));
+        }
+        SurfaceFormat::Bc5Unorm | SurfaceFormat::Bc5Snorm => {
+            let snorm = format == SurfaceFormat::Bc5Snorm;
+            block[..8].copy_from_slice(&bc4_block(&channel(0), snorm, quality));
+            block[8..].copy_from_slice(&bc4_block(&channel(1), snorm, quality));
+        }
+        _ => unreachable!("uncompressed formats use encode_pixel"),
+    }
+}
+
+/// Encode a BC1 color block.
+///
+/// Pixels with alpha below 128 are transparent if `allow_alpha` is `true`.
+/// BC2 and BC3 always use the 4 color mode and ignore alpha.
+fn bc1_block(colors: &[[u8; 4]; 16], allow_alpha: bool, quality: EncodeQuality) -> [u8; 8] {
+    let transparent = colors.map(|c| allow_alpha && c[3] < 128);
+
+    // Fit the endpoints only to pixels that will be visible.
+    let opaque: Vec<[f32; 3]> = colors
+        .iter()
+        .zip(transparent)
+        .filter(|(_, t)| !t)
+        .map(|(c, _)| [c[0] as f32, c[1] as f32, c[2] as f32])
+        .collect();
+    if opaque.is_empty() {
+        // All pixels use the transparent index 3 in the 3 color mode.
+        return [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+    }
+
+    let (start, end) = match quality {
+        EncodeQuality::Fast => bounding_box_endpoints(&opaque),
+        EncodeQuality::Normal | EncodeQuality::Slow => principal_axis_endpoints(&opaque),
+    };
+
+    let has_transparency = transparent.contains(&true);
+    let mut best = bc1_fit(colors, &transparent, start, end, !has_transparency);
+
+    if quality == EncodeQuality::Slow {
+        let mut candidates = Vec::new();
+        if allow_alpha && !has_transparency {
+            // The 3 color mode can sometimes represent the block more accurately.
+            candidates.push(bc1_fit(colors, &transparent, start, end, false));
+        }
+        for (start, end, four_colors) in [
+            refine_endpoints(&opaque, &best.1, best.2),
+            refine_endpoints(&opaque, &best.1, !best.2),
+        ] {
+            // Transparent pixels require the 3 color mode.
+            let valid = if four_colors {
+                !has_transparency
+            } else {
+                allow_alpha
+            };
+            if valid {
+                candidates.push(bc1_fit(colors, &transparent, start, end, four_colors));
+            }
+        }
+        for candidate in candidates {
+            if candidate.0 < best.0 {
+                best = candidate;
+            }
+        }
+    }
+
+    best.3
+}
+
+/// Quantize endpoints and select indices for the 4 color or 3 color mode.
+/// Returns the error, opaque palette weights, mode, and encoded block.
+fn bc1_fit(
+    colors: &[[u8; 4]; 16],
+    transparent: &[bool; 16],
+    start: [f32; 3],
+    end: [f32; 3],
+    four_colors: bool,
+) -> (u32, Vec<u8>, bool, [u8; 8]) {
+    let mut color0 = rgb_to_rgb565(start);
+    let mut color1 = rgb_to_rgb565(end);
+    // The mode is determined by the endpoint order.
+    if (four_colors && color0 < color1) || (!four_colors && color0 > color1) {
+        core::mem::swap(&mut color0, &mut color1);
+    }
+
+    let palette = bc1_palette(color0, color1, true);
+    // Equal endpoints select the 3 color mode, so index 3 would be transparent.
+    let color_count = if color0 > color1 { 4 } else { 3 };
+
+    let mut error = 0;
+    let mut indices = 0u32;
+    let mut selected = Vec::new();
+    for (i, (color, transparent)) in colors.iter().zip(transparent).enumerate() {
+        let index = if *transparent {
+            3
+        } else {
+            let (index, e) = (0..color_count)
+                .map(|p| (p, color_distance(color, &palette[p])))
+                .min_by_key(|(_, e)| *e)
+                .unwrap();
+            error += e;
+            selected.push(index as u8);
+            index
+        };
+        indices |= (index as u32) << (i * 2);
+    }
+
+    let mut block = [0u8; 8];
+    block[..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&color1.to_le_bytes());
+    block[4..].copy_from_slice(&indices.to_le_bytes());
+    (error, selected, color0 > color1, block)
+}
+
+fn color_distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    (0..3)
+        .map(|c| {
+            let d = a[c] as i32 - b[c] as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
+
+fn rgb_to_rgb565(rgb: [f32; 3]) -> u16 {
+    // BC color endpoints store red in the most significant bits.
+    let r = unorm(rgb[0] / 255.0, 5) as u16;
+    let g = unorm(rgb[1] / 255.0, 6) as u16;
+    let b = unorm(rgb[2] / 255.0, 5) as u16;
+    (r << 11) | (g << 5) | b
+}
+
+fn bounding_box_endpoints(colors: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for color in colors {
+        for c in 0..3 {
+            min[c] = min[c].min(color[c]);
+            max[c] = max[c].max(color[c]);
+        }
+    }
+
+    // Pick the box diagonal using the sign of the covariance with the widest channel.
+    let widest = (0..3)
+        .max_by(|a, b| (max[*a] - min[*a]).total_cmp(&(max[*b] - min[*b])))
+        .unwrap();
+    let center: [f32; 3] = core::array::from_fn(|c| (min[c] + max[c]) / 2.0);
+    for c in 0..3 {
+        let covariance: f32 = colors
+            .iter()
+            .map(|color| (color[c] - center[c]) * (color[widest] - center[widest]))
+            .sum();
+        if covariance < 0.0 {
+            core::mem::swap(&mut min[c], &mut max[c]);
+        }
+    }
+    (max, min)
+}
+
+fn principal_axis_endpoints(colors: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let count = colors.len() as f32;
+    let mut mean = [0.0; 3];
+    for color in colors {
+        for c in 0..3 {
+            mean[c] += color[c] / count;
+        }
+    }
+
+    let mut covariance = [[0.0f32; 3]; 3];
+    for color in colors {
+        let d = [color[0] - mean[0], color[1] - mean[1], color[2] - mean[2]];
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j];
+            }
+        }
+    }
+
+    // Power iteration finds the dominant eigenvector.
+    // The box diagonal is a good initial guess and avoids starting orthogonal to the axis.
+    // Normalizing by the largest component avoids needing a square root.
+    let (start, end) = bounding_box_endpoints(colors);
+    let mut axis: [f32; 3] = core::array::from_fn(|c| start[c] - end[c]);
+    for _ in 0..8 {
+        let next: [f32; 3] = core::array::from_fn(|i| {
+            covariance[i][0] * axis[0] + covariance[i][1] * axis[1] + covariance[i][2] * axis[2]
+        });
+        let scale = next.iter().fold(0.0f32, |m, v| m.max(v.max(-v)));
+        if scale == 0.0 {
+            // All colors are identical.
+            return (start, end);
+        }
+        axis = next.map(|v| v / scale);
+    }
+
+    let length_squared = axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2];
+    let mut min_t = f32::MAX;
+    let mut max_t = f32::MIN;
+    for color in colors {
+        let t = (0..3).map(|c| (color[c] - mean[c]) * axis[c]).sum::<f32>() / length_squared;
+        min_t = min_t.min(t);
+        max_t = max_t.max(t);
+    }
+
+    let point = |t: f32| core::array::from_fn(|c| (mean[c] + t * axis[c]).clamp(0.0, 255.0));
+    (point(max_t), point(min_t))
+}
+
+/// Solve for endpoints minimizing the squared error for the selected palette indices.
+fn refine_endpoints(
+    colors: &[[f32; 3]],
+    indices: &[u8],
+    four_colors: bool,
+) -> ([f32; 3], [f32; 3], bool) {
+    // Weights for endpoint 0 for each palette index.
+    let weights: [f32; 4] = if four_colors {
+        [1.0, 0.0, 2.0 / 3.0, 1.0 / 3.0]
+    } else {
+        [1.0, 0.0, 0.5, 0.0]
+    };
+
+    let mut aa = 0.0;
+    let mut ab = 0.0;
+    let mut bb = 0.0;
+    let mut ax = [0.0f32; 3];
+    let mut bx = [0.0f32; 3];
+    for (color, index) in colors.iter().zip(indices) {
+        // The 3 color mode can't use index 3 for opaque pixels.
+        let index = if four_colors { *index } else { (*index).min(2) };
+        let a = weights[index as usize];
+        let b = 1.0 - a;
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        for c in 0..3 {
+            ax[c] += a * color[c];
+            bx[c] += b * color[c];
+        }
+    }
+
+    let determinant = aa * bb - ab * ab;
+    if determinant == 0.0 {
+        // All pixels use the same weight, so fall back to the principal axis.
+        let (start, end) = principal_axis_endpoints(colors);
+        return (start, end, four_colors);
+    }
+
+    let start =
+        core::array::from_fn(|c| ((ax[c] * bb - bx[c] * ab) / determinant).clamp(0.0, 255.0));
+    let end = core::array::from_fn(|c| ((bx[c] * aa - ax[c] * ab) / determinant).clamp(0.0, 255.0));
+    (start, end, four_colors)
+}
+
+/// Encode a BC4 block from values in `0.0..=1.0` or `-1.0..=1.0`.
+fn bc4_block(values: &[f32; 16], snorm: bool, quality: EncodeQuality) -> [u8; 8] {
+    let (range_min, range_max) = if snorm { (-1.0, 1.0) } else { (0.0, 1.0) };
+    let values = values.map(|v| {
+        if v.is_nan() {
+            0.0
+        } else {
+            v.clamp(range_min, range_max)
+        }
+    });
+
+    let min = values.iter().copied().fold(range_max, f32::min);
+    let max = values.iter().copied().fold(range_min, f32::max);
+
+    let quantize = |v: f32| {
+        if snorm {
+            // Avoid -128 since it also represents -1.0.
+            self::snorm(v, 8) as i8 as u8
+        } else {
+            unorm(v, 8) as u8
+        }
+    };
+    let dequantize = |e: u8| {
+        if snorm {
+            e as i8 as i32
+        } else {
+            e as i32
+        }
+    };
+
+    // The 8 value mode uses the first endpoint as the larger value.
+    let mut best = bc4_fit(&values, quantize(max), quantize(min), snorm);
+
+    if quality != EncodeQuality::Fast {
+        // The 6 value mode has exact values for the range limits.
+        // Fit the remaining values if the block contains the limits.
+        let inner = values
+            .iter()
+            .filter(|v| **v != range_min && **v != range_max);
+        let inner_min = inner.clone().copied().fold(range_max, f32::min);
+        let inner_max = inner.copied().fold(range_min, f32::max);
+        if inner_min <= inner_max {
+            let candidate = bc4_fit(&values, quantize(inner_min), quantize(inner_max), snorm);
+            if candidate.0 < best.0 {
+                best = candidate;
+            }
+        }
+    }
+
+    if quality == EncodeQuality::Slow {
+        // Search nearby endpoints since rounding isn't always optimal.
+        let (e0, e1) = (best.1[0], best.1[1]);
+        for d0 in -2..=2 {
+            for d1 in -2..=2 {
+                let offset = |e: u8, d: i32| {
+                    let value = dequantize(e) + d;
+                    if snorm {
+                        value.clamp(-127, 127) as i8 as u8
+                    } else {
+                        value.clamp(0, 255) as u8
+                    }
+                };
+                let candidate = bc4_fit(&values, offset(e0, d0), offset(e1, d1), snorm);
+                if candidate.0 < best.0 {
+                    best = candidate;
+                }
+            }
+        }
+    }
+
+    best.1
+}
+
+/// Select indices for the given endpoints.
+/// Returns the squared error and encoded block.
+fn bc4_fit(values: &[f32; 16], endpoint0: u8, endpoint1: u8, snorm: bool) -> (f32, [u8; 8]) {
+    let palette = bc4_palette(endpoint0, endpoint1, snorm);
+
+    let mut error = 0.0;
+    let mut indices = 0u64;
+    for (i, value) in values.iter().enumerate() {
+        let (index, e) = palette
+            .iter()
+            .map(|p| (p - value) * (p - value))
+            .enumerate()
+            .fold(
+                (0, f32::MAX),
+                |best, (j, e)| if e < best.1 { (j, e) } else { best },
+            );
+        error += e;
+        indices |= (index as u64) << (i * 3);
+    }
+
+    let mut block = [0u8; 8];
+    block[0] = endpoint0;
+    block[1] = endpoint1;
+    block[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+    (error, block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_rgba32f, decode_rgba8};
+
+    const QUALITIES: [EncodeQuality; 3] = [
+        EncodeQuality::Fast,
+        EncodeQuality::Normal,
+        EncodeQuality::Slow,
+    ];
+
+    #[test]
+    fn encode_rgba8_r8g8b8a8_unorm() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            data.to_vec(),
+            encode_rgba8(
+                2,
+                1,
+                1,
+                SurfaceFormat::R8G8B8A8Unorm,
+                &data,
+                EncodeQuality::Normal
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn encode_rgba8_packed_formats() {
+        let encode = |format, rgba: &[u8]| {
+            encode_rgba8(1, 1, 1, format, rgba, EncodeQuality::Normal).unwrap()
+        };
+        assert_eq!(
+            0xF81Fu16.to_le_bytes().to_vec(),
+            encode(SurfaceFormat::R5G6B5Unorm, &[255, 0, 255, 255])
+        );
+        assert_eq!(
+            0x800003FFu32.to_le_bytes().to_vec(),
+            encode(SurfaceFormat::R10G10B10A2Unorm, &[255, 0, 0, 170])
+        );
+        assert_eq!(
+            vec![0x81, 0x00],
+            encode(SurfaceFormat::R8G8Snorm, &[0, 128, 0, 255])
+        );
+    }
+
+    #[test]
+    fn encode_rgba8_8bit_integer_formats() {
+        // The channel bytes round trip for both unsigned and signed formats.
+        let data = [0, 127, 128, 255];
+        for format in [SurfaceFormat::R8G8B8A8Uint, SurfaceFormat::R8G8B8A8Sint] {
+            let encoded = encode_rgba8(1, 1, 1, format, &data, EncodeQuality::Normal).unwrap();
+            assert_eq!(data.to_vec(), encoded, "{format:?}");
+            assert_eq!(
+                data.to_vec(),
+                decode_rgba8(1, 1, 1, format, &encoded).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn encode_rgba32f_float_formats() {
+        // 1.0, -2.0, 0.5, and 65504.0 as half floats.
+        assert_eq!(
+            vec![0x00, 0x3C, 0x00, 0xC0, 0x00, 0x38, 0xFF, 0x7B],
+            encode_rgba32f(
+                1,
+                1,
+                1,
+                SurfaceFormat::R16G16B16A16Float,
+                &[1.0, -2.0, 0.5, 65504.0],
+                EncodeQuality::Normal
+            )
+            .unwrap()
+        );
+
+        // Denormals and values too large for half floats.
+        assert_eq!(0x0001, f32_to_f16(f32::from_bits((127 - 24) << 23)));
+        assert_eq!(0x7C00, f32_to_f16(1e6));
+        assert_eq!(0xFC00, f32_to_f16(f32::NEG_INFINITY));
+
+        // 1.0 for red and green and 0.5 for blue.
+        let value = 0x3C0 | (0x3C0 << 11) | (0x1C0 << 22);
+        assert_eq!(
+            (value as u32).to_le_bytes().to_vec(),
+            encode_rgba32f(
+                1,
+                1,
+                1,
+                SurfaceFormat::R11G11B10Float,
+                &[1.0, 1.0, 0.5, 1.0],
+                EncodeQuality::Normal
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn encode_rgba8_bc1_round_trip() {
+        // Red and blue alternating with a partial block.
+        let mut rgba = Vec::new();
+        for _ in 0..2 {
+            rgba.extend_from_slice(&[255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255]);
+        }
+        for quality in QUALITIES {
+            let bc1 = encode_rgba8(3, 2, 1, SurfaceFormat::Bc1Unorm, &rgba, quality).unwrap();
+            assert_eq!(8, bc1.len());
+            assert_eq!(
+                rgba,
+                decode_rgba8(3, 2, 1, SurfaceFormat::Bc1Unorm, &bc1).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn encode_rgba8_bc1_transparency() {
+        let rgba = [0, 255, 0, 255, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0];
+        for quality in QUALITIES {
+            let bc1 = encode_rgba8(2, 2, 1, SurfaceFormat::Bc1Unorm, &rgba, quality).unwrap();
+            let decoded = decode_rgba8(2, 2, 1, SurfaceFormat::Bc1Unorm, &bc1).unwrap();
+            assert_eq!(&rgba[..4], &decoded[..4]);
+            assert_eq!(0, decoded[7]);
+            assert_eq!(&rgba[8..12], &decoded[8..12]);
+            assert_eq!(0, decoded[15]);
+        }
+    }
+
+    #[test]
+    fn encode_rgba32f_bc4_bc5_round_trip() {
+        // Gradients decode to similar values.
+        let values: Vec<_> = (0..16)
+            .flat_map(|i| [i as f32 / 15.0, 1.0 - i as f32 / 15.0, 0.0, 1.0])
+            .collect();
+        for quality in QUALITIES {
+            let bc5 = encode_rgba32f(4, 4, 1, SurfaceFormat::Bc5Unorm, &values, quality).unwrap();
+            let decoded = decode_rgba32f(4, 4, 1, SurfaceFormat::Bc5Unorm, &bc5).unwrap();
+            for (a, b) in values.iter().zip(&decoded) {
+                // The 8 value mode has steps of 1/7.
+                assert!((a - b).abs() <= 1.0 / 14.0, "{a} != {b}");
+            }
+        }
+
+        // The 6 value mode represents the range limits exactly.
+        let values: Vec<_> = [-1.0, 0.25, 0.3, 1.0]
+            .iter()
+            .flat_map(|v| [*v, 0.0, 0.0, 1.0])
+            .collect();
+        let bc4 = encode_rgba32f(
+            4,
+            1,
+            1,
+            SurfaceFormat::Bc4Snorm,
+            &values,
+            EncodeQuality::Normal,
+        )
+        .unwrap();
+        let decoded = decode_rgba32f(4, 1, 1, SurfaceFormat::Bc4Snorm, &bc4).unwrap();
+        assert_eq!(-1.0, decoded[0]);
+        assert_eq!(1.0, decoded[12]);
+    }
+
+    #[test]
+    fn encode_rgba8_not_enough_data() {
+        assert_eq!(
+            Err(SwizzleError::NotEnoughData {
+                expected_size: 80,
+                actual_size: 64
+            }),
+            encode_rgba8(
+                5,
+                4,
+                1,
+                SurfaceFormat::Bc1Unorm,
+                &[0u8; 64],
+                EncodeQuality::Normal
+            )
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    decode_rgba8, deswizzle_mipmap, surface_layout, swizzled_mipmap_size, AaMode, HwConfig,
+    SurfaceDim, SurfaceFormat, TileMode,
+};
+use alloc::vec::Vec;
+
+/// Tiling parameters and score for a possible layout found by [guess_tiling].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilingCandidate {
+    pub tile_mode: TileMode,
+    pub pitch: u32,
+    pub swizzle: u32,
+    /// The sum of absolute differences between neighboring bytes in the deswizzled data.
+    /// Block compressed formats compare the decoded RGBA8 pixels instead.
+    /// Lower scores are more likely to be correct.
+    pub score: u64,
+}
+
+/// Find the most likely tile mode, pitch, and swizzle for the base level of a surface
+/// with unknown tiling parameters.
+///
+/// Candidates are limited to parameters with a tiled size that fits in `data`
+/// and uses at least half of `data` to allow for padding or additional mipmaps.
+/// The result contains at most `max_candidates` candidates sorted by increasing score.
+/// The score assumes neighboring texels in the image have similar values.
+#[allow(clippy::too_many_arguments)]
+pub fn guess_tiling(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    data: &[u8],
+    max_candidates: usize,
+) -> Vec<TilingCandidate> {
+    // Deswizzle using the dimensions in blocks for compressed formats.
+    let (block_width, block_height) = format.block_dim();
+    let width_in_blocks = width.div_ceil(block_width);
+    let height_in_blocks = height.div_ceil(block_height);
+    let bytes_per_pixel = format.bytes_per_pixel();
+
+    let mut candidates = Vec::new();
+    for tile_mode in TileMode::supported() {
+        for pitch in pitch_candidates(dim, width, height, depth_or_array_layers, format, tile_mode)
+        {
+            // The swizzle only affects macro tiled modes.
+            let swizzle_count = if tile_mode.is_macro_tiled() { 8 } else { 1 };
+            for swizzle in (0..swizzle_count).map(|i| i << 8) {
+                let Ok(size) = swizzled_mipmap_size(
+                    width_in_blocks,
+                    height_in_blocks,
+                    depth_or_array_layers,
+                    swizzle,
+                    pitch,
+                    tile_mode,
+                    bytes_per_pixel,
+                    AaMode::X1,
+                    &HwConfig::LATTE,
+                ) else {
+                    continue;
+                };
+                if size == 0 || size > data.len() || data.len() > size * 2 {
+                    continue;
+                }
+
+                if let Ok(deswizzled) = deswizzle_mipmap(
+                    width_in_blocks,
+                    height_in_blocks,
+                    depth_or_array_layers,
+                    data,
+                    swizzle,
+                    pitch,
+                    tile_mode,
+                    bytes_per_pixel,
+                    AaMode::X1,
+                ) {
+                    candidates.push(TilingCandidate {
+                        tile_mode,
+                        pitch,
+                        swizzle,
+                        score: score(&deswizzled, width, height, depth_or_array_layers, format),
+                    });
+                }
+            }
+        }
+    }
+
+    // Use a stable sort to prefer earlier candidates for equal scores.
+    candidates.sort_by_key(|c| c.score);
+    candidates.truncate(max_candidates);
+    candidates
+}
+
+fn pitch_candidates(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    tile_mode: TileMode,
+) -> Vec<u32> {
+    let mut pitches = Vec::new();
+    if let Ok(layout) = surface_layout(
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        1,
+        format,
+        AaMode::X1,
+        tile_mode,
+        0,
+    ) {
+        pitches.push(layout.pitch);
+    }
+
+    // Also check common alignments in case the pitch was padded differently.
+    let width = width.div_ceil(format.block_dim().0);
+    pitches.extend((3..=8).map(|i| width.next_multiple_of(1 << i)));
+    pitches.retain(|p| *p >= width);
+    pitches.sort();
+    pitches.dedup();
+    pitches
+}
+
+fn score(
+    deswizzled: &[u8],
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+) -> u64 {
+    // Neighboring bytes in compressed blocks are not neighboring pixels.
+    if format.block_dim() != (1, 1) {
+        if let Ok(rgba) = decode_rgba8(width, height, depth_or_array_layers, format, deswizzled) {
+            return smoothness(&rgba, width, height, 4);
+        }
+    }
+
+    let (block_width, block_height) = format.block_dim();
+    smoothness(
+        deswizzled,
+        width.div_ceil(block_width),
+        height.div_ceil(block_height),
+        format.bytes_per_pixel(),
+    )
+}
+
+// Sum the differences between each byte and the same byte in the texels to the right and below.
+fn smoothness(data: &[u8], width: u32, height: u32, bytes_per_pixel: u32) -> u64 {
+    let row_size = (width * bytes_per_pixel) as usize;
+    let bpp = bytes_per_pixel as usize;
+
+    let mut score = 0;
+    for slice in data.chunks_exact(row_size * height as usize) {
+        for (y, row) in slice.chunks_exact(row_size).enumerate() {
+            for (i, byte) in row.iter().enumerate() {
+                if let Some(right) = row.get(i + bpp) {
+                    score += byte.abs_diff(*right) as u64;
+                }
+                if let Some(below) = slice.get((y + 1) * row_size + i) {
+                    score += byte.abs_diff(*below) as u64;
+                }
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swizzle_mipmap;
+
+    #[test]
+    fn guess_tiling_gradient() {
+        let linear: Vec<_> = (0..64 * 64)
+            .flat_map(|i| {
+                let (x, y) = (i % 64, i / 64);
+                [x * 4, y * 4, x + y, 255].map(|v| v as u8)
+            })
+            .collect();
+        let tiled = swizzle_mipmap(
+            64,
+            64,
+            1,
+            &linear,
+            0x300,
+            64,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+
+        let candidates = guess_tiling(
+            SurfaceDim::D2,
+            64,
+            64,
+            1,
+            SurfaceFormat::R8G8B8A8Unorm,
+            &tiled,
+            5,
+        );
+        assert_eq!(5, candidates.len());
+        assert!(candidates.windows(2).all(|c| c[0].score <= c[1].score));
+
+        let best = candidates[0];
+        assert_eq!(
+            linear,
+            deswizzle_mipmap(
+                64,
+                64,
+                1,
+                &tiled,
+                best.swizzle,
+                best.pitch,
+                best.tile_mode,
+                4,
+                AaMode::X1
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn guess_tiling_bc1() {
+        // Solid color blocks with a gradient of RGB565 colors.
+        let linear: Vec<_> = (0..16 * 16)
+            .flat_map(|i| {
+                let (x, y) = (i % 16, i / 16);
+                let color: u16 = ((x * 2) << 11) | ((y * 4) << 5) | (x + y);
+                let [c0, c1] = color.to_le_bytes();
+                [c0, c1, c0, c1, 0, 0, 0, 0]
+            })
+            .collect();
+        let pitch = surface_layout(
+            SurfaceDim::D2,
+            64,
+            64,
+            1,
+            1,
+            SurfaceFormat::Bc1Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            0x300,
+        )
+        .unwrap()
+        .pitch;
+        let tiled = swizzle_mipmap(
+            16,
+            16,
+            1,
+            &linear,
+            0x300,
+            pitch,
+            TileMode::D2TiledThin1,
+            8,
+            AaMode::X1,
+        )
+        .unwrap();
+
+        let candidates = guess_tiling(
+            SurfaceDim::D2,
+            64,
+            64,
+            1,
+            SurfaceFormat::Bc1Unorm,
+            &tiled,
+            1,
+        );
+        let best = candidates[0];
+        assert_eq!(
+            linear,
+            deswizzle_mipmap(
+                16,
+                16,
+                1,
+                &tiled,
+                best.swizzle,
+                best.pitch,
+                best.tile_mode,
+                8,
+                AaMode::X1
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn guess_tiling_not_enough_data() {
+        let candidates = guess_tiling(
+            SurfaceDim::D2,
+            64,
+            64,
+            1,
+            SurfaceFormat::R8G8B8A8Unorm,
+            &[0u8; 64],
+            5,
+        );
+        assert!(candidates.is_empty());
+    }
+}
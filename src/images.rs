@@ -0,0 +1,232 @@
+use crate::{
+    decode_rgba32f, decode_rgba8, deswizzled_surface_mipmaps, div_round_up, encode_surface_rgba32f,
+    encode_surface_rgba8, generate_mipmaps_rgba32f, generate_mipmaps_rgba8, swizzle_surface,
+    AaMode, EncodeQuality, Gx2Surface, MipmapFilter, SurfaceDim, SurfaceFormat, SwizzleError,
+    SwizzledSurface, TileMode,
+};
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+use std::{vec, vec::Vec};
+
+impl Gx2Surface<'_> {
+    /// Deswizzle and decode all layers and mipmaps to images indexed by `[layer][mip]`.
+    ///
+    /// Formats with [SurfaceFormat::is_hdr] decode to [DynamicImage::ImageRgba32F]
+    /// and all other formats decode to [DynamicImage::ImageRgba8].
+    /// 3D surfaces have a single layer with the depth slices for each mipmap stacked vertically.
+    pub fn to_images(&self) -> Result<Vec<Vec<DynamicImage>>, SwizzleError> {
+        let data = self.deswizzle()?;
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (block_width, block_height) = self.format.block_dim();
+        let bytes_per_pixel = self.format.bytes_per_pixel() as usize;
+
+        let mipmaps = deswizzled_surface_mipmaps(
+            self.dim,
+            self.width,
+            self.height,
+            self.depth_or_array_layers,
+            self.mipmap_count,
+        );
+
+        let layer_count = match self.dim {
+            SurfaceDim::D3 => 1,
+            _ => self.depth_or_array_layers as usize,
+        };
+        let mut images = vec![Vec::new(); layer_count];
+
+        let mut offset = 0;
+        for (i, (width, height, depth)) in mipmaps.into_iter().enumerate() {
+            let size = div_round_up(width, block_width) as usize
+                * div_round_up(height, block_height) as usize
+                * depth as usize
+                * bytes_per_pixel;
+            let mip_data = &data[offset..offset + size];
+            offset += size;
+
+            match self.dim {
+                SurfaceDim::Cube => {
+                    // Cube maps are ordered by [layer][mip].
+                    let layer = i / self.mipmap_count as usize;
+                    images[layer].push(self.decode_image(width, height, mip_data)?);
+                }
+                SurfaceDim::D3 => {
+                    // Stack depth slices vertically.
+                    images[0].push(self.decode_image(width, height * depth, mip_data)?);
+                }
+                SurfaceDim::D1 | SurfaceDim::D2 => {
+                    // Other surfaces are ordered by [mip][layer].
+                    let layer_size = size / depth as usize;
+                    for (layer, layer_data) in mip_data.chunks_exact(layer_size).enumerate() {
+                        images[layer].push(self.decode_image(width, height, layer_data)?);
+                    }
+                }
+            }
+        }
+
+        Ok(images)
+    }
+
+    fn decode_image(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<DynamicImage, SwizzleError> {
+        if self.format.is_hdr() {
+            let rgba = decode_rgba32f(width, height, 1, self.format, data)?;
+            Ok(Rgba32FImage::from_raw(width, height, rgba).unwrap().into())
+        } else {
+            let rgba = decode_rgba8(width, height, 1, self.format, data)?;
+            Ok(RgbaImage::from_raw(width, height, rgba).unwrap().into())
+        }
+    }
+}
+
+impl SwizzledSurface {
+    /// Create a 2D surface by generating mipmaps, encoding, and swizzling `image`.
+    ///
+    /// Formats with [SurfaceFormat::is_hdr] are encoded from RGBA32F to preserve precision.
+    /// The `mipmap_count` includes the base level.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_image(
+        image: &DynamicImage,
+        format: SurfaceFormat,
+        tile_mode: TileMode,
+        swizzle: u32,
+        mipmap_count: u32,
+        filter: MipmapFilter,
+        quality: EncodeQuality,
+    ) -> Result<Self, SwizzleError> {
+        let (width, height) = (image.width(), image.height());
+        let dim = SurfaceDim::D2;
+
+        let data = if format.is_hdr() {
+            let rgba = image.to_rgba32f();
+            let mipmaps = generate_mipmaps_rgba32f(
+                dim,
+                width,
+                height,
+                1,
+                mipmap_count,
+                format,
+                filter,
+                rgba.as_raw(),
+            )?;
+            encode_surface_rgba32f(
+                dim,
+                width,
+                height,
+                1,
+                mipmap_count,
+                format,
+                &mipmaps,
+                quality,
+            )?
+        } else {
+            let rgba = image.to_rgba8();
+            let mipmaps = generate_mipmaps_rgba8(
+                dim,
+                width,
+                height,
+                1,
+                mipmap_count,
+                format,
+                filter,
+                rgba.as_raw(),
+            )?;
+            encode_surface_rgba8(
+                dim,
+                width,
+                height,
+                1,
+                mipmap_count,
+                format,
+                &mipmaps,
+                quality,
+            )?
+        };
+
+        swizzle_surface(
+            dim,
+            width,
+            height,
+            1,
+            mipmap_count,
+            format,
+            AaMode::X1,
+            tile_mode,
+            swizzle,
+            &data,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_images_bc1_mipmaps() {
+        let surface = crate::tests::bc1_mipmaps_surface(include_bytes!(
+            "data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin"
+        ));
+        let images = surface.to_images().unwrap();
+        assert_eq!(1, images.len());
+        let dimensions: Vec<_> = images[0].iter().map(|i| (i.width(), i.height())).collect();
+        assert_eq!(
+            vec![
+                (256, 256),
+                (128, 128),
+                (64, 64),
+                (32, 32),
+                (16, 16),
+                (8, 8),
+                (4, 4),
+                (2, 2)
+            ],
+            dimensions
+        );
+        assert!(images[0]
+            .iter()
+            .all(|i| matches!(i, DynamicImage::ImageRgba8(_))));
+    }
+
+    #[test]
+    fn from_image_to_images_rgba8() {
+        let image = RgbaImage::from_fn(64, 32, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let surface = SwizzledSurface::from_image(
+            &image.clone().into(),
+            SurfaceFormat::R8G8B8A8Unorm,
+            TileMode::D2TiledThin1,
+            0,
+            3,
+            MipmapFilter::Box,
+            EncodeQuality::Normal,
+        )
+        .unwrap();
+        let images = surface.as_gx2_surface().to_images().unwrap();
+        assert_eq!(1, images.len());
+        assert_eq!(3, images[0].len());
+        assert_eq!(DynamicImage::ImageRgba8(image), images[0][0]);
+        assert_eq!((16, 8), (images[0][2].width(), images[0][2].height()));
+    }
+
+    #[test]
+    fn from_image_to_images_hdr() {
+        let image = Rgba32FImage::from_pixel(4, 4, image::Rgba([2.0, 0.5, -1.0, 1.0]));
+        let surface = SwizzledSurface::from_image(
+            &image.clone().into(),
+            SurfaceFormat::R16G16B16A16Float,
+            TileMode::LinearAligned,
+            0,
+            1,
+            MipmapFilter::Box,
+            EncodeQuality::Normal,
+        )
+        .unwrap();
+        let images = surface.as_gx2_surface().to_images().unwrap();
+        assert_eq!(DynamicImage::ImageRgba32F(image), images[0][0]);
+    }
+}
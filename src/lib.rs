@@ -4,23 +4,80 @@
 //!
 //! Most applications should construct a [Gx2Surface] and use [Gx2Surface::deswizzle]
 //! to correctly handle offsets and parameter changes for different mip levels.
+//! The deswizzled data for each mipmap can be converted to RGBA
+//! using [decode_rgba8] or [decode_rgba32f].
+//!
+//! Use a [SwizzlePlan] to reuse the tiled addresses for many mipmaps with the same parameters.
+//! [TiledAddresses] and [LinearCoordinates] iterate over texels in linear or tiled order
+//! for streaming data in either order.
+//! [deswizzle_mipmap_rows] deswizzles large surfaces without storing the entire linear output in memory.
+//! The tiled input still needs to be in memory.
+//! [deswizzle_surfaces] converts many surfaces at once and reuses plans for identical layouts.
+//!
+//! With the `"encode"` feature, new surfaces can be created by encoding RGBA data with `encode_rgba8` or `encode_rgba32f`
+//! and tiling the result with [swizzle_surface].
 #![no_std]
 extern crate alloc;
 
 #[cfg(feature = "std")]
 extern crate std;
 
-pub use addrlib::TileMode;
+pub use addresses::{explain_address, LinearCoordinates, TexelAddress, TiledAddresses};
 use addrlib::{
     hwl_compute_surface_info, ComputeSurfaceAddrFromCoordInput, ComputeSurfaceInfoInput,
     ComputeSurfaceInfoOutput, SurfaceFlags,
 };
+pub use addrlib::{
+    pipe_bank_swizzle, swizzle_from_pipe_bank, AddressBreakdown, ChipFamily, HwConfig, TileMode,
+};
 use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+pub use batch::{deswizzle_surfaces, deswizzle_surfaces_with_callbacks};
+pub use decode::{decode_rgba32f, decode_rgba8};
+#[cfg(feature = "encode")]
+pub use encode::{
+    encode_rgba32f, encode_rgba8, encode_surface_rgba32f, encode_surface_rgba8, EncodeQuality,
+};
+pub use guess::{guess_tiling, TilingCandidate};
+#[cfg(feature = "std")]
+pub use mipmaps::{generate_mipmaps_rgba32f, generate_mipmaps_rgba8, MipmapFilter};
+pub use pattern::{
+    compare_index_pattern, index_pattern, infer_index_pattern, IndexPatternReport, TexelMismatch,
+    TilingParams,
+};
+pub use plan::{SwizzlePlan, SwizzleRun};
+#[cfg(feature = "std")]
+pub use stream::deswizzle_mipmap_to_writer;
+pub use stream::{deswizzle_mipmap_rows, deswizzle_mipmap_slices};
+pub use surface_swizzle::{select_swizzle, suggest_swizzle, validate_swizzle};
+pub use validation::ValidationIssue;
+#[cfg(feature = "std")]
+pub use visualize::write_ppm;
+pub use visualize::{layout_rgb, LayoutColoring};
 
+mod addresses;
 mod addrlib;
+mod batch;
+mod decode;
+#[cfg(feature = "encode")]
+mod encode;
+mod guess;
+#[cfg(feature = "image")]
+mod images;
+#[cfg(feature = "std")]
+mod mipmaps;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pattern;
+mod plan;
+mod stream;
+mod surface_swizzle;
+mod validation;
+mod visualize;
 
 /// Errors than can occur while converting between tiled and linear memory layouts.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SwizzleError {
     /// The source data does not contain enough bytes.
     NotEnoughData {
@@ -43,6 +100,21 @@ pub enum SwizzleError {
         image_data_len: usize,
         mipmap_data_len: usize,
     },
+
+    /// The operation was cancelled before completing.
+    Cancelled,
+
+    /// The tile mode is not supported for address calculations.
+    UnsupportedTileMode { tile_mode: TileMode },
+
+    /// The swizzle sets bits not used for the pipe, bank, or mip level.
+    InvalidSwizzle { swizzle: u32 },
+
+    /// The pipe or bank swizzle is out of range for the number of pipes or banks.
+    InvalidPipeBankSwizzle {
+        pipe_swizzle: u32,
+        bank_swizzle: u32,
+    },
 }
 
 #[cfg(feature = "std")]
@@ -68,6 +140,20 @@ impl std::fmt::Display for SwizzleError {
                 image_data_len,
                 mipmap_data_len,
             } => write!(f, "Mipmap offsets {mipmap_offsets:?} out of range for {image_data_len} bytes and {mipmap_data_len} mipmap bytes"),
+            SwizzleError::Cancelled => write!(f, "Operation was cancelled"),
+            SwizzleError::UnsupportedTileMode { tile_mode } => {
+                write!(f, "Tile mode {tile_mode:?} is not supported")
+            }
+            SwizzleError::InvalidSwizzle { swizzle } => {
+                write!(f, "Swizzle 0x{swizzle:X} sets unsupported bits")
+            }
+            SwizzleError::InvalidPipeBankSwizzle {
+                pipe_swizzle,
+                bank_swizzle,
+            } => write!(
+                f,
+                "Pipe swizzle {pipe_swizzle} or bank swizzle {bank_swizzle} is out of range"
+            ),
         }
     }
 }
@@ -233,6 +319,17 @@ c_enum! {
 }
 
 impl SurfaceFormat {
+    /// Returns `true` if the color components are stored in the sRGB color space.
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self,
+            SurfaceFormat::R8G8B8A8Srgb
+                | SurfaceFormat::Bc1Srgb
+                | SurfaceFormat::Bc2Srgb
+                | SurfaceFormat::Bc3Srgb
+        )
+    }
+
     pub fn block_dim(&self) -> (u32, u32) {
         match self {
             SurfaceFormat::Bc1Unorm => (4, 4),
@@ -321,6 +418,120 @@ c_enum! {
     Cube = 3,
 }
 
+c_enum! {
+    /// GX2TileMode for the tile mode requested when creating a surface
+    Gx2TileMode,
+    /// GX2_TILE_MODE_DEFAULT
+    Default = 0x0,
+    /// GX2_TILE_MODE_LINEAR_ALIGNED
+    LinearAligned = 0x1,
+    /// GX2_TILE_MODE_TILED_1D_THIN1
+    D1TiledThin1 = 0x2,
+    /// GX2_TILE_MODE_TILED_1D_THICK
+    D1TiledThick = 0x3,
+    /// GX2_TILE_MODE_TILED_2D_THIN1
+    D2TiledThin1 = 0x4,
+    /// GX2_TILE_MODE_TILED_2D_THIN2
+    D2TiledThin2 = 0x5,
+    /// GX2_TILE_MODE_TILED_2D_THIN4
+    D2TiledThin4 = 0x6,
+    /// GX2_TILE_MODE_TILED_2D_THICK
+    D2TiledThick = 0x7,
+    /// GX2_TILE_MODE_TILED_2B_THIN1
+    B2TiledThin1 = 0x8,
+    /// GX2_TILE_MODE_TILED_2B_THIN2
+    B2TiledThin2 = 0x9,
+    /// GX2_TILE_MODE_TILED_2B_THIN4
+    B2TiledThin4 = 0xA,
+    /// GX2_TILE_MODE_TILED_2B_THICK
+    B2TiledThick = 0xB,
+    /// GX2_TILE_MODE_TILED_3D_THIN1
+    D3TiledThin1 = 0xC,
+    /// GX2_TILE_MODE_TILED_3D_THICK
+    D3TiledThick = 0xD,
+    /// GX2_TILE_MODE_TILED_3B_THIN1
+    B3TiledThin1 = 0xE,
+    /// GX2_TILE_MODE_TILED_3B_THICK
+    B3TiledThick = 0xF,
+    /// GX2_TILE_MODE_LINEAR_SPECIAL
+    LinearSpecial = 0x10,
+}
+
+// The pipe and bank swizzle bits stored in the low bits of GX2 image and mip pointers.
+// https://github.com/cemu-project/Cemu/blob/85141f17f977157b91b72883d879f50b27f17dda/src/Cafe/HW/Latte/Core/LatteTextureLoader.cpp#L30-L31
+const MIP_PTR_SWIZZLE_MASK: u32 = 0x700;
+
+// GX2SurfaceUse flags that affect the default tile mode.
+const SURFACE_USE_COLOR_BUFFER: u32 = 0x2;
+const SURFACE_USE_DEPTH_BUFFER: u32 = 0x4;
+
+impl Gx2TileMode {
+    /// Calculate the [TileMode] the driver would use for a surface with these parameters.
+    ///
+    /// Unlike [TileMode], a value of 0 is [Gx2TileMode::Default] and lets the driver choose.
+    /// [Gx2TileMode::Default] uses linear tiling for single sampled 1D textures.
+    /// Other surfaces use 2D tiling or 1D tiling if the base level is smaller than a macro tile
+    /// in either dimension.
+    /// [Gx2TileMode::LinearSpecial] uses unaligned linear tiling.
+    /// The remaining values are the same as [TileMode].
+    // Based on GX2CalcSurfaceSizeAndAlignment.
+    #[allow(clippy::too_many_arguments)]
+    pub fn resolve(
+        self,
+        dim: SurfaceDim,
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        format: SurfaceFormat,
+        aa: AaMode,
+        usage: u32,
+    ) -> TileMode {
+        match self {
+            Gx2TileMode::Default => {
+                let is_depth_buffer = usage & SURFACE_USE_DEPTH_BUFFER != 0;
+                let is_color_buffer = usage & SURFACE_USE_COLOR_BUFFER != 0;
+                if dim == SurfaceDim::D1 && aa == AaMode::X1 && !is_depth_buffer {
+                    return TileMode::LinearAligned;
+                }
+
+                let tile_mode = if dim != SurfaceDim::D3 || is_color_buffer {
+                    TileMode::D2TiledThin1
+                } else {
+                    TileMode::D2TiledThick
+                };
+
+                // Surfaces smaller than the alignment in either dimension use 1D tiling.
+                // This uses the same condition as compute_surface_info_macro_tiled.
+                let (block_width, block_height) = format.block_dim();
+                let width = div_round_up(width, block_width);
+                let height = div_round_up(height, block_height);
+                match mip_surface_info(
+                    dim,
+                    width,
+                    height,
+                    depth_or_array_layers,
+                    format,
+                    aa,
+                    tile_mode,
+                    0,
+                ) {
+                    Ok(output) if width < output.pitch_align || height < output.height_align => {
+                        if tile_mode == TileMode::D2TiledThick {
+                            TileMode::D1TiledThick
+                        } else {
+                            TileMode::D1TiledThin1
+                        }
+                    }
+                    _ => tile_mode,
+                }
+            }
+            Gx2TileMode::LinearSpecial => TileMode::LinearGeneral,
+            // The remaining values are the same for GX2 and addrlib.
+            _ => TileMode::from_repr(self as u32).unwrap(),
+        }
+    }
+}
+
 // TODO: impl Default?
 // TODO: How to handle array layers?
 // TODO: additional enums?
@@ -372,184 +583,181 @@ pub struct Gx2Surface<'a> {
     pub mipmap_offsets: [u32; 13],
 }
 
-// TODO: Also define a swizzle surface?
-impl<'a> Gx2Surface<'a> {
+impl Gx2Surface<'_> {
     /// Convert all layers and mipmaps from tiled to a combined linear vector.
+    ///
+    /// All mip levels use the same [swizzle](#structfield.swizzle).
+    /// See [Gx2Surface::deswizzle_with_mip_ptr] for surfaces with a separate mipmap swizzle.
     pub fn deswizzle(&self) -> Result<Vec<u8>, SwizzleError> {
+        self.deswizzle_with_swizzles(&[])
+    }
+
+    /// The same as [Gx2Surface::deswizzle] but mip levels past the base level use
+    /// the pipe and bank swizzle bits from the GX2 `mipPtr`.
+    ///
+    /// The base level uses [swizzle](#structfield.swizzle).
+    pub fn deswizzle_with_mip_ptr(&self, mip_ptr: u32) -> Result<Vec<u8>, SwizzleError> {
+        let mip_swizzle = mip_ptr & MIP_PTR_SWIZZLE_MASK;
+        let swizzles: Vec<_> = (0..self.mipmap_count)
+            .map(|mip| if mip == 0 { self.swizzle } else { mip_swizzle })
+            .collect();
+        self.deswizzle_with_swizzles(&swizzles)
+    }
+
+    /// The same as [Gx2Surface::deswizzle] but using the swizzle in `swizzles` for each mip level.
+    ///
+    /// Mip levels past the end of `swizzles` use [swizzle](#structfield.swizzle).
+    pub fn deswizzle_with_swizzles(&self, swizzles: &[u32]) -> Result<Vec<u8>, SwizzleError> {
         // TODO: The compute info functions can also validate?
         self.validate()?;
         // TODO: how to handle empty surfaces?
-        if self.width == 0 || self.height == 0 || self.depth_or_array_layers == 0 || self.pitch == 0
-        {
+        if self.is_empty() {
             return Ok(Vec::new());
         }
 
-        let (block_width, block_height) = self.format.block_dim();
-        let bytes_per_pixel = self.format.bytes_per_pixel();
+        // Mipmaps can be deswizzled independently.
+        #[cfg(feature = "rayon")]
+        let mipmaps: Vec<_> = {
+            use rayon::prelude::*;
+            (0..self.mipmap_count)
+                .into_par_iter()
+                .map(|mip| self.deswizzle_mipmap(mip, self.mip_swizzle(swizzles, mip)))
+                .collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let mipmaps: Vec<_> = (0..self.mipmap_count)
+            .map(|mip| self.deswizzle_mipmap(mip, self.mip_swizzle(swizzles, mip)))
+            .collect();
 
-        let mut data = Vec::new();
-        for mip in 0..self.mipmap_count {
-            let source = if mip == 0 {
-                // The mip 0 data is at the start of the image data.
-                self.image_data
-            } else if mip == 1 {
-                // The slice already accounts for the mip 1 offset.
-                let next_offset = self.mipmap_offsets[mip as usize] as usize;
-                if next_offset != 0 {
-                    &self.mipmap_data[..next_offset]
-                } else {
-                    self.mipmap_data
-                }
-            } else {
-                // Remaining mip levels are relative to the start of the mipmap data.
-                let offset = if mip == 1 {
-                    0
-                } else {
-                    self.mipmap_offsets[mip as usize - 1] as usize
-                };
-                let next_offset = self.mipmap_offsets[mip as usize] as usize;
-                if next_offset != 0 {
-                    &self.mipmap_data[offset..next_offset]
-                } else {
-                    &self.mipmap_data[offset..]
-                }
-            };
+        self.combine_mipmaps(mipmaps)
+    }
 
-            // TODO: How to handle dimensions not divisible by block dimensions?
-            // TODO: cemu uses mipPtr & 0x700 for swizzle for mipmaps?
-            let width = div_round_up(self.width >> mip, block_width);
-            let height = div_round_up(self.height >> mip, block_height);
-
-            // Some parameters change based on dimensions or mip level.
-            // Small mips may use micro instead of macro tiling.
-            // TODO: how to set these parameters?
-            let input = ComputeSurfaceInfoInput {
-                size: source.len() as u32,
-                tile_mode: self.tile_mode,
-                format: self.format,
-                bpp: bytes_per_pixel * u8::BITS,
-                num_samples: 1 << self.aa as u32,
-                width,
-                height,
-                num_slices: self.depth_or_array_layers,
-                slice: 0,
-                mip_level: mip,
-                flags: match self.dim {
-                    SurfaceDim::D1 => SurfaceFlags::default(),
-                    SurfaceDim::D2 => SurfaceFlags::default(),
-                    SurfaceDim::D3 => SurfaceFlags::volume,
-                    SurfaceDim::Cube => SurfaceFlags::cube,
-                },
-                tile_info: Default::default(),
-                tile_type: addrlib::TileType::Displayable,
-                tile_index: 0,
-            };
-            // TODO: Can this use defaults?
-            let mut output = ComputeSurfaceInfoOutput {
-                size: 0,
-                pitch: 0,
-                height: 0,
-                depth: 0,
-                surf_size: 0,
-                tile_mode: self.tile_mode,
-                base_align: 0,
-                pitch_align: 0,
-                height_align: 0,
-                depth_align: 0,
-                bpp: 0,
-                pixel_pitch: 0,
-                pixel_height: 0,
-                pixel_bits: 0,
-                slice_size: 0,
-                pitch_tile_max: 0,
-                height_tile_max: 0,
-                slice_tile_max: 0,
-                tile_info: Default::default(),
-                tile_type: addrlib::TileType::Displayable,
-                tile_index: 0,
-            };
-            hwl_compute_surface_info(&input, &mut output);
+    fn mip_swizzle(&self, swizzles: &[u32], mip: u32) -> u32 {
+        swizzles.get(mip as usize).copied().unwrap_or(self.swizzle)
+    }
 
-            // TODO: Why is output.pitch sometimes too large?
-            let pitch = output.pitch.min(self.pitch);
+    pub(crate) fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0 || self.depth_or_array_layers == 0 || self.pitch == 0
+    }
 
-            // TODO: is this data all layers for each mip?
-            // TODO: Store this as layer major instead of mip major to match dds?
-            // TODO: Should this be depth and also layers?
-            let mip = deswizzle_mipmap(
-                width,
-                height,
-                self.depth_or_array_layers,
-                source,
-                self.swizzle,
-                pitch,
-                output.tile_mode,
-                bytes_per_pixel,
-                self.aa,
-            )?;
-            data.extend_from_slice(&mip);
+    // Combine the deswizzled data for each mipmap in the expected layer and mipmap order.
+    pub(crate) fn combine_mipmaps(
+        &self,
+        mipmaps: Vec<Result<Vec<u8>, SwizzleError>>,
+    ) -> Result<Vec<u8>, SwizzleError> {
+        let mut data = Vec::new();
+        for mip in mipmaps {
+            data.extend_from_slice(&mip?);
         }
 
         if self.dim == SurfaceDim::Cube {
-            Ok(self.mip_major_to_layer_major(&data, block_width, block_height, bytes_per_pixel))
+            Ok(convert_cube_layout::<true>(
+                &data,
+                self.width,
+                self.height,
+                self.mipmap_count,
+                self.format,
+            ))
         } else {
             Ok(data)
         }
     }
 
-    fn mip_major_to_layer_major(
-        &self,
-        data: &[u8],
-        block_width: u32,
-        block_height: u32,
-        bytes_per_pixel: u32,
-    ) -> Vec<u8> {
-        // Convert from [mip][layer] to [layer][mip] ordering.
-        // TODO: Is there a better way of doing this?
-        let mut new_data = vec![0u8; data.len()];
-
-        let mut mip_offsets: Vec<_> = (0..self.mipmap_count.saturating_sub(1))
-            .map(|mip| {
-                let width = div_round_up(self.width >> mip, block_width);
-                let height = div_round_up(self.height >> mip, block_height);
-                width * height * bytes_per_pixel
-            })
-            .scan(0, |state, x| Some(*state + x))
-            .collect();
-        mip_offsets.insert(0, 0);
-
-        let output_layer_size = (0..self.mipmap_count)
-            .map(|mip| {
-                let width = div_round_up(self.width >> mip, block_width);
-                let height = div_round_up(self.height >> mip, block_height);
-                width * height * bytes_per_pixel
-            })
-            .sum::<u32>();
-
-        let mut mip_offset = 0;
-        for mip in 0..self.mipmap_count {
-            let width = div_round_up(self.width >> mip, block_width);
-            let height = div_round_up(self.height >> mip, block_height);
-            let mip_size = (width * height * bytes_per_pixel) as usize;
-
-            for layer in 0..6 {
-                let layer_offset = width * height * layer * bytes_per_pixel;
-                let input_offset = layer_offset as usize + mip_offset;
-                let mip_data = &data[input_offset..input_offset + mip_size];
+    fn deswizzle_mipmap(&self, mip: u32, swizzle: u32) -> Result<Vec<u8>, SwizzleError> {
+        let (source, params) = self.mipmap_params(mip, swizzle)?;
+        deswizzle_mipmap(
+            params.width,
+            params.height,
+            params.depth_or_array_layers,
+            source,
+            params.swizzle,
+            params.pitch,
+            params.tile_mode,
+            params.bytes_per_pixel,
+            params.aa,
+        )
+    }
 
-                let output_layer_offset = output_layer_size * layer;
-                let output_mip_offset = mip_offsets[mip as usize];
-                let output_offset = (output_layer_offset + output_mip_offset) as usize;
+    // The tiled data and parameters for deswizzle_mipmap for the given mip level.
+    pub(crate) fn mipmap_params(
+        &self,
+        mip: u32,
+        swizzle: u32,
+    ) -> Result<(&[u8], MipmapParams), SwizzleError> {
+        let (block_width, block_height) = self.format.block_dim();
+        let bytes_per_pixel = self.format.bytes_per_pixel();
 
-                new_data[output_offset..output_offset + mip_size].copy_from_slice(mip_data);
+        let source = if mip == 0 {
+            // The mip 0 data is at the start of the image data.
+            self.image_data
+        } else if mip == 1 {
+            // The slice already accounts for the mip 1 offset.
+            let next_offset = self.mipmap_offsets[mip as usize] as usize;
+            if next_offset != 0 {
+                &self.mipmap_data[..next_offset]
+            } else {
+                self.mipmap_data
+            }
+        } else {
+            // Remaining mip levels are relative to the start of the mipmap data.
+            let offset = if mip == 1 {
+                0
+            } else {
+                self.mipmap_offsets[mip as usize - 1] as usize
+            };
+            let next_offset = self.mipmap_offsets[mip as usize] as usize;
+            if next_offset != 0 {
+                &self.mipmap_data[offset..next_offset]
+            } else {
+                &self.mipmap_data[offset..]
             }
+        };
 
-            mip_offset += mip_size * 6;
-        }
-        new_data
+        // TODO: How to handle dimensions not divisible by block dimensions?
+        let (width, height, depth) = mip_dimensions(
+            self.dim,
+            self.width,
+            self.height,
+            self.depth_or_array_layers,
+            mip,
+        );
+        let width = div_round_up(width, block_width);
+        let height = div_round_up(height, block_height);
+
+        // Some parameters change based on dimensions or mip level.
+        // Small mips may use micro instead of macro tiling.
+        let output = mip_surface_info(
+            self.dim,
+            width,
+            height,
+            depth,
+            self.format,
+            self.aa,
+            self.tile_mode,
+            mip,
+        )?;
+
+        // TODO: Why is output.pitch sometimes too large?
+        let pitch = output.pitch.min(self.pitch);
+
+        // TODO: is this data all layers for each mip?
+        // TODO: Store this as layer major instead of mip major to match dds?
+        Ok((
+            source,
+            MipmapParams {
+                width,
+                height,
+                depth_or_array_layers: depth,
+                swizzle,
+                pitch,
+                tile_mode: output.tile_mode,
+                bytes_per_pixel,
+                aa: self.aa,
+            },
+        ))
     }
 
-    fn validate(&self) -> Result<(), SwizzleError> {
+    pub(crate) fn validate(&self) -> Result<(), SwizzleError> {
         if self.mipmap_offsets[0] > self.image_data.len() as u32
             || self.mipmap_offsets[1..]
                 .iter()
@@ -560,155 +768,746 @@ impl<'a> Gx2Surface<'a> {
                 image_data_len: self.image_data.len(),
                 mipmap_data_len: self.mipmap_data.len(),
             })
-        } else if self
-            .width
-            .checked_mul(self.height)
-            .and_then(|u| u.checked_mul(self.depth_or_array_layers))
-            .and_then(|u| u.checked_mul(self.format.bytes_per_pixel() * u8::BITS))
-            .and_then(|u| u.checked_mul(self.pitch))
-            .and_then(|u| u.checked_mul(1 << self.aa as u32))
-            .is_none()
-            || self.mipmap_count > self.mipmap_offsets.len() as u32
-        {
-            // Check dimensions to prevent overflow.
-            Err(SwizzleError::InvalidSurface {
-                width: self.width,
-                height: self.height,
-                depth: self.depth_or_array_layers,
-                format: self.format,
-                mipmap_count: self.mipmap_count,
-            })
         } else {
-            Ok(())
+            validate_dimensions(
+                self.width,
+                self.height,
+                self.depth_or_array_layers,
+                self.mipmap_count,
+                self.format,
+                self.aa,
+                self.pitch,
+            )
         }
     }
 }
 
-// TODO: Docs and examples.
-/// Convert the tiled data in `source` to a combined linear vector.
+// The parameters for deswizzling a single mipmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct MipmapParams {
+    pub width: u32,
+    pub height: u32,
+    pub depth_or_array_layers: u32,
+    pub swizzle: u32,
+    pub pitch: u32,
+    pub tile_mode: TileMode,
+    pub bytes_per_pixel: u32,
+    pub aa: AaMode,
+}
+
+/// An owned Wii U GX2 texture surface with tiled image data.
 ///
-/// For block compressed formats, `width` and `height` should be the dimensions in blocks
-/// with `bytes_per_pixel` being the size of a block in bytes.
+/// The fields have the same meaning as the fields for [Gx2Surface].
+/// Create a new surface from linear data using [swizzle_surface].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwizzledSurface {
+    pub dim: SurfaceDim,
+    pub width: u32,
+    pub height: u32,
+    pub depth_or_array_layers: u32,
+    pub mipmap_count: u32,
+    pub format: SurfaceFormat,
+    pub aa: AaMode,
+    pub usage: u32,
+    pub image_data: Vec<u8>,
+    pub mipmap_data: Vec<u8>,
+    pub tile_mode: TileMode,
+    pub swizzle: u32,
+    pub alignment: u32,
+    pub pitch: u32,
+    pub mipmap_offsets: [u32; 13],
+}
+
+impl SwizzledSurface {
+    /// Borrow the data as a [Gx2Surface] to deswizzle it again.
+    pub fn as_gx2_surface(&self) -> Gx2Surface<'_> {
+        Gx2Surface {
+            dim: self.dim,
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: self.depth_or_array_layers,
+            mipmap_count: self.mipmap_count,
+            format: self.format,
+            aa: self.aa,
+            usage: self.usage,
+            image_data: &self.image_data,
+            mipmap_data: &self.mipmap_data,
+            tile_mode: self.tile_mode,
+            swizzle: self.swizzle,
+            alignment: self.alignment,
+            pitch: self.pitch,
+            mipmap_offsets: self.mipmap_offsets,
+        }
+    }
+}
+
+/// The memory layout of a surface calculated using the same conventions as GX2CalcSurfaceSizeAndAlignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurfaceLayout {
+    /// The size of the tiled data for the base mip level in bytes.
+    pub image_size: u32,
+    /// The size of the tiled data for all mipmaps past the base level in bytes.
+    pub mipmap_size: u32,
+    /// The alignment of the base mip level in bytes.
+    pub alignment: u32,
+    /// The pitch of the base mip level.
+    pub pitch: u32,
+    /// The offsets for each mipmap past the base level using the same conventions as [Gx2Surface].
+    pub mipmap_offsets: [u32; 13],
+    /// The layout for each mip level starting with the base level.
+    pub mipmaps: Vec<MipmapLayout>,
+}
+
+/// The memory layout of a single mip level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MipmapLayout {
+    /// The width in blocks for compressed formats or pixels for uncompressed formats.
+    pub width: u32,
+    /// The height in blocks for compressed formats or pixels for uncompressed formats.
+    pub height: u32,
+    /// The depth or number of array layers.
+    pub depth_or_array_layers: u32,
+    /// The pitch used for tiling this mip level.
+    pub pitch: u32,
+    /// The tile mode used for this level.
+    /// Small mipmaps may use micro instead of macro tiling.
+    pub tile_mode: TileMode,
+    /// The offset in bytes from the start of the image data for the base level
+    /// or the start of the mipmap data for all other levels.
+    pub offset: u32,
+    /// The size of the tiled data in bytes including padding.
+    pub size: u32,
+}
+
+/// Calculate the memory layout for all mipmaps of a surface.
+///
+/// This can be used to inspect surfaces or calculate the fields for a [Gx2Surface].
 #[allow(clippy::too_many_arguments)]
-pub fn deswizzle_mipmap(
+pub fn surface_layout(
+    dim: SurfaceDim,
     width: u32,
     height: u32,
     depth_or_array_layers: u32,
-    source: &[u8],
-    swizzle: u32,
-    pitch: u32,
-    tile_mode: TileMode,
-    bytes_per_pixel: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
     aa: AaMode,
-) -> Result<Vec<u8>, SwizzleError> {
-    let output_size = width as usize
-        * height as usize
-        * depth_or_array_layers as usize
-        * bytes_per_pixel as usize;
-    // TODO: How to handle zero sized inputs?
-    if output_size == 0 {
-        return Ok(Vec::new());
-    }
-
-    // TODO: Why is this sometimes 0?
-    let expected_size = swizzled_mipmap_size(
+    tile_mode: TileMode,
+    swizzle: u32,
+) -> Result<SurfaceLayout, SwizzleError> {
+    validate_dimensions(
         width,
         height,
         depth_or_array_layers,
-        swizzle,
-        pitch,
-        tile_mode,
-        bytes_per_pixel,
+        mipmap_count,
+        format,
         aa,
-    );
-    if source.len() < expected_size {
-        return Err(SwizzleError::NotEnoughData {
-            expected_size,
-            actual_size: source.len(),
-        });
+        1,
+    )?;
+
+    let mut layout = SurfaceLayout {
+        image_size: 0,
+        mipmap_size: 0,
+        alignment: 0,
+        pitch: 0,
+        mipmap_offsets: [0; 13],
+        mipmaps: Vec::new(),
+    };
+    if width == 0 || height == 0 || depth_or_array_layers == 0 || mipmap_count == 0 {
+        return Ok(layout);
     }
 
-    let mut output = vec![0u8; output_size];
+    let (block_width, block_height) = format.block_dim();
+
+    // Calculate offsets using the same rules as GX2.
+    // Mipmap offsets past mip 1 are relative to the mip 1 offset.
+    let mut level_tile_mode = tile_mode;
+    let mut previous_size = 0;
+    let mut mip_offset = 0;
+    for mip in 0..mipmap_count {
+        let (mip_width, mip_height, mip_depth) =
+            mip_dimensions(dim, width, height, depth_or_array_layers, mip);
+        let mip_width = div_round_up(mip_width, block_width);
+        let mip_height = div_round_up(mip_height, block_height);
+
+        let output = mip_surface_info(
+            dim, mip_width, mip_height, mip_depth, format, aa, tile_mode, mip,
+        )?;
+
+        if mip == 0 {
+            layout.pitch = output.pitch;
+            layout.alignment = output.base_align;
+        } else {
+            let mut padding = 0;
+            // GX2 adds the swizzle as padding when switching from macro to micro tiling.
+            if level_tile_mode >= TileMode::D2TiledThin1
+                && level_tile_mode != TileMode::D2TiledXthick
+                && output.tile_mode < TileMode::D2TiledThin1
+            {
+                level_tile_mode = output.tile_mode;
+                if mip > 1 {
+                    padding = swizzle & 0xFFFF;
+                }
+            }
+            padding += (output.base_align - previous_size % output.base_align) % output.base_align;
 
-    swizzle_surface_inner::<false>(
-        width,
-        height,
-        depth_or_array_layers,
-        source,
-        &mut output,
-        swizzle,
-        pitch,
-        tile_mode,
-        bytes_per_pixel,
-        aa,
-    )?;
+            if mip == 1 {
+                layout.mipmap_offsets[0] = padding + previous_size;
+            } else {
+                mip_offset += padding + previous_size;
+                layout.mipmap_offsets[mip as usize - 1] = mip_offset;
+            }
+        }
+        previous_size = output.surf_size as u32;
+
+        let mip_layout = MipmapLayout {
+            width: mip_width,
+            height: mip_height,
+            depth_or_array_layers: mip_depth,
+            pitch: output.pitch.min(layout.pitch),
+            tile_mode: output.tile_mode,
+            offset: mip_offset,
+            size: output.surf_size as u32,
+        };
+        if mip == 0 {
+            layout.image_size = mip_layout.size;
+        } else {
+            layout.mipmap_size = mip_layout.offset + mip_layout.size;
+        }
+        layout.mipmaps.push(mip_layout);
+    }
 
-    Ok(output)
+    Ok(layout)
 }
 
-/// Convert the linear data in `source` to a combined tiled vector.
+/// Convert all layers and mipmaps from a combined linear vector to a tiled surface.
 ///
-/// For block compressed formats, `width` and `height` should be the dimensions in blocks
-/// with `bytes_per_pixel` being the size of a block in bytes.
+/// The `data` should use the same ordering of layers and mipmaps
+/// as the output of [Gx2Surface::deswizzle].
+/// The pitch, alignment, and mipmap offsets are calculated from the surface parameters
+/// using the same conventions as GX2CalcSurfaceSizeAndAlignment.
+/// The [usage](struct.SwizzledSurface.html#structfield.usage) is set to 1 for textures.
+/// Use [Gx2TileMode::resolve] to match the `tile_mode` GX2 would choose by default.
+/// Use [suggest_swizzle] or [select_swizzle] to choose a `swizzle` for new surfaces.
 #[allow(clippy::too_many_arguments)]
-pub fn swizzle_mipmap(
+pub fn swizzle_surface(
+    dim: SurfaceDim,
     width: u32,
     height: u32,
     depth_or_array_layers: u32,
-    source: &[u8],
-    swizzle: u32,
-    pitch: u32,
-    tile_mode: TileMode,
-    bytes_per_pixel: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
     aa: AaMode,
-) -> Result<Vec<u8>, SwizzleError> {
-    // TODO: Is this the correct output size?
-    let output_size = swizzled_mipmap_size(
+    tile_mode: TileMode,
+    swizzle: u32,
+    data: &[u8],
+) -> Result<SwizzledSurface, SwizzleError> {
+    let layout = surface_layout(
+        dim,
         width,
         height,
         depth_or_array_layers,
-        swizzle,
-        pitch,
-        tile_mode,
-        bytes_per_pixel,
+        mipmap_count,
+        format,
         aa,
-    );
-    if output_size == 0 {
-        return Ok(Vec::new());
-    }
+        tile_mode,
+        swizzle,
+    )?;
 
-    let expected_size =
-        deswizzled_mipmap_size(width, height, depth_or_array_layers, bytes_per_pixel);
-    if source.len() < expected_size {
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let expected_size = layout
+        .mipmaps
+        .iter()
+        .map(|m| {
+            deswizzled_mipmap_size(m.width, m.height, m.depth_or_array_layers, bytes_per_pixel)
+        })
+        .sum();
+    if data.len() < expected_size {
         return Err(SwizzleError::NotEnoughData {
             expected_size,
-            actual_size: source.len(),
+            actual_size: data.len(),
         });
     }
 
-    let mut output = vec![0u8; output_size];
+    let data = if dim == SurfaceDim::Cube && !layout.mipmaps.is_empty() {
+        convert_cube_layout::<false>(data, width, height, mipmap_count, format)
+    } else {
+        data[..expected_size].to_vec()
+    };
 
-    swizzle_surface_inner::<true>(
-        width,
-        height,
-        depth_or_array_layers,
-        source,
-        &mut output,
-        swizzle,
-        pitch,
-        tile_mode,
-        bytes_per_pixel,
+    let mut image_data = vec![0u8; layout.image_size as usize];
+    let mut mipmap_data = vec![0u8; layout.mipmap_size as usize];
+
+    let mut linear_offset = 0;
+    for (mip, mip_layout) in layout.mipmaps.iter().enumerate() {
+        let size = deswizzled_mipmap_size(
+            mip_layout.width,
+            mip_layout.height,
+            mip_layout.depth_or_array_layers,
+            bytes_per_pixel,
+        );
+        let tiled = swizzle_mipmap(
+            mip_layout.width,
+            mip_layout.height,
+            mip_layout.depth_or_array_layers,
+            &data[linear_offset..linear_offset + size],
+            swizzle,
+            mip_layout.pitch,
+            mip_layout.tile_mode,
+            bytes_per_pixel,
+            aa,
+        )?;
+        linear_offset += size;
+
+        if tiled.len() > mip_layout.size as usize {
+            return Err(SwizzleError::InvalidSurface {
+                width,
+                height,
+                depth: depth_or_array_layers,
+                format,
+                mipmap_count,
+            });
+        }
+        let buffer = if mip == 0 {
+            &mut image_data
+        } else {
+            &mut mipmap_data
+        };
+        let offset = mip_layout.offset as usize;
+        buffer[offset..offset + tiled.len()].copy_from_slice(&tiled);
+    }
+
+    Ok(SwizzledSurface {
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        aa,
+        usage: 1,
+        image_data,
+        mipmap_data,
+        tile_mode,
+        swizzle,
+        alignment: layout.alignment,
+        pitch: layout.pitch,
+        mipmap_offsets: layout.mipmap_offsets,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn mip_surface_info(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    format: SurfaceFormat,
+    aa: AaMode,
+    tile_mode: TileMode,
+    mip: u32,
+) -> Result<ComputeSurfaceInfoOutput, SwizzleError> {
+    // TODO: how to set these parameters?
+    let input = ComputeSurfaceInfoInput {
+        size: 0,
+        tile_mode,
+        format,
+        bpp: format.bytes_per_pixel() * u8::BITS,
+        num_samples: 1 << aa as u32,
+        width,
+        height,
+        num_slices: depth_or_array_layers,
+        slice: 0,
+        mip_level: mip,
+        flags: match dim {
+            SurfaceDim::D1 => SurfaceFlags::default(),
+            SurfaceDim::D2 => SurfaceFlags::default(),
+            SurfaceDim::D3 => SurfaceFlags::volume,
+            SurfaceDim::Cube => SurfaceFlags::cube,
+        },
+        tile_info: Default::default(),
+        tile_type: addrlib::TileType::Displayable,
+        tile_index: 0,
+    };
+    // TODO: Can this use defaults?
+    let mut output = ComputeSurfaceInfoOutput {
+        size: 0,
+        pitch: 0,
+        height: 0,
+        depth: 0,
+        surf_size: 0,
+        tile_mode,
+        base_align: 0,
+        pitch_align: 0,
+        height_align: 0,
+        depth_align: 0,
+        bpp: 0,
+        pixel_pitch: 0,
+        pixel_height: 0,
+        pixel_bits: 0,
+        slice_size: 0,
+        pitch_tile_max: 0,
+        height_tile_max: 0,
+        slice_tile_max: 0,
+        tile_info: Default::default(),
+        tile_type: addrlib::TileType::Displayable,
+        tile_index: 0,
+    };
+    // GX2 surfaces always use the Latte configuration.
+    hwl_compute_surface_info(&HwConfig::LATTE, &input, &mut output)?;
+    Ok(output)
+}
+
+fn validate_dimensions(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    aa: AaMode,
+    pitch: u32,
+) -> Result<(), SwizzleError> {
+    if width
+        .checked_mul(height)
+        .and_then(|u| u.checked_mul(depth_or_array_layers))
+        .and_then(|u| u.checked_mul(format.bytes_per_pixel() * u8::BITS))
+        .and_then(|u| u.checked_mul(pitch))
+        .and_then(|u| u.checked_mul(1 << aa as u32))
+        .is_none()
+        || mipmap_count > MAX_MIPMAP_COUNT
+    {
+        // Check dimensions to prevent overflow.
+        Err(SwizzleError::InvalidSurface {
+            width,
+            height,
+            depth: depth_or_array_layers,
+            format,
+            mipmap_count,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// The dimensions in pixels of a mipmap.
+/// Only 3D surfaces reduce the depth for each mipmap.
+/// Array layers and cube faces are the same for all mipmaps.
+pub(crate) fn mip_dimensions(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mip: u32,
+) -> (u32, u32, u32) {
+    let depth = if dim == SurfaceDim::D3 {
+        (depth_or_array_layers >> mip).max(1)
+    } else {
+        depth_or_array_layers
+    };
+    ((width >> mip).max(1), (height >> mip).max(1), depth)
+}
+
+/// The dimensions in pixels of each mipmap in the deswizzled data for a surface.
+/// Cube maps are ordered by [layer][mip] and all other surfaces are ordered by [mip][layer].
+#[cfg(feature = "encode")]
+pub(crate) fn deswizzled_surface_mipmaps(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+) -> Vec<(u32, u32, u32)> {
+    if dim == SurfaceDim::Cube {
+        (0..depth_or_array_layers)
+            .flat_map(|_| {
+                (0..mipmap_count).map(|mip| {
+                    let (width, height, _) = mip_dimensions(dim, width, height, 1, mip);
+                    (width, height, 1)
+                })
+            })
+            .collect()
+    } else {
+        (0..mipmap_count)
+            .map(|mip| mip_dimensions(dim, width, height, depth_or_array_layers, mip))
+            .collect()
+    }
+}
+
+/// Convert cube map data between [mip][layer] and [layer][mip] ordering.
+fn convert_cube_layout<const LAYER_MAJOR: bool>(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+) -> Vec<u8> {
+    // TODO: Is there a better way of doing this?
+    let (block_width, block_height) = format.block_dim();
+    let bytes_per_pixel = format.bytes_per_pixel();
+
+    let mut new_data = vec![0u8; data.len()];
+
+    let mip_size = |mip: u32| {
+        let (width, height, _) = mip_dimensions(SurfaceDim::Cube, width, height, 6, mip);
+        div_round_up(width, block_width) * div_round_up(height, block_height) * bytes_per_pixel
+    };
+
+    let mut mip_offsets: Vec<_> = (0..mipmap_count.saturating_sub(1))
+        .map(mip_size)
+        .scan(0, |state, x| {
+            *state += x;
+            Some(*state)
+        })
+        .collect();
+    mip_offsets.insert(0, 0);
+
+    let layer_size = (0..mipmap_count).map(mip_size).sum::<u32>();
+
+    let mut mip_offset = 0;
+    for mip in 0..mipmap_count {
+        let mip_size = mip_size(mip) as usize;
+
+        for layer in 0..6 {
+            let mip_major_offset = mip_size * layer as usize + mip_offset;
+            let layer_major_offset = (layer_size * layer + mip_offsets[mip as usize]) as usize;
+
+            let (input_offset, output_offset) = if LAYER_MAJOR {
+                (mip_major_offset, layer_major_offset)
+            } else {
+                (layer_major_offset, mip_major_offset)
+            };
+
+            new_data[output_offset..output_offset + mip_size]
+                .copy_from_slice(&data[input_offset..input_offset + mip_size]);
+        }
+
+        mip_offset += mip_size * 6;
+    }
+    new_data
+}
+
+// TODO: Docs and examples.
+/// Convert the tiled data in `source` to a combined linear vector.
+///
+/// For block compressed formats, `width` and `height` should be the dimensions in blocks
+/// with `bytes_per_pixel` being the size of a block in bytes.
+///
+/// This uses [HwConfig::LATTE] for the Wii U.
+/// Use [deswizzle_mipmap_with_config] for other hardware configurations.
+#[allow(clippy::too_many_arguments)]
+pub fn deswizzle_mipmap(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+) -> Result<Vec<u8>, SwizzleError> {
+    deswizzle_mipmap_with_config(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        &HwConfig::LATTE,
+    )
+}
+
+/// The same as [deswizzle_mipmap] but using the hardware parameters in `hw_config`.
+///
+/// This allows tiling surfaces for other R6XX and R7XX family GPUs.
+#[allow(clippy::too_many_arguments)]
+pub fn deswizzle_mipmap_with_config(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    hw_config: &HwConfig,
+) -> Result<Vec<u8>, SwizzleError> {
+    let output_size = width as usize
+        * height as usize
+        * depth_or_array_layers as usize
+        * bytes_per_pixel as usize;
+    // TODO: How to handle zero sized inputs?
+    if output_size == 0 {
+        return Ok(Vec::new());
+    }
+
+    // TODO: Why is this sometimes 0?
+    let expected_size = swizzled_mipmap_size(
+        width,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        hw_config,
+    )?;
+    if source.len() < expected_size {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size,
+            actual_size: source.len(),
+        });
+    }
+
+    let mut output = vec![0u8; output_size];
+
+    swizzle_surface_inner::<false>(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        &mut output,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        hw_config,
+    )?;
+
+    Ok(output)
+}
+
+/// Convert the linear data in `source` to a combined tiled vector.
+///
+/// For block compressed formats, `width` and `height` should be the dimensions in blocks
+/// with `bytes_per_pixel` being the size of a block in bytes.
+///
+/// The output contains all bytes up to the end of the highest tiled address.
+/// Use [swizzle_surface] to include padding and mipmaps.
+///
+/// This uses [HwConfig::LATTE] for the Wii U.
+/// Use [swizzle_mipmap_with_config] for other hardware configurations.
+#[allow(clippy::too_many_arguments)]
+pub fn swizzle_mipmap(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+) -> Result<Vec<u8>, SwizzleError> {
+    swizzle_mipmap_with_config(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        &HwConfig::LATTE,
+    )
+}
+
+/// The same as [swizzle_mipmap] but using the hardware parameters in `hw_config`.
+///
+/// This allows tiling surfaces for other R6XX and R7XX family GPUs.
+#[allow(clippy::too_many_arguments)]
+pub fn swizzle_mipmap_with_config(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    hw_config: &HwConfig,
+) -> Result<Vec<u8>, SwizzleError> {
+    // TODO: Is this the correct output size?
+    let output_size = swizzled_mipmap_size(
+        width,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        hw_config,
+    )?;
+    // The corner address of a single texel surface is 0, so check the dimensions instead.
+    if width == 0 || height == 0 || depth_or_array_layers == 0 || bytes_per_pixel == 0 {
+        return Ok(Vec::new());
+    }
+
+    let expected_size =
+        deswizzled_mipmap_size(width, height, depth_or_array_layers, bytes_per_pixel);
+    if source.len() < expected_size {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size,
+            actual_size: source.len(),
+        });
+    }
+
+    // The corner address is only a lower bound for the size.
+    // The output grows to fit the highest address.
+    let mut output = Vec::with_capacity(output_size);
+
+    swizzle_surface_inner::<true>(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        &mut output,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
         aa,
+        hw_config,
     )?;
 
     Ok(output)
 }
 
+/// The maximum number of mipmaps including the base level supported by GX2.
+pub const MAX_MIPMAP_COUNT: u32 = 13;
+
+/// The number of mipmaps for a full mipmap chain down to 1x1 pixels
+/// limited to [MAX_MIPMAP_COUNT].
+///
+/// Only 3D surfaces consider the depth.
+pub fn max_mipmap_count(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+) -> u32 {
+    let depth = if dim == SurfaceDim::D3 {
+        depth_or_array_layers
+    } else {
+        1
+    };
+    let size = width.max(height).max(depth).max(1);
+    (u32::BITS - size.leading_zeros()).min(MAX_MIPMAP_COUNT)
+}
+
 fn div_round_up(x: u32, d: u32) -> u32 {
-    (x + d - 1) / d
+    x.div_ceil(d)
 }
 
-fn deswizzled_mipmap_size(
+pub(crate) fn deswizzled_mipmap_size(
     width: u32,
     height: u32,
     depth_or_array_layers: u32,
@@ -728,241 +1527,1411 @@ fn swizzled_mipmap_size(
     tile_mode: TileMode,
     bytes_per_pixel: u32,
     aa: AaMode,
-) -> usize {
+    hw: &HwConfig,
+) -> Result<usize, SwizzleError> {
     // Addrlib code doesn't handle a bpp of 0.
     if bytes_per_pixel == 0 {
-        return 0;
+        return Ok(0);
     }
     let bpp = bytes_per_pixel * u8::BITS;
 
     // TODO: name in gx2?
-    let (pipe_swizzle, bank_swizzle) = addrlib::pipe_bank_swizzle(swizzle);
+    let (pipe_swizzle, bank_swizzle) = addrlib::pipe_bank_swizzle(hw, swizzle);
+
+    // TODO: How to initialize these parameters?
+    let sample = 0;
+    let num_samples = 1 << aa as u32;
+    let tile_base = 0; // TODO: only used for depth map textures?
+    let comp_bits = 0; // TODO: only used for depth map textures?
+
+    // TODO: How many of these fields are set from functions?
+    // TODO: Find a way to get values used from cemu to create test cases?
+    let p_in = ComputeSurfaceAddrFromCoordInput {
+        x: width.saturating_sub(1),
+        y: height.saturating_sub(1),
+        slice: depth_or_array_layers.saturating_sub(1),
+        sample,
+        bpp,
+        pitch,
+        height,
+        num_slices: depth_or_array_layers,
+        num_samples,
+        tile_mode,
+        is_depth: false,
+        tile_base,
+        comp_bits,
+        pipe_swizzle,
+        bank_swizzle,
+    };
+
+    // TODO: Will the corner always be the largest address?
+    addrlib::dispatch_compute_surface_addrfrom_coord(hw, &p_in).map(|a| a as usize)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn swizzle_surface_inner<const SWIZZLE: bool>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    output: &mut Vec<u8>,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    hw: &HwConfig,
+) -> Result<(), SwizzleError> {
+    #[cfg(feature = "rayon")]
+    {
+        parallel::swizzle_surface_inner::<SWIZZLE>(
+            width,
+            height,
+            depth_or_array_layers,
+            source,
+            output,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            hw,
+        )
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    for_each_run(
+        width,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        hw,
+        &mut |address, linear_address, size| {
+            copy_bytes::<SWIZZLE>(source, output, address, linear_address, size)
+        },
+    )
+}
+
+// Call f with the tiled address, linear address, and size of each run of bytes
+// with consecutive addresses in both layouts.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn for_each_run<F>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    hw: &HwConfig,
+    f: &mut F,
+) -> Result<(), SwizzleError>
+where
+    F: FnMut(usize, usize, usize) -> Result<(), SwizzleError>,
+{
+    for z in 0..depth_or_array_layers {
+        for_each_run_in_rows(
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            z,
+            0..height,
+            hw,
+            f,
+        )?;
+    }
+    Ok(())
+}
+
+// Call f for each run in the given rows of a single slice.
+// The rows should start at a multiple of the micro tile height.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn for_each_run_in_rows<F>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    z: u32,
+    rows: Range<u32>,
+    hw: &HwConfig,
+    f: &mut F,
+) -> Result<(), SwizzleError>
+where
+    F: ?Sized + FnMut(usize, usize, usize) -> Result<(), SwizzleError>,
+{
+    let bpp = bytes_per_pixel * u8::BITS;
+    let (pipe_swizzle, bank_swizzle) = addrlib::pipe_bank_swizzle(hw, swizzle);
+
+    let input = |x, y, slice| ComputeSurfaceAddrFromCoordInput {
+        x,
+        y,
+        slice,
+        sample: 0,
+        bpp,
+        pitch,
+        height,
+        num_slices: depth_or_array_layers,
+        num_samples: 1 << aa as u32,
+        tile_mode,
+        is_depth: false,
+        tile_base: 0,
+        comp_bits: 0,
+        pipe_swizzle,
+        bank_swizzle,
+    };
+
+    let row_size = width as usize * bytes_per_pixel as usize;
+    let slice_offset = z as usize * row_size * height as usize;
+    let rows = rows.start..rows.end.min(height);
+
+    if tile_mode.is_linear() {
+        // Each row is contiguous in memory for linear surfaces.
+        for y in rows {
+            let address = addrlib::dispatch_compute_surface_addrfrom_coord(hw, &input(0, y, z))?;
+            let linear_address = slice_offset + y as usize * row_size;
+            f(address as usize, linear_address, row_size)?;
+        }
+        return Ok(());
+    }
+
+    if addrlib::compute_micro_tile_address(hw, &input(0, 0, 0)).is_none() {
+        // Multisampled surfaces can split samples across tiles.
+        return for_each_texel(
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            z,
+            rows,
+            hw,
+            f,
+        );
+    }
+
+    // The pixel index pattern within a micro tile only depends on the lowest bits of each coordinate.
+    // Precompute the runs of pixels in each row that have consecutive element offsets.
+    let runs = micro_tile_runs(z, bpp, bytes_per_pixel, tile_mode);
+
+    for tile_y in rows.clone().step_by(MICRO_TILE_SIZE as usize) {
+        for tile_x in (0..width).step_by(MICRO_TILE_SIZE as usize) {
+            // Addresses are only calculated once per micro tile.
+            let Some(tile) = addrlib::compute_micro_tile_address(hw, &input(tile_x, tile_y, z))
+            else {
+                continue;
+            };
+
+            for run in &runs {
+                let x = tile_x + run.x;
+                let y = tile_y + run.y;
+                if x >= width || y >= rows.end {
+                    continue;
+                }
+
+                let len = run.len.min(width - x);
+                let linear_address =
+                    slice_offset + y as usize * row_size + x as usize * bytes_per_pixel as usize;
+
+                let run_size = len * bytes_per_pixel;
+                if tile.contiguous_bytes(run.element_offset) >= run_size {
+                    f(
+                        tile.address(run.element_offset) as usize,
+                        linear_address,
+                        run_size as usize,
+                    )?;
+                } else {
+                    // Runs crossing a pipe interleave boundary are not contiguous.
+                    for i in 0..len {
+                        let element_offset = run.element_offset + i * bytes_per_pixel;
+                        f(
+                            tile.address(element_offset) as usize,
+                            linear_address + (i * bytes_per_pixel) as usize,
+                            bytes_per_pixel as usize,
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) const MICRO_TILE_SIZE: u32 = 8;
+
+// Pixels in a row of a micro tile with consecutive addresses.
+struct MicroTileRun {
+    x: u32,
+    y: u32,
+    len: u32,
+    element_offset: u32,
+}
+
+fn micro_tile_runs(
+    z: u32,
+    bpp: u32,
+    bytes_per_pixel: u32,
+    tile_mode: TileMode,
+) -> Vec<MicroTileRun> {
+    let mut runs: Vec<MicroTileRun> = Vec::new();
+    for y in 0..MICRO_TILE_SIZE {
+        for x in 0..MICRO_TILE_SIZE {
+            let element_offset = addrlib::micro_tile_element_offset(x, y, z, bpp, tile_mode);
+            match runs.last_mut() {
+                Some(run)
+                    if run.y == y
+                        && run.element_offset + run.len * bytes_per_pixel == element_offset =>
+                {
+                    run.len += 1
+                }
+                _ => runs.push(MicroTileRun {
+                    x,
+                    y,
+                    len: 1,
+                    element_offset,
+                }),
+            }
+        }
+    }
+    runs
+}
+
+// Calculate the address for each texel separately.
+#[cfg(test)]
+#[allow(clippy::too_many_arguments)]
+fn swizzle_surface_texels<const SWIZZLE: bool>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    output: &mut Vec<u8>,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    hw: &HwConfig,
+) -> Result<(), SwizzleError> {
+    let f = &mut |address, linear_address, size| {
+        copy_bytes::<SWIZZLE>(source, output, address, linear_address, size)
+    };
+    for z in 0..depth_or_array_layers {
+        for_each_texel(
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            z,
+            0..height,
+            hw,
+            f,
+        )?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn for_each_texel<F>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    z: u32,
+    rows: Range<u32>,
+    hw: &HwConfig,
+    f: &mut F,
+) -> Result<(), SwizzleError>
+where
+    F: ?Sized + FnMut(usize, usize, usize) -> Result<(), SwizzleError>,
+{
+    // TODO: always bytes per pixel * 8?
+    let bpp = bytes_per_pixel * u8::BITS;
+
+    // TODO: name in gx2?
+    let (pipe_swizzle, bank_swizzle) = addrlib::pipe_bank_swizzle(hw, swizzle);
+
+    // TODO: How to initialize these parameters?
+    let sample = 0;
+    let num_samples = 1 << aa as u32; // TODO: is this based on self.aa?
+    let tile_base = 0; // TODO: only used for depth map textures?
+    let comp_bits = 0; // TODO: only used for depth map textures?
+
+    // TODO: Is it correct to use depth and layers as slices?
+    for y in rows {
+        for x in 0..width {
+            let p_in = ComputeSurfaceAddrFromCoordInput {
+                x,
+                y,
+                slice: z,
+                sample,
+                bpp,
+                pitch,
+                height,
+                num_slices: depth_or_array_layers,
+                num_samples,
+                tile_mode,
+                is_depth: false,
+                tile_base,
+                comp_bits,
+                pipe_swizzle,
+                bank_swizzle,
+            };
+
+            let address = addrlib::dispatch_compute_surface_addrfrom_coord(hw, &p_in)? as usize;
+            let linear_address = ((z * width * height + y * width + x) * bytes_per_pixel) as usize;
+
+            f(address, linear_address, bytes_per_pixel as usize)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(test, not(feature = "rayon")))]
+fn copy_bytes<const SWIZZLE: bool>(
+    source: &[u8],
+    output: &mut Vec<u8>,
+    address: usize,
+    linear_address: usize,
+    size: usize,
+) -> Result<(), SwizzleError> {
+    if SWIZZLE {
+        if output.len() < address + size {
+            output.resize(address + size, 0);
+        }
+        output[address..address + size]
+            .copy_from_slice(&source[linear_address..linear_address + size]);
+    } else {
+        // The expected size only checks the corner address.
+        let bytes = source
+            .get(address..address + size)
+            .ok_or(SwizzleError::NotEnoughData {
+                expected_size: address + size,
+                actual_size: source.len(),
+            })?;
+        output[linear_address..linear_address + size].copy_from_slice(bytes);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swizzle_surface_inner_matches_texels() {
+        // Test edge tiles, thick tiles, and runs crossing pipe interleave boundaries.
+        let (width, height, depth) = (29, 23, 5);
+        for (tile_mode, pitch) in TileMode::supported().flat_map(|t| [(t, 32), (t, 64)]) {
+            for bytes_per_pixel in [1, 2, 4, 8, 16] {
+                for swizzle in [0, 0x700] {
+                    let size = (width * height * depth * bytes_per_pixel) as usize;
+                    let linear: Vec<_> = (0..size).map(|i| (i % 251) as u8).collect();
+
+                    let mut expected = Vec::new();
+                    swizzle_surface_texels::<true>(
+                        width,
+                        height,
+                        depth,
+                        &linear,
+                        &mut expected,
+                        swizzle,
+                        pitch,
+                        tile_mode,
+                        bytes_per_pixel,
+                        AaMode::X1,
+                        &HwConfig::LATTE,
+                    )
+                    .unwrap();
+
+                    let mut tiled = Vec::new();
+                    swizzle_surface_inner::<true>(
+                        width,
+                        height,
+                        depth,
+                        &linear,
+                        &mut tiled,
+                        swizzle,
+                        pitch,
+                        tile_mode,
+                        bytes_per_pixel,
+                        AaMode::X1,
+                        &HwConfig::LATTE,
+                    )
+                    .unwrap();
+                    assert_eq!(expected, tiled, "{tile_mode:?} {bytes_per_pixel}");
+
+                    let mut expected = vec![0u8; size];
+                    swizzle_surface_texels::<false>(
+                        width,
+                        height,
+                        depth,
+                        &tiled,
+                        &mut expected,
+                        swizzle,
+                        pitch,
+                        tile_mode,
+                        bytes_per_pixel,
+                        AaMode::X1,
+                        &HwConfig::LATTE,
+                    )
+                    .unwrap();
+
+                    let mut deswizzled = vec![0u8; size];
+                    swizzle_surface_inner::<false>(
+                        width,
+                        height,
+                        depth,
+                        &tiled,
+                        &mut deswizzled,
+                        swizzle,
+                        pitch,
+                        tile_mode,
+                        bytes_per_pixel,
+                        AaMode::X1,
+                        &HwConfig::LATTE,
+                    )
+                    .unwrap();
+                    assert_eq!(expected, deswizzled, "{tile_mode:?} {bytes_per_pixel}");
+                }
+            }
+        }
+    }
+
+    // TODO: Add a test for micro tiling.
+    #[test]
+    fn deswizzle_empty() {
+        assert!(deswizzle_mipmap(
+            0,
+            0,
+            0,
+            &[],
+            853504,
+            256,
+            TileMode::D2TiledThin1,
+            8,
+            AaMode::X1
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn deswizzle_macro_tiled_1024x1024_bc1() {
+        let expected = include_bytes!("data/1024x1024_bc1_tm4_p256_s853504_deswizzled.bin");
+        let swizzled = include_bytes!("data/1024x1024_bc1_tm4_p256_s853504_swizzled.bin");
+
+        assert_eq!(
+            expected,
+            &deswizzle_mipmap(
+                1024 / 4,
+                1024 / 4,
+                1,
+                swizzled,
+                853504,
+                256,
+                TileMode::D2TiledThin1,
+                8,
+                AaMode::X1
+            )
+            .unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn deswizzle_macro_tiled_16x16x16_rgba8() {
+        let expected = include_bytes!("data/16x16x16_rgba8_tm7_p32_s852224_deswizzled.bin");
+        let swizzled = include_bytes!("data/16x16x16_rgba8_tm7_p32_s852224_swizzled.bin");
+
+        assert_eq!(
+            expected,
+            &deswizzle_mipmap(
+                16,
+                16,
+                16,
+                swizzled,
+                852224,
+                32,
+                TileMode::D2TiledThick,
+                4,
+                AaMode::X1
+            )
+            .unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn macro_tiled_32x64x4_rgba8_addresses() {
+        // Expected addresses derived by hand from addrlib's ComputeSurfaceAddrFromCoordMacroTiled
+        // for a 32x64x4 RGBA8 surface with swizzle 0x300.
+        for (tile_mode, addresses) in [
+            (
+                TileMode::D2TiledThin2,
+                [768, 956, 8884, 31228, 22528, 29492],
+            ),
+            (
+                TileMode::D2TiledThin4,
+                [768, 956, 10932, 31228, 20480, 25396],
+            ),
+            (
+                TileMode::D3TiledThin1,
+                [768, 956, 11188, 32508, 20992, 29748],
+            ),
+            (
+                TileMode::D3TiledThick,
+                [768, 956, 11444, 31740, 21504, 22836],
+            ),
+        ] {
+            for ((x, y, z), address) in [
+                (0, 0, 0),
+                (7, 5, 0),
+                (13, 21, 1),
+                (31, 63, 3),
+                (16, 40, 2),
+                (5, 33, 3),
+            ]
+            .into_iter()
+            .zip(addresses)
+            {
+                assert_eq!(
+                    address,
+                    explain_address(x, y, z, 64, 4, 0x300, 32, tile_mode, 4, AaMode::X1)
+                        .unwrap()
+                        .address,
+                    "{tile_mode:?} {x} {y} {z}"
+                );
+            }
+        }
+    }
+
+    // The 256x256 BC1 surface with 8 mipmaps from the test data using the tiled bytes in data.
+    pub(crate) fn bc1_mipmaps_surface(data: &[u8]) -> Gx2Surface<'_> {
+        Gx2Surface {
+            dim: SurfaceDim::D2,
+            width: 256,
+            height: 256,
+            depth_or_array_layers: 1,
+            mipmap_count: 8,
+            format: SurfaceFormat::Bc1Unorm,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: data,
+            mipmap_data: data.get(32768..).unwrap_or_default(),
+            tile_mode: TileMode::D2TiledThin1,
+            swizzle: 132352,
+            alignment: 4096,
+            pitch: 64,
+            mipmap_offsets: [
+                32768, 9472, 11520, 12032, 12544, 13056, 13568, 0, 0, 0, 0, 0, 0,
+            ],
+        }
+    }
+
+    #[test]
+    fn deswizzle_surface_256x256_bc1_mipmaps() {
+        let expected = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_deswizzled.bin");
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+
+        let surface = Gx2Surface {
+            dim: SurfaceDim::D2,
+            width: 256,
+            height: 256,
+            depth_or_array_layers: 1,
+            mipmap_count: 8,
+            format: SurfaceFormat::Bc1Unorm,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: swizzled,
+            mipmap_data: &swizzled[32768..],
+            tile_mode: TileMode::D2TiledThin1,
+            swizzle: 132352,
+            alignment: 4096,
+            pitch: 64,
+            mipmap_offsets: [
+                32768, 9472, 11520, 12032, 12544, 13056, 13568, 0, 0, 0, 0, 0, 0,
+            ],
+        };
+        assert_eq!(expected, &surface.deswizzle().unwrap()[..]);
+    }
+
+    #[test]
+    fn deswizzle_surface_64x64_cube_bc1_mipmaps() {
+        let expected = include_bytes!("data/64x64_cube_bc1_tm4_p32_s67328_deswizzled.bin");
+        let swizzled = include_bytes!("data/64x64_cube_bc1_tm4_p32_s67328_swizzled.bin");
+
+        let surface = Gx2Surface {
+            dim: SurfaceDim::Cube,
+            width: 64,
+            height: 64,
+            depth_or_array_layers: 6,
+            mipmap_count: 2,
+            format: SurfaceFormat::Bc1Unorm,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: swizzled,
+            mipmap_data: &swizzled[24576..],
+            tile_mode: TileMode::D2TiledThin1,
+            swizzle: 67328,
+            alignment: 4096,
+            pitch: 32,
+            mipmap_offsets: [24576, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        };
+        assert_eq!(expected, &surface.deswizzle().unwrap()[..]);
+    }
+
+    #[test]
+    fn swizzle_surface_256x256_bc1_mipmaps() {
+        let deswizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_deswizzled.bin");
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+
+        let surface = swizzle_surface(
+            SurfaceDim::D2,
+            256,
+            256,
+            1,
+            8,
+            SurfaceFormat::Bc1Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            132352,
+            deswizzled,
+        )
+        .unwrap();
+        assert_eq!(64, surface.pitch);
+        assert_eq!(4096, surface.alignment);
+        assert_eq!(
+            [32768, 9472, 11520, 12032, 12544, 13056, 13568, 0, 0, 0, 0, 0, 0],
+            surface.mipmap_offsets
+        );
+        assert_eq!(&swizzled[..32768], &surface.image_data[..]);
+        // Smaller mipmaps contain padding, so only compare the texels.
+        assert_eq!(swizzled.len() - 32768, surface.mipmap_data.len());
+        assert_eq!(
+            &deswizzled[..],
+            &surface.as_gx2_surface().deswizzle().unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn surface_layout_256x256_bc1_mipmaps() {
+        let layout = surface_layout(
+            SurfaceDim::D2,
+            256,
+            256,
+            1,
+            8,
+            SurfaceFormat::Bc1Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            132352,
+        )
+        .unwrap();
+        assert_eq!(32768, layout.image_size);
+        assert_eq!(14080, layout.mipmap_size);
+        assert_eq!(
+            vec![
+                (64, 64, TileMode::D2TiledThin1, 0),
+                (32, 32, TileMode::D2TiledThin1, 0),
+                (16, 16, TileMode::D1TiledThin1, 9472),
+                (8, 8, TileMode::D1TiledThin1, 11520),
+                (4, 4, TileMode::D1TiledThin1, 12032),
+                (2, 2, TileMode::D1TiledThin1, 12544),
+                (1, 1, TileMode::D1TiledThin1, 13056),
+                (1, 1, TileMode::D1TiledThin1, 13568),
+            ],
+            layout
+                .mipmaps
+                .iter()
+                .map(|m| (m.width, m.height, m.tile_mode, m.offset))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn swizzle_surface_64x64_cube_bc1_mipmaps() {
+        let deswizzled = include_bytes!("data/64x64_cube_bc1_tm4_p32_s67328_deswizzled.bin");
+        let swizzled = include_bytes!("data/64x64_cube_bc1_tm4_p32_s67328_swizzled.bin");
+
+        let surface = swizzle_surface(
+            SurfaceDim::Cube,
+            64,
+            64,
+            6,
+            2,
+            SurfaceFormat::Bc1Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            67328,
+            deswizzled,
+        )
+        .unwrap();
+        assert_eq!(32, surface.pitch);
+        assert_eq!(4096, surface.alignment);
+        assert_eq!(
+            [24576, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            surface.mipmap_offsets
+        );
+        assert_eq!(
+            swizzled.len(),
+            surface.image_data.len() + surface.mipmap_data.len()
+        );
+        // The pitch is larger than the width, so only compare the texels.
+        assert_eq!(
+            &deswizzled[..],
+            &surface.as_gx2_surface().deswizzle().unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn swizzle_deswizzle_macro_tiled_16x16x16_rgba8() {
+        let deswizzled = include_bytes!("data/16x16x16_rgba8_tm7_p32_s852224_deswizzled.bin");
+        let swizzled = include_bytes!("data/16x16x16_rgba8_tm7_p32_s852224_swizzled.bin");
+
+        let output = swizzle_mipmap(
+            16,
+            16,
+            16,
+            deswizzled,
+            852224,
+            32,
+            TileMode::D2TiledThick,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+        assert_eq!(&swizzled[..output.len()], &output[..]);
+    }
+
+    #[test]
+    fn swizzle_deswizzle_hw_config() {
+        let hw_config = HwConfig {
+            num_banks: 8,
+            num_pipes: 4,
+            ..HwConfig::default()
+        };
+        let linear: Vec<_> = (0..128 * 128 * 4).map(|i| (i % 251) as u8).collect();
+
+        let swizzled = swizzle_mipmap_with_config(
+            128,
+            128,
+            1,
+            &linear,
+            0x700,
+            128,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+            &hw_config,
+        )
+        .unwrap();
+        let latte = swizzle_mipmap(
+            128,
+            128,
+            1,
+            &linear,
+            0x700,
+            128,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+        assert_ne!(latte, swizzled);
+
+        let deswizzled = deswizzle_mipmap_with_config(
+            128,
+            128,
+            1,
+            &swizzled,
+            0x700,
+            128,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+            &hw_config,
+        )
+        .unwrap();
+        assert_eq!(linear, deswizzled);
+    }
+
+    #[test]
+    fn pipe_bank_swizzle_round_trip() {
+        let hw = HwConfig::LATTE;
+        assert_eq!((1, 3), pipe_bank_swizzle(&hw, 0x700));
+        assert_eq!(0x700, swizzle_from_pipe_bank(&hw, 1, 3));
+        assert_eq!((1, 2), pipe_bank_swizzle(&hw, 132352));
+        assert_eq!(0x500, swizzle_from_pipe_bank(&hw, 1, 2));
+        for swizzle in (0..8).map(|i| i << 8) {
+            let (pipe, bank) = pipe_bank_swizzle(&hw, swizzle);
+            assert_eq!(swizzle, swizzle_from_pipe_bank(&hw, pipe, bank));
+        }
+    }
+
+    #[test]
+    fn deswizzle_surface_mip_ptr_swizzle() {
+        let layout = surface_layout(
+            SurfaceDim::D2,
+            256,
+            256,
+            1,
+            2,
+            SurfaceFormat::R8G8B8A8Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            0,
+        )
+        .unwrap();
+        let linear: Vec<_> = (0..(256 * 256 + 128 * 128) * 4)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        // Use a different swizzle for the base level and mipmaps.
+        let image_data = swizzle_mipmap(
+            256,
+            256,
+            1,
+            &linear[..256 * 256 * 4],
+            0x200,
+            layout.mipmaps[0].pitch,
+            layout.mipmaps[0].tile_mode,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+        let mipmap_data = swizzle_mipmap(
+            128,
+            128,
+            1,
+            &linear[256 * 256 * 4..],
+            0x500,
+            layout.mipmaps[1].pitch,
+            layout.mipmaps[1].tile_mode,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+
+        let surface = Gx2Surface {
+            dim: SurfaceDim::D2,
+            width: 256,
+            height: 256,
+            depth_or_array_layers: 1,
+            mipmap_count: 2,
+            format: SurfaceFormat::R8G8B8A8Unorm,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: &image_data,
+            mipmap_data: &mipmap_data,
+            tile_mode: TileMode::D2TiledThin1,
+            swizzle: 0x200,
+            alignment: layout.alignment,
+            pitch: layout.pitch,
+            mipmap_offsets: layout.mipmap_offsets,
+        };
+        assert_ne!(linear, surface.deswizzle().unwrap());
+        assert_eq!(linear, surface.deswizzle_with_mip_ptr(0x1000500).unwrap());
+        assert_eq!(
+            linear,
+            surface.deswizzle_with_swizzles(&[0x200, 0x500]).unwrap()
+        );
+    }
+
+    #[test]
+    fn mip_surface_info_latte_alignment() {
+        // The pitch and alignment stored by GX2 in the game surfaces used as fixtures.
+        // The R6XX dual base and pitch alignment rules don't apply to Latte.
+        // Using them gives a pitch of 96 instead of 64 for the 256x256 surface.
+        for (dim, width, height, layers, pitch, alignment) in [
+            (SurfaceDim::D2, 64, 64, 1, 64, 4096),
+            (SurfaceDim::Cube, 16, 16, 6, 32, 4096),
+        ] {
+            let output = mip_surface_info(
+                dim,
+                width,
+                height,
+                layers,
+                SurfaceFormat::Bc1Unorm,
+                AaMode::X1,
+                TileMode::D2TiledThin1,
+                0,
+            )
+            .unwrap();
+            assert_eq!(pitch, output.pitch, "{dim:?}");
+            assert_eq!(alignment, output.base_align, "{dim:?}");
+        }
+    }
+
+    #[test]
+    fn swizzle_surface_volume_mipmaps() {
+        // Mipmaps for 3D surfaces also halve the depth.
+        let data: Vec<_> = (0..(512 + 64 + 8 + 1) * 4).map(|i| i as u8).collect();
+        let surface = swizzle_surface(
+            SurfaceDim::D3,
+            8,
+            8,
+            8,
+            4,
+            SurfaceFormat::R8G8B8A8Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThick,
+            0,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(data, surface.as_gx2_surface().deswizzle().unwrap());
+    }
+
+    #[test]
+    fn swizzle_surface_volume_xthick_mipmaps() {
+        // Smaller mipmaps switch to thick or 1D tiling.
+        let size: usize = (0..5).map(|i| (64 >> i) * (64 >> i) * (16 >> i) * 4).sum();
+        let data: Vec<_> = (0..size).map(|i| (i % 251) as u8).collect();
+        for tile_mode in [TileMode::D2TiledXthick, TileMode::D3TiledXThick] {
+            let surface = swizzle_surface(
+                SurfaceDim::D3,
+                64,
+                64,
+                16,
+                5,
+                SurfaceFormat::R8G8B8A8Unorm,
+                AaMode::X1,
+                tile_mode,
+                0,
+                &data,
+            )
+            .unwrap();
+            assert_eq!(data, surface.as_gx2_surface().deswizzle().unwrap());
+        }
+    }
+
+    #[test]
+    fn xthick_addresses() {
+        // Expected addresses derived by hand from addrlib's ComputeSurfaceAddrFromCoordMacroTiled
+        // for a 64x32x16 RGBA8 surface with swizzle 0x300 and 8 slice thick micro tiles.
+        // The 2D and 3D modes only differ in the bank and pipe rotation for slices 8 to 15.
+        for (tile_mode, addresses) in [
+            (
+                TileMode::D2TiledXthick,
+                [768, 6980, 15332, 66816, 98836, 129664, 124924],
+            ),
+            (
+                TileMode::D3TiledXThick,
+                [768, 6980, 15332, 66560, 99092, 129920, 124668],
+            ),
+        ] {
+            for ((x, y, z), address) in [
+                (0, 0, 0),
+                (1, 2, 3),
+                (5, 6, 7),
+                (0, 0, 8),
+                (9, 17, 8),
+                (40, 20, 15),
+                (63, 31, 12),
+            ]
+            .into_iter()
+            .zip(addresses)
+            {
+                assert_eq!(
+                    address,
+                    explain_address(x, y, z, 32, 16, 0x300, 64, tile_mode, 4, AaMode::X1)
+                        .unwrap()
+                        .address,
+                    "{tile_mode:?} {x} {y} {z}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mipmap_depth_3d() {
+        // Only 3D surfaces halve the depth for each mip level.
+        for (dim, depths) in [
+            (SurfaceDim::D3, vec![16, 8, 4, 2, 1]),
+            (SurfaceDim::D2, vec![16, 16, 16, 16, 16]),
+        ] {
+            let layout = surface_layout(
+                dim,
+                64,
+                64,
+                16,
+                5,
+                SurfaceFormat::R8G8B8A8Unorm,
+                AaMode::X1,
+                TileMode::D2TiledThick,
+                0,
+            )
+            .unwrap();
+            assert_eq!(
+                depths,
+                layout
+                    .mipmaps
+                    .iter()
+                    .map(|m| m.depth_or_array_layers)
+                    .collect::<Vec<_>>()
+            );
+        }
 
-    // TODO: How to initialize these parameters?
-    let sample = 0;
-    let num_samples = 1 << aa as u32;
-    let tile_base = 0; // TODO: only used for depth map textures?
-    let comp_bits = 0; // TODO: only used for depth map textures?
+        // Deswizzled 3D mipmaps also use the halved depth.
+        let size: usize = (0..5).map(|i| (64 >> i) * (64 >> i) * (16 >> i) * 4).sum();
+        let data: Vec<_> = (0..size).map(|i| (i % 251) as u8).collect();
+        let surface = swizzle_surface(
+            SurfaceDim::D3,
+            64,
+            64,
+            16,
+            5,
+            SurfaceFormat::R8G8B8A8Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThick,
+            0,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(data, surface.as_gx2_surface().deswizzle().unwrap());
+    }
 
-    // TODO: How many of these fields are set from functions?
-    // TODO: Find a way to get values used from cemu to create test cases?
-    let p_in = ComputeSurfaceAddrFromCoordInput {
-        x: width.saturating_sub(1),
-        y: height.saturating_sub(1),
-        slice: depth_or_array_layers.saturating_sub(1),
-        sample,
-        bpp,
-        pitch,
-        height,
-        num_slices: depth_or_array_layers,
-        num_samples,
-        tile_mode,
-        is_depth: false,
-        tile_base,
-        comp_bits,
-        pipe_swizzle,
-        bank_swizzle,
-    };
+    #[test]
+    fn unsupported_tile_mode() {
+        for tile_mode in [TileMode::PowerSave, TileMode::Count] {
+            assert_eq!(
+                Err(SwizzleError::UnsupportedTileMode { tile_mode }),
+                deswizzle_mipmap(16, 16, 1, &[0u8; 1024], 0, 16, tile_mode, 4, AaMode::X1)
+            );
+            assert_eq!(
+                Err(SwizzleError::UnsupportedTileMode { tile_mode }),
+                surface_layout(
+                    SurfaceDim::D2,
+                    16,
+                    16,
+                    1,
+                    1,
+                    SurfaceFormat::R8G8B8A8Unorm,
+                    AaMode::X1,
+                    tile_mode,
+                    0
+                )
+            );
+        }
+    }
 
-    // TODO: Will the corner always be the largest address?
-    addrlib::dispatch_compute_surface_addrfrom_coord(&p_in) as usize
-}
+    #[cfg(all(feature = "trace", feature = "std"))]
+    #[test]
+    fn trace_surface_info() {
+        use alloc::string::{String, ToString};
+        use std::sync::Mutex;
 
-#[allow(clippy::too_many_arguments)]
-fn swizzle_surface_inner<const SWIZZLE: bool>(
-    width: u32,
-    height: u32,
-    depth_or_array_layers: u32,
-    source: &[u8],
-    output: &mut [u8],
-    swizzle: u32,
-    pitch: u32,
-    tile_mode: TileMode,
-    bytes_per_pixel: u32,
-    aa: AaMode,
-) -> Result<(), SwizzleError> {
-    // TODO: validate dimensions?
-    // TODO: compute surface info to fill in these params?
-    // TODO: rounding or padding of dimensions?
-    // TODO: handle div round up based on block dimensions?
+        static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
-    // TODO: always bytes per pixel * 8?
-    let bpp = bytes_per_pixel * u8::BITS;
+        struct Logger;
 
-    // TODO: name in gx2?
-    let (pipe_swizzle, bank_swizzle) = addrlib::pipe_bank_swizzle(swizzle);
+        impl log::Log for Logger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
 
-    // TODO: How to initialize these parameters?
-    let sample = 0;
-    let num_samples = 1 << aa as u32; // TODO: is this based on self.aa?
-    let tile_base = 0; // TODO: only used for depth map textures?
-    let comp_bits = 0; // TODO: only used for depth map textures?
+            fn log(&self, record: &log::Record) {
+                MESSAGES.lock().unwrap().push(record.args().to_string());
+            }
 
-    // TODO: addrlib uses input and output structs to "dispatch" swizzling?
-    // TODO: only the input pin values matter?
-    // TODO: cemu uses this structure as well?
-    // TODO: should these define the public API?
+            fn flush(&self) {}
+        }
 
-    // TODO: Is it correct to use depth and layers as slices?
-    for z in 0..depth_or_array_layers {
-        for y in 0..height {
-            for x in 0..width {
-                // TODO: How many of these fields are set from functions?
-                // TODO: Find a way to get values used from cemu to create test cases?
-                let p_in = ComputeSurfaceAddrFromCoordInput {
-                    x,
-                    y,
-                    slice: z,
-                    sample,
-                    bpp,
-                    pitch,
-                    height,
-                    num_slices: depth_or_array_layers,
-                    num_samples,
-                    tile_mode,
-                    is_depth: false,
-                    tile_base,
-                    comp_bits,
-                    pipe_swizzle,
-                    bank_swizzle,
-                };
+        log::set_logger(&Logger).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        surface_layout(
+            SurfaceDim::D2,
+            17,
+            33,
+            1,
+            1,
+            SurfaceFormat::R8G8B8A8Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            0,
+        )
+        .unwrap();
+
+        let messages = MESSAGES.lock().unwrap();
+        for stage in [
+            "compute_surface_mip_level_tile_mode: base_tile_mode=D2TiledThin1 bpp=32 level=0 width=17 height=33",
+            "compute_surface_alignments_macrotiled: tile_mode=D2TiledThin1 bpp=32",
+            "pad_dimensions: pitch=32 height=48 slices=1",
+            "hwl_compute_surface_info: ComputeSurfaceInfoInput",
+        ] {
+            assert!(messages.iter().any(|m| m.starts_with(stage)), "{stage}");
+        }
+    }
 
-                let address = addrlib::dispatch_compute_surface_addrfrom_coord(&p_in) as usize;
-                let linear_address =
-                    ((z * width * height + y * width + x) * bytes_per_pixel) as usize;
+    #[test]
+    fn bank_swapped_pitch() {
+        let pitch = |tile_mode| {
+            surface_layout(
+                SurfaceDim::D2,
+                130,
+                64,
+                1,
+                1,
+                SurfaceFormat::R8G8B8A8Unorm,
+                AaMode::X1,
+                tile_mode,
+                0,
+            )
+            .unwrap()
+            .pitch
+        };
+        // The pitch is aligned to the bank swap width of 128 pixels.
+        // TODO: Compare with the pitch GX2 stores for bank swapped surfaces.
+        assert_eq!(160, pitch(TileMode::D2TiledThin1));
+        assert_eq!(256, pitch(TileMode::B2TiledThin1));
+    }
 
-                if SWIZZLE {
-                    // TODO: This should never be out of bounds on valid inputs?
-                    output[address..address + bytes_per_pixel as usize].copy_from_slice(
-                        &source[linear_address..linear_address + bytes_per_pixel as usize],
-                    );
-                } else {
-                    // TODO: This should never be out of bounds on valid inputs?
-                    output[linear_address..linear_address + bytes_per_pixel as usize]
-                        .copy_from_slice(&source[address..address + bytes_per_pixel as usize]);
+    #[test]
+    fn bank_swapped_banks_match_addrlib() {
+        // This only checks consistency with the addrlib bank swap calculations.
+        // See bank_swapped_addresses for expected addresses.
+        // The bank is XORed with the swap order every bank swap width pixels.
+        let bank_swap_order = [0, 1, 3, 2];
+        for (tile_mode, base_tile_mode, pitch, bank_swap_width) in [
+            (TileMode::B2TiledThin1, TileMode::D2TiledThin1, 256, 128),
+            (TileMode::B2TiledThin1, TileMode::D2TiledThin1, 64, 64),
+            (TileMode::B2TiledThin2, TileMode::D2TiledThin2, 256, 128),
+            (TileMode::B2TiledThin4, TileMode::D2TiledThin4, 256, 64),
+            (TileMode::B2TiledThick, TileMode::D2TiledThick, 256, 64),
+            (TileMode::B3TiledThin1, TileMode::D3TiledThin1, 256, 128),
+            (TileMode::B3TiledThick, TileMode::D3TiledThick, 256, 64),
+        ] {
+            for z in 0..8 {
+                for y in (0..64).step_by(5) {
+                    for x in (0..pitch).step_by(7) {
+                        let explain = |tile_mode| {
+                            explain_address(x, y, z, 64, 8, 0x300, pitch, tile_mode, 4, AaMode::X1)
+                                .unwrap()
+                        };
+                        let swapped = explain(tile_mode);
+                        let base = explain(base_tile_mode);
+
+                        let bank_swap = bank_swap_order[(x / bank_swap_width) as usize % 4];
+                        assert_eq!(bank_swap, swapped.bank_swap, "{tile_mode:?} {x} {y} {z}");
+                        assert_eq!(base.bank ^ bank_swap, swapped.bank);
+                        assert_eq!(base.pipe, swapped.pipe);
+                        assert_eq!(base.tile_offset, swapped.tile_offset);
+                        assert_eq!(base.element_offset, swapped.element_offset);
+                    }
                 }
             }
         }
     }
-    Ok(())
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn bank_swapped_addresses() {
+        // Expected addresses derived by hand from addrlib's ComputeSurfaceAddrFromCoordMacroTiled
+        // and ComputeSurfaceBankSwappedWidth for a 512x64 RGBA8 surface with swizzle 0x300.
+        // The bank swap width is 128 pixels, so x=130, x=300, and x=450 use swaps 1, 3, and 2.
+        for (tile_mode, addresses) in [
+            (
+                TileMode::B2TiledThin1,
+                [768, 1532, 8216, 245756, 51872, 95240, 392188],
+            ),
+            (
+                TileMode::B3TiledThin1,
+                [768, 1532, 8216, 245500, 51872, 95240, 391676],
+            ),
+        ] {
+            for ((x, y, z), address) in [
+                (0, 0, 0),
+                (31, 15, 0),
+                (130, 9, 0),
+                (255, 63, 1),
+                (300, 20, 0),
+                (450, 40, 0),
+                (511, 63, 2),
+            ]
+            .into_iter()
+            .zip(addresses)
+            {
+                assert_eq!(
+                    address,
+                    explain_address(x, y, z, 64, 4, 0x300, 512, tile_mode, 4, AaMode::X1)
+                        .unwrap()
+                        .address,
+                    "{tile_mode:?} {x} {y} {z}"
+                );
+            }
+        }
+    }
 
-    // TODO: Add a test for micro tiling.
     #[test]
-    fn deswizzle_empty() {
+    fn bank_swapped_zero_pitch() {
+        // Check that calculating the bank swap width terminates.
         assert!(deswizzle_mipmap(
+            16,
+            16,
+            1,
+            &[0u8; 4096],
             0,
             0,
-            0,
-            &[],
-            853504,
-            256,
-            TileMode::D2TiledThin1,
-            8,
+            TileMode::B2TiledThin1,
+            4,
             AaMode::X1
         )
-        .unwrap()
-        .is_empty());
+        .is_ok());
     }
 
     #[test]
-    fn deswizzle_macro_tiled_1024x1024_bc1() {
-        let expected = include_bytes!("data/1024x1024_bc1_tm4_p256_s853504_deswizzled.bin");
-        let swizzled = include_bytes!("data/1024x1024_bc1_tm4_p256_s853504_swizzled.bin");
-
-        assert_eq!(
-            expected,
-            &deswizzle_mipmap(
-                1024 / 4,
-                1024 / 4,
-                1,
-                swizzled,
-                853504,
-                256,
-                TileMode::D2TiledThin1,
-                8,
-                AaMode::X1
-            )
-            .unwrap()[..]
-        );
+    fn macro_tiled_rotation() {
+        for (tile_mode, rotation, thickness, macro_width, macro_height) in [
+            (TileMode::D2TiledThin1, 2, 1, 32, 16),
+            (TileMode::D2TiledThin2, 2, 1, 16, 32),
+            (TileMode::D2TiledThin4, 2, 1, 8, 64),
+            (TileMode::D2TiledThick, 2, 4, 32, 16),
+            (TileMode::D3TiledThin1, 1, 1, 32, 16),
+            (TileMode::D3TiledThick, 1, 4, 32, 16),
+        ] {
+            for y in (0..128).step_by(3) {
+                for x in (0..64).step_by(5) {
+                    let mut bank_pipe = None;
+                    for z in 0..8 {
+                        let b =
+                            explain_address(x, y, z, 128, 8, 0x300, 64, tile_mode, 4, AaMode::X1)
+                                .unwrap();
+                        assert_eq!(rotation, b.rotation, "{tile_mode:?}");
+                        assert_eq!(3 + z / thickness * rotation, b.swizzle_contribution);
+                        assert_eq!(x / macro_width, b.macro_tile_x);
+                        assert_eq!(y / macro_height, b.macro_tile_y);
+
+                        // Removing the swizzle and slice rotation gives the same pipe and bank.
+                        let unrotated = (b.pipe + 2 * b.bank) ^ (b.swizzle_contribution % 8);
+                        assert_eq!(*bank_pipe.get_or_insert(unrotated), unrotated);
+                    }
+                }
+            }
+        }
     }
 
     #[test]
-    fn deswizzle_macro_tiled_16x16x16_rgba8() {
-        let expected = include_bytes!("data/16x16x16_rgba8_tm7_p32_s852224_deswizzled.bin");
-        let swizzled = include_bytes!("data/16x16x16_rgba8_tm7_p32_s852224_swizzled.bin");
+    fn mip_level_tile_mode_3d() {
+        let mip_tile_mode = |hw, tile_mode, level, size, num_slices| {
+            addrlib::compute_surface_mip_level_tile_mode(
+                hw, tile_mode, 32, level, size, size, num_slices, 1, false, false,
+            )
+        };
+
+        // 3D modes only convert to 2D if the rotation is a multiple of the pipe count.
+        let latte = HwConfig::LATTE;
+        let one_pipe = HwConfig {
+            num_pipes: 1,
+            ..HwConfig::LATTE
+        };
+        assert_eq!(
+            TileMode::D3TiledThin1,
+            mip_tile_mode(&latte, TileMode::D3TiledThin1, 0, 256, 1)
+        );
+        assert_eq!(
+            TileMode::D2TiledThin1,
+            mip_tile_mode(&one_pipe, TileMode::D3TiledThin1, 0, 256, 1)
+        );
+        assert_eq!(
+            TileMode::B2TiledThick,
+            mip_tile_mode(&one_pipe, TileMode::B3TiledThick, 0, 256, 4)
+        );
 
+        // Smaller mip levels use thin or 1D modes.
         assert_eq!(
-            expected,
-            &deswizzle_mipmap(
-                16,
-                16,
-                16,
-                swizzled,
-                852224,
-                32,
-                TileMode::D2TiledThick,
-                4,
-                AaMode::X1
-            )
-            .unwrap()[..]
+            TileMode::D3TiledThin1,
+            mip_tile_mode(&latte, TileMode::D3TiledThick, 1, 256, 2)
+        );
+        assert_eq!(
+            TileMode::D1TiledThin1,
+            mip_tile_mode(&latte, TileMode::D3TiledThin1, 3, 8, 1)
+        );
+        assert_eq!(
+            TileMode::D2TiledThin2,
+            mip_tile_mode(&latte, TileMode::D2TiledThin2, 1, 256, 1)
+        );
+        assert_eq!(
+            TileMode::D1TiledThin1,
+            mip_tile_mode(&latte, TileMode::D2TiledThin2, 1, 16, 1)
         );
     }
 
     #[test]
-    fn deswizzle_surface_256x256_bc1_mipmaps() {
-        let expected = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_deswizzled.bin");
-        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
-
-        let surface = Gx2Surface {
-            dim: SurfaceDim::D2,
-            width: 256,
-            height: 256,
-            depth_or_array_layers: 1,
-            mipmap_count: 8,
-            format: SurfaceFormat::Bc1Unorm,
-            aa: AaMode::X1,
-            usage: 1,
-            image_data: swizzled,
-            mipmap_data: &swizzled[32768..],
-            tile_mode: TileMode::D2TiledThin1,
-            swizzle: 132352,
-            alignment: 4096,
-            pitch: 64,
-            mipmap_offsets: [
-                32768, 9472, 11520, 12032, 12544, 13056, 13568, 0, 0, 0, 0, 0, 0,
-            ],
-        };
-        assert_eq!(expected, &surface.deswizzle().unwrap()[..]);
+    fn max_mipmap_counts() {
+        assert_eq!(9, max_mipmap_count(SurfaceDim::D2, 256, 1, 1));
+        assert_eq!(1, max_mipmap_count(SurfaceDim::D2, 1, 1, 6));
+        assert_eq!(6, max_mipmap_count(SurfaceDim::D3, 4, 4, 32));
+        assert_eq!(13, max_mipmap_count(SurfaceDim::D2, 8192, 8192, 1));
     }
 
+    #[cfg(all(feature = "std", feature = "encode"))]
     #[test]
-    fn deswizzle_surface_64x64_cube_bc1_mipmaps() {
-        let expected = include_bytes!("data/64x64_cube_bc1_tm4_p32_s67328_deswizzled.bin");
-        let swizzled = include_bytes!("data/64x64_cube_bc1_tm4_p32_s67328_swizzled.bin");
-
-        let surface = Gx2Surface {
-            dim: SurfaceDim::Cube,
-            width: 64,
-            height: 64,
-            depth_or_array_layers: 6,
-            mipmap_count: 2,
-            format: SurfaceFormat::Bc1Unorm,
-            aa: AaMode::X1,
-            usage: 1,
-            image_data: swizzled,
-            mipmap_data: &swizzled[24576..],
-            tile_mode: TileMode::D2TiledThin1,
-            swizzle: 67328,
-            alignment: 4096,
-            pitch: 32,
-            mipmap_offsets: [24576, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-        };
-        assert_eq!(expected, &surface.deswizzle().unwrap()[..]);
+    fn generate_encode_swizzle_cube_bc1_mipmaps() {
+        let data: Vec<_> = (0..6 * 16 * 16)
+            .flat_map(|i| [i as u8, 0, 0, 255])
+            .collect();
+        let mipmaps = generate_mipmaps_rgba8(
+            SurfaceDim::Cube,
+            16,
+            16,
+            6,
+            5,
+            SurfaceFormat::Bc1Unorm,
+            MipmapFilter::Box,
+            &data,
+        )
+        .unwrap();
+        let encoded = encode_surface_rgba8(
+            SurfaceDim::Cube,
+            16,
+            16,
+            6,
+            5,
+            SurfaceFormat::Bc1Unorm,
+            &mipmaps,
+            EncodeQuality::Normal,
+        )
+        .unwrap();
+        // Each face has 16 + 4 + 1 + 1 + 1 blocks.
+        assert_eq!(6 * 23 * 8, encoded.len());
+
+        let surface = swizzle_surface(
+            SurfaceDim::Cube,
+            16,
+            16,
+            6,
+            5,
+            SurfaceFormat::Bc1Unorm,
+            AaMode::X1,
+            TileMode::D2TiledThin1,
+            0,
+            &encoded,
+        )
+        .unwrap();
+        assert_eq!(encoded, surface.as_gx2_surface().deswizzle().unwrap());
     }
 
     #[test]
@@ -1014,6 +2983,117 @@ mod tests {
         assert_eq!(None, SurfaceFormat::from_repr(0xff));
     }
 
+    #[test]
+    fn gx2_tile_mode_resolve() {
+        let resolve = |tile_mode: Gx2TileMode, dim, width, height, depth, usage| {
+            tile_mode.resolve(
+                dim,
+                width,
+                height,
+                depth,
+                SurfaceFormat::R8G8B8A8Unorm,
+                AaMode::X1,
+                usage,
+            )
+        };
+        let resolve_bc1 = |width, height| {
+            Gx2TileMode::Default.resolve(
+                SurfaceDim::D2,
+                width,
+                height,
+                1,
+                SurfaceFormat::Bc1Unorm,
+                AaMode::X1,
+                1,
+            )
+        };
+        let default = Gx2TileMode::Default;
+        assert_eq!(
+            TileMode::LinearAligned,
+            resolve(default, SurfaceDim::D1, 256, 1, 1, 1)
+        );
+        assert_eq!(
+            TileMode::D2TiledThin1,
+            resolve(default, SurfaceDim::D2, 256, 256, 1, 1)
+        );
+        assert_eq!(
+            TileMode::D1TiledThin1,
+            resolve(default, SurfaceDim::D2, 8, 8, 1, 1)
+        );
+        assert_eq!(
+            TileMode::D1TiledThin1,
+            resolve(default, SurfaceDim::D2, 256, 8, 1, 1)
+        );
+        // The alignment is in blocks for block compressed formats.
+        assert_eq!(TileMode::D1TiledThin1, resolve_bc1(16, 16));
+        assert_eq!(TileMode::D1TiledThin1, resolve_bc1(256, 16));
+        assert_eq!(TileMode::D2TiledThin1, resolve_bc1(256, 256));
+        assert_eq!(
+            TileMode::D2TiledThick,
+            resolve(default, SurfaceDim::D3, 256, 256, 8, 1)
+        );
+        assert_eq!(
+            TileMode::D2TiledThin1,
+            resolve(default, SurfaceDim::D3, 256, 256, 8, 2)
+        );
+        assert_eq!(
+            TileMode::D1TiledThick,
+            resolve(default, SurfaceDim::D3, 8, 8, 8, 1)
+        );
+        assert_eq!(
+            TileMode::LinearGeneral,
+            resolve(Gx2TileMode::LinearSpecial, SurfaceDim::D2, 16, 16, 1, 1)
+        );
+        assert_eq!(
+            TileMode::B2TiledThin4,
+            resolve(Gx2TileMode::B2TiledThin4, SurfaceDim::D2, 16, 16, 1, 1)
+        );
+    }
+
+    #[test]
+    fn tile_mode_properties() {
+        let hw = HwConfig::LATTE;
+        assert_eq!(1, TileMode::LinearAligned.thickness());
+        assert_eq!(4, TileMode::B2TiledThick.thickness());
+        assert_eq!(8, TileMode::D3TiledXThick.thickness());
+
+        assert!(TileMode::LinearGeneral.is_linear());
+        assert!(TileMode::D1TiledThick.is_micro_tiled());
+        assert!(!TileMode::D1TiledThick.is_macro_tiled());
+        assert!(TileMode::D2TiledXthick.is_macro_tiled());
+        assert!(!TileMode::PowerSave.is_macro_tiled());
+
+        assert!(TileMode::B3TiledThin1.is_bank_swapped());
+        assert_eq!(
+            TileMode::D3TiledThin1,
+            TileMode::B3TiledThin1.to_non_bank_swapped()
+        );
+        assert_eq!(
+            TileMode::D1TiledThin1,
+            TileMode::D1TiledThin1.to_non_bank_swapped()
+        );
+
+        assert_eq!(4, TileMode::D2TiledThin4.macro_tile_aspect_ratio());
+        assert_eq!(2, TileMode::D2TiledThin1.rotation(&hw));
+        assert_eq!(1, TileMode::D3TiledThin1.rotation(&hw));
+        assert_eq!(0, TileMode::D1TiledThin1.rotation(&hw));
+
+        assert_eq!(None, TileMode::LinearAligned.micro_tile_dimensions());
+        assert_eq!(
+            Some((8, 8, 4)),
+            TileMode::D1TiledThick.micro_tile_dimensions()
+        );
+        assert_eq!(None, TileMode::D1TiledThick.macro_tile_dimensions(&hw));
+        assert_eq!(
+            Some((32, 16, 1)),
+            TileMode::D2TiledThin1.macro_tile_dimensions(&hw)
+        );
+        assert_eq!(
+            Some((8, 64, 1)),
+            TileMode::B2TiledThin4.macro_tile_dimensions(&hw)
+        );
+    }
+
     #[test]
     fn tile_mode_from_repr() {
         assert_eq!(Some(TileMode::D2TiledThin1), TileMode::from_repr(4));
@@ -0,0 +1,501 @@
+use crate::{mip_dimensions, validate_dimensions, AaMode, SurfaceDim, SurfaceFormat, SwizzleError};
+use std::{vec, vec::Vec};
+
+// Mipmaps are generated in floating point from the previous level
+// to avoid accumulating rounding errors from 8-bit intermediate values.
+
+/// The filter used to downsample each mipmap from the previous level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MipmapFilter {
+    /// Average each 2x2 block of pixels or 2x2x2 block of voxels.
+    #[default]
+    Box,
+    /// Kaiser windowed sinc filter with a radius of 3 pixels.
+    /// Sharper than [MipmapFilter::Box] with less ringing than [MipmapFilter::Lanczos3].
+    Kaiser,
+    /// Lanczos windowed sinc filter with a radius of 3 pixels.
+    Lanczos3,
+}
+
+impl MipmapFilter {
+    fn radius(&self) -> f32 {
+        match self {
+            MipmapFilter::Box => 0.5,
+            MipmapFilter::Kaiser => 3.0,
+            MipmapFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        match self {
+            MipmapFilter::Box => {
+                if (-0.5..0.5).contains(&x) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            MipmapFilter::Kaiser => {
+                // https://en.wikipedia.org/wiki/Kaiser_window
+                const ALPHA: f32 = 4.0;
+                let t = x / self.radius();
+                if t.abs() >= 1.0 {
+                    0.0
+                } else {
+                    sinc(x) * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+                }
+            }
+            MipmapFilter::Lanczos3 => {
+                if x.abs() >= self.radius() {
+                    0.0
+                } else {
+                    sinc(x) * sinc(x / self.radius())
+                }
+            }
+        }
+    }
+}
+
+/// Generate mipmaps from the RGBA8 pixels for the base mip level.
+///
+/// The output contains all layers and mipmaps in the same layout as [Gx2Surface::deswizzle](crate::Gx2Surface::deswizzle)
+/// with the base level first.
+/// Encode the output to `format` if needed before calling [swizzle_surface](crate::swizzle_surface).
+///
+/// Each mipmap halves the width and height.
+/// The depth is also halved for [SurfaceDim::D3] but not for array layers or cube faces.
+/// Formats with [SurfaceFormat::is_srgb] are filtered in linear space to preserve brightness.
+///
+/// The `mipmap_count` includes the base level and can be at most [MAX_MIPMAP_COUNT](crate::MAX_MIPMAP_COUNT).
+/// Use [max_mipmap_count](crate::max_mipmap_count) for a full mipmap chain.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_mipmaps_rgba8(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    filter: MipmapFilter,
+    data: &[u8],
+) -> Result<Vec<u8>, SwizzleError> {
+    generate_mipmaps(
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        filter,
+        data,
+        rgba8_to_linear,
+        linear_to_rgba8,
+    )
+}
+
+/// Generate mipmaps from the RGBA32F pixels for the base mip level.
+///
+/// Values are not clamped, so this preserves the range of HDR formats.
+///
+/// See [generate_mipmaps_rgba8] for details on how the dimensions and data are handled.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_mipmaps_rgba32f(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    filter: MipmapFilter,
+    data: &[f32],
+) -> Result<Vec<f32>, SwizzleError> {
+    generate_mipmaps(
+        dim,
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        filter,
+        data,
+        rgba32f_to_linear,
+        linear_to_rgba32f,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_mipmaps<T: Copy>(
+    dim: SurfaceDim,
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    mipmap_count: u32,
+    format: SurfaceFormat,
+    filter: MipmapFilter,
+    data: &[T],
+    to_linear: fn([T; 4], bool) -> [f32; 4],
+    from_linear: fn([f32; 4], bool) -> [T; 4],
+) -> Result<Vec<T>, SwizzleError> {
+    validate_dimensions(
+        width,
+        height,
+        depth_or_array_layers,
+        mipmap_count,
+        format,
+        AaMode::X1,
+        1,
+    )?;
+    if width == 0 || height == 0 || depth_or_array_layers == 0 || mipmap_count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let expected_len = width as usize * height as usize * depth_or_array_layers as usize * 4;
+    if data.len() < expected_len {
+        return Err(SwizzleError::NotEnoughData {
+            expected_size: expected_len * core::mem::size_of::<T>(),
+            actual_size: core::mem::size_of_val(data),
+        });
+    }
+
+    // Volume textures filter all slices together.
+    let depth = if dim == SurfaceDim::D3 {
+        depth_or_array_layers
+    } else {
+        1
+    };
+
+    let srgb = format.is_srgb();
+    let layer_len = width as usize * height as usize * depth as usize * 4;
+
+    // Store mipmaps for each layer as [layer][mip].
+    let mut layer_mipmaps = Vec::new();
+    for layer_data in data[..expected_len].chunks_exact(layer_len) {
+        let mut base = Image {
+            width: width as usize,
+            height: height as usize,
+            depth: depth as usize,
+            pixels: layer_data
+                .chunks_exact(4)
+                .map(|p| to_linear(p.try_into().unwrap(), srgb))
+                .collect(),
+        };
+
+        let mut mipmaps = Vec::new();
+        for mip in 0..mipmap_count {
+            if mip > 0 {
+                let (mip_width, mip_height, mip_depth) =
+                    mip_dimensions(dim, width, height, depth_or_array_layers, mip);
+                let mip_depth = if dim == SurfaceDim::D3 { mip_depth } else { 1 };
+                base = base.resize(
+                    [mip_width as usize, mip_height as usize, mip_depth as usize],
+                    filter,
+                );
+            }
+            let pixels: Vec<_> = base
+                .pixels
+                .iter()
+                .flat_map(|p| from_linear(*p, srgb))
+                .collect();
+            mipmaps.push(pixels);
+        }
+        layer_mipmaps.push(mipmaps);
+    }
+
+    // Cube maps are [layer][mip] and all other surfaces are [mip][layer].
+    let mut output = Vec::new();
+    if dim == SurfaceDim::Cube {
+        for mip_data in layer_mipmaps.iter().flatten() {
+            output.extend_from_slice(mip_data);
+        }
+    } else {
+        for mip in 0..mipmap_count as usize {
+            for mipmaps in &layer_mipmaps {
+                output.extend_from_slice(&mipmaps[mip]);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+struct Image {
+    width: usize,
+    height: usize,
+    depth: usize,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Image {
+    fn resize(self, dimensions: [usize; 3], filter: MipmapFilter) -> Self {
+        // Filters are separable, so resize one axis at a time.
+        let mut image = self;
+        for (axis, size) in dimensions.into_iter().enumerate() {
+            image = image.resize_axis(axis, size, filter);
+        }
+        image
+    }
+
+    fn resize_axis(self, axis: usize, size: usize, filter: MipmapFilter) -> Self {
+        let mut dimensions = [self.width, self.height, self.depth];
+        if dimensions[axis] == size {
+            return self;
+        }
+        let weights = filter_weights(dimensions[axis], size, filter);
+
+        let strides = [1, self.width, self.width * self.height];
+        dimensions[axis] = size;
+        let [width, height, depth] = dimensions;
+
+        let mut pixels = Vec::with_capacity(width * height * depth);
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let mut coords = [x, y, z];
+                    let mut pixel = [0.0; 4];
+                    for (i, weight) in &weights[coords[axis]] {
+                        coords[axis] = *i;
+                        let index = coords[0] * strides[0]
+                            + coords[1] * strides[1]
+                            + coords[2] * strides[2];
+                        for (p, value) in pixel.iter_mut().zip(self.pixels[index]) {
+                            *p += value * weight;
+                        }
+                    }
+                    pixels.push(pixel);
+                }
+            }
+        }
+
+        Image {
+            width,
+            height,
+            depth,
+            pixels,
+        }
+    }
+}
+
+/// Calculate the source pixels and normalized weights for each output pixel.
+fn filter_weights(size: usize, new_size: usize, filter: MipmapFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = size as f32 / new_size as f32;
+    // Scale the filter when downsampling to avoid aliasing.
+    let filter_scale = scale.max(1.0);
+    let support = filter.radius() * filter_scale;
+
+    (0..new_size)
+        .map(|x| {
+            let center = (x as f32 + 0.5) * scale;
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(size);
+
+            let mut weights: Vec<_> = (start..end)
+                .map(|i| (i, filter.weight((i as f32 + 0.5 - center) / filter_scale)))
+                .filter(|(_, w)| *w != 0.0)
+                .collect();
+
+            let sum: f32 = weights.iter().map(|(_, w)| w).sum();
+            if sum == 0.0 {
+                // Use the nearest pixel if the filter misses all pixels.
+                vec![((center as usize).min(size - 1), 1.0)]
+            } else {
+                for (_, w) in &mut weights {
+                    *w /= sum;
+                }
+                weights
+            }
+        })
+        .collect()
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * core::f32::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// Modified Bessel function of the first kind of order 0.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x_squared = x * x / 4.0;
+    for k in 1..32 {
+        term *= half_x_squared / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-7 {
+            break;
+        }
+    }
+    sum
+}
+
+fn rgba8_to_linear(rgba: [u8; 4], srgb: bool) -> [f32; 4] {
+    let mut pixel = rgba.map(|u| u as f32 / 255.0);
+    if srgb {
+        // Alpha is always linear.
+        for c in &mut pixel[..3] {
+            *c = srgb_to_linear(*c);
+        }
+    }
+    pixel
+}
+
+fn linear_to_rgba8(pixel: [f32; 4], srgb: bool) -> [u8; 4] {
+    let mut pixel = pixel;
+    if srgb {
+        for c in &mut pixel[..3] {
+            *c = linear_to_srgb(c.clamp(0.0, 1.0));
+        }
+    }
+    // Filters with negative weights can produce values outside the valid range.
+    pixel.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn rgba32f_to_linear(pixel: [f32; 4], srgb: bool) -> [f32; 4] {
+    let mut pixel = pixel;
+    if srgb {
+        for c in &mut pixel[..3] {
+            *c = srgb_to_linear(c.max(0.0));
+        }
+    }
+    pixel
+}
+
+fn linear_to_rgba32f(pixel: [f32; 4], srgb: bool) -> [f32; 4] {
+    let mut pixel = pixel;
+    if srgb {
+        for c in &mut pixel[..3] {
+            *c = linear_to_srgb(c.max(0.0));
+        }
+    }
+    pixel
+}
+
+// https://en.wikipedia.org/wiki/SRGB#Transformation
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_mipmaps_box_2d() {
+        // 4x2 pixels with a gradient in red.
+        let data: Vec<_> = [0, 64, 128, 255, 0, 64, 128, 255]
+            .iter()
+            .flat_map(|r| [*r, 0, 0, 255])
+            .collect();
+        let mipmaps = generate_mipmaps_rgba8(
+            SurfaceDim::D2,
+            4,
+            2,
+            1,
+            3,
+            SurfaceFormat::R8G8B8A8Unorm,
+            MipmapFilter::Box,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                0, 0, 0, 255, 64, 0, 0, 255, 128, 0, 0, 255, 255, 0, 0, 255, // mip 0
+                0, 0, 0, 255, 64, 0, 0, 255, 128, 0, 0, 255, 255, 0, 0, 255, // mip 0
+                32, 0, 0, 255, 192, 0, 0, 255, // mip 1
+                112, 0, 0, 255 // mip 2
+            ],
+            mipmaps
+        );
+    }
+
+    #[test]
+    fn generate_mipmaps_srgb() {
+        let data = [0, 0, 0, 0, 255, 255, 255, 255];
+        let generate = |format| {
+            generate_mipmaps_rgba8(SurfaceDim::D2, 2, 1, 1, 2, format, MipmapFilter::Box, &data)
+                .unwrap()
+        };
+        // Averaging in linear space is brighter than averaging sRGB values.
+        assert_eq!(
+            &[128, 128, 128, 128],
+            &generate(SurfaceFormat::Bc1Unorm)[8..]
+        );
+        assert_eq!(
+            &[188, 188, 188, 128],
+            &generate(SurfaceFormat::Bc1Srgb)[8..]
+        );
+    }
+
+    #[test]
+    fn generate_mipmaps_volume_and_cube() {
+        // Volume mipmaps halve the depth.
+        let data = vec![255u8; 4 * 4 * 4 * 4];
+        let mipmaps = generate_mipmaps_rgba8(
+            SurfaceDim::D3,
+            4,
+            4,
+            4,
+            3,
+            SurfaceFormat::R8G8B8A8Unorm,
+            MipmapFilter::Lanczos3,
+            &data,
+        )
+        .unwrap();
+        assert_eq!((64 + 8 + 1) * 4, mipmaps.len());
+        assert!(mipmaps.iter().all(|u| *u == 255));
+
+        // Cube maps are ordered by face and then mipmap.
+        let data: Vec<_> = (0..6u8).flat_map(|face| [face; 2 * 2 * 4]).collect();
+        let mipmaps = generate_mipmaps_rgba8(
+            SurfaceDim::Cube,
+            2,
+            2,
+            6,
+            2,
+            SurfaceFormat::R8G8B8A8Unorm,
+            MipmapFilter::Kaiser,
+            &data,
+        )
+        .unwrap();
+        let expected: Vec<_> = (0..6u8).flat_map(|face| [face; 5 * 4]).collect();
+        assert_eq!(expected, mipmaps);
+    }
+
+    #[test]
+    fn generate_mipmaps_too_many_mipmaps() {
+        assert_eq!(
+            Err(SwizzleError::InvalidSurface {
+                width: 8192,
+                height: 8192,
+                depth: 1,
+                format: SurfaceFormat::R8G8B8A8Unorm,
+                mipmap_count: 14
+            }),
+            generate_mipmaps_rgba8(
+                SurfaceDim::D2,
+                8192,
+                8192,
+                1,
+                14,
+                SurfaceFormat::R8G8B8A8Unorm,
+                MipmapFilter::Box,
+                &[]
+            )
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{for_each_run_in_rows, AaMode, HwConfig, SwizzleError, TileMode};
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+use rayon::prelude::*;
+
+// The rows for each block should be a multiple of the micro tile height.
+// This keeps the tiles for each block independent from other blocks.
+const BLOCK_ROWS: u32 = 64;
+
+// Split each slice into blocks of rows that can be processed in parallel.
+fn blocks(height: u32, depth_or_array_layers: u32) -> Vec<(u32, Range<u32>)> {
+    (0..depth_or_array_layers)
+        .flat_map(|z| {
+            (0..height)
+                .step_by(BLOCK_ROWS as usize)
+                .map(move |y| (z, y..(y + BLOCK_ROWS).min(height)))
+        })
+        .collect()
+}
+
+// Group blocks with overlapping tiled address ranges like the slices of thick tiles.
+// Each group has the indices of its blocks and a tiled address range disjoint from other groups.
+fn tiled_groups(ranges: &[Range<usize>]) -> Vec<(Vec<usize>, Range<usize>)> {
+    let mut indices: Vec<_> = (0..ranges.len()).collect();
+    indices.sort_by_key(|i| ranges[*i].start);
+
+    let mut groups: Vec<(Vec<usize>, Range<usize>)> = Vec::new();
+    for i in indices {
+        let range = ranges[i].clone();
+        if range.is_empty() {
+            continue;
+        }
+        match groups.last_mut() {
+            Some((group, group_range)) if range.start < group_range.end => {
+                group.push(i);
+                group_range.end = group_range.end.max(range.end);
+            }
+            _ => groups.push((vec![i], range)),
+        }
+    }
+    // Blocks in a group may write the same addresses, so keep the serial order.
+    for (group, _) in &mut groups {
+        group.sort_unstable();
+    }
+    groups
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn swizzle_surface_inner<const SWIZZLE: bool>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    output: &mut Vec<u8>,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    hw: &HwConfig,
+) -> Result<(), SwizzleError> {
+    let blocks = blocks(height, depth_or_array_layers);
+
+    let rows_in_block = |block: &(u32, Range<u32>), f: &mut dyn FnMut(usize, usize, usize) -> _| {
+        for_each_run_in_rows(
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            block.0,
+            block.1.clone(),
+            hw,
+            f,
+        )
+    };
+
+    if SWIZZLE {
+        // Find the tiled address range written by each block.
+        let ranges = blocks
+            .par_iter()
+            .map(|block| {
+                #[allow(clippy::reversed_empty_ranges)]
+                let mut range = usize::MAX..0;
+                rows_in_block(block, &mut |address, _, size| {
+                    range.start = range.start.min(address);
+                    range.end = range.end.max(address + size);
+                    Ok(())
+                })
+                .map(|_| range)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The output grows to fit the highest address like the serial implementation.
+        let end = ranges.iter().map(|r| r.end).max().unwrap_or_default();
+        if output.len() < end {
+            output.resize(end, 0);
+        }
+
+        // Each group of blocks writes to a disjoint range of the tiled output.
+        let groups = tiled_groups(&ranges);
+        let mut chunks = Vec::with_capacity(groups.len());
+        let mut remaining = output.as_mut_slice();
+        let mut offset = 0;
+        for (group, range) in groups {
+            let (_, rest) = core::mem::take(&mut remaining).split_at_mut(range.start - offset);
+            let (chunk, rest) = rest.split_at_mut(range.len());
+            chunks.push((group, range.start, chunk));
+            remaining = rest;
+            offset = range.end;
+        }
+
+        chunks
+            .into_par_iter()
+            .try_for_each(|(group, start, chunk)| {
+                group.into_iter().try_for_each(|i| {
+                    rows_in_block(&blocks[i], &mut |address, linear_address, size| {
+                        let address = address - start;
+                        chunk[address..address + size]
+                            .copy_from_slice(&source[linear_address..linear_address + size]);
+                        Ok(())
+                    })
+                })
+            })
+    } else {
+        // Each block of rows writes to a disjoint range of the linear output.
+        let row_size = width as usize * bytes_per_pixel as usize;
+        let mut chunks = Vec::with_capacity(blocks.len());
+        let mut remaining = output.as_mut_slice();
+        let mut start = 0;
+        for block in &blocks {
+            let size = block.1.len() * row_size;
+            let (chunk, rest) = core::mem::take(&mut remaining).split_at_mut(size);
+            chunks.push((block, start, chunk));
+            remaining = rest;
+            start += size;
+        }
+
+        let results: Vec<_> = chunks
+            .into_par_iter()
+            .map(|(block, start, chunk)| {
+                rows_in_block(block, &mut |address, linear_address, size| {
+                    let linear_address = linear_address - start;
+                    let bytes =
+                        source
+                            .get(address..address + size)
+                            .ok_or(SwizzleError::NotEnoughData {
+                                expected_size: address + size,
+                                actual_size: source.len(),
+                            })?;
+                    chunk[linear_address..linear_address + size].copy_from_slice(bytes);
+                    Ok(())
+                })
+            })
+            .collect();
+
+        // Return the first error to match the serial implementation.
+        results.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swizzle_surface_texels;
+
+    #[test]
+    fn tiled_groups_overlapping() {
+        let groups = tiled_groups(&[0..4, 8..12, 2..6, 12..16, 4..4]);
+        assert_eq!(
+            vec![(vec![0, 2], 0..6), (vec![1], 8..12), (vec![3], 12..16)],
+            groups
+        );
+    }
+
+    #[test]
+    fn parallel_matches_texels_multiple_blocks() {
+        let (width, height, depth) = (40, 150, 3);
+        // Multisampled and thick surfaces have blocks with overlapping tiled addresses.
+        for (tile_mode, aa) in [
+            (TileMode::LinearAligned, AaMode::X1),
+            (TileMode::D1TiledThin1, AaMode::X1),
+            (TileMode::D2TiledThin1, AaMode::X1),
+            (TileMode::D2TiledThin1, AaMode::X4),
+            (TileMode::D2TiledThick, AaMode::X1),
+            (TileMode::B2TiledThin4, AaMode::X1),
+        ] {
+            let size = (width * height * depth * 4) as usize;
+            let linear: Vec<_> = (0..size).map(|i| (i % 251) as u8).collect();
+
+            let mut expected = Vec::new();
+            swizzle_surface_texels::<true>(
+                width,
+                height,
+                depth,
+                &linear,
+                &mut expected,
+                0x300,
+                64,
+                tile_mode,
+                4,
+                aa,
+                &HwConfig::LATTE,
+            )
+            .unwrap();
+
+            let mut tiled = Vec::new();
+            swizzle_surface_inner::<true>(
+                width,
+                height,
+                depth,
+                &linear,
+                &mut tiled,
+                0x300,
+                64,
+                tile_mode,
+                4,
+                aa,
+                &HwConfig::LATTE,
+            )
+            .unwrap();
+            assert_eq!(expected, tiled, "{tile_mode:?} {aa:?}");
+
+            let mut expected = vec![0u8; size];
+            swizzle_surface_texels::<false>(
+                width,
+                height,
+                depth,
+                &tiled,
+                &mut expected,
+                0x300,
+                64,
+                tile_mode,
+                4,
+                aa,
+                &HwConfig::LATTE,
+            )
+            .unwrap();
+
+            let mut deswizzled = vec![0u8; size];
+            swizzle_surface_inner::<false>(
+                width,
+                height,
+                depth,
+                &tiled,
+                &mut deswizzled,
+                0x300,
+                64,
+                tile_mode,
+                4,
+                aa,
+                &HwConfig::LATTE,
+            )
+            .unwrap();
+            assert_eq!(expected, deswizzled, "{tile_mode:?} {aa:?}");
+        }
+    }
+}
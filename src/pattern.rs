@@ -0,0 +1,313 @@
+use crate::{AaMode, SwizzleError, TileMode, TiledAddresses};
+use alloc::{vec, vec::Vec};
+
+/// Tiling parameters for a mipmap with the same meaning as [deswizzle_mipmap](crate::deswizzle_mipmap).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilingParams {
+    pub tile_mode: TileMode,
+    pub pitch: u32,
+    pub swizzle: u32,
+}
+
+/// A texel with a different address in captured tiled data than expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TexelMismatch {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    /// The tiled address calculated by this crate.
+    pub expected_address: usize,
+    /// The tiled address in the captured data or `None` if the texel's index was not found.
+    pub actual_address: Option<usize>,
+}
+
+/// The result of comparing captured tiled data with [compare_index_pattern].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexPatternReport {
+    /// All texels with a different address than expected in linear order.
+    pub mismatches: Vec<TexelMismatch>,
+    /// Parameters that explain the captured data if there are any mismatches.
+    pub inferred: Option<TilingParams>,
+}
+
+impl IndexPatternReport {
+    /// Returns `true` if the captured data matches the expected tiled addresses.
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Create linear data for a mipmap where each 4-byte chunk is a unique index.
+///
+/// Each chunk stores its index plus one in little endian,
+/// so zeroed padding in captured data is never confused with a texel.
+/// Render or upload this data on hardware or an emulator and capture the tiled memory
+/// to check tiled addresses with [compare_index_pattern].
+pub fn index_pattern(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    bytes_per_pixel: u32,
+) -> Vec<u8> {
+    let size = width as usize
+        * height as usize
+        * depth_or_array_layers as usize
+        * bytes_per_pixel as usize;
+    let mut data: Vec<_> = (1..)
+        .take(size.div_ceil(4))
+        .flat_map(|i: u32| i.to_le_bytes())
+        .collect();
+    data.truncate(size);
+    data
+}
+
+/// Compare the `captured` tiled data for an [index_pattern] with the tiled addresses
+/// for the given parameters.
+///
+/// The parameters are the same as [deswizzle_mipmap](crate::deswizzle_mipmap).
+/// If any texels have a different address,
+/// the report also includes the parameters found by [infer_index_pattern] if any.
+#[allow(clippy::too_many_arguments)]
+pub fn compare_index_pattern(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    captured: &[u8],
+) -> Result<IndexPatternReport, SwizzleError> {
+    let positions = chunk_positions(
+        width,
+        height,
+        depth_or_array_layers,
+        bytes_per_pixel,
+        captured,
+    );
+
+    let mut mismatches = Vec::new();
+    let addresses = TiledAddresses::new(
+        width,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+    )?;
+    for (i, texel) in addresses.enumerate() {
+        let actual_address = captured_address(&positions, i, bytes_per_pixel);
+        if actual_address != Some(texel.address) {
+            mismatches.push(TexelMismatch {
+                x: texel.x,
+                y: texel.y,
+                z: texel.z,
+                expected_address: texel.address,
+                actual_address,
+            });
+        }
+    }
+
+    let inferred = if mismatches.is_empty() {
+        None
+    } else {
+        infer_index_pattern(
+            width,
+            height,
+            depth_or_array_layers,
+            bytes_per_pixel,
+            aa,
+            captured,
+        )
+    };
+
+    Ok(IndexPatternReport {
+        mismatches,
+        inferred,
+    })
+}
+
+/// Find the first tile mode, pitch, and swizzle that produces the same addresses
+/// as the `captured` tiled data for an [index_pattern].
+///
+/// Returns `None` if no combination of parameters explains the captured data.
+pub fn infer_index_pattern(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    captured: &[u8],
+) -> Option<TilingParams> {
+    let positions = chunk_positions(
+        width,
+        height,
+        depth_or_array_layers,
+        bytes_per_pixel,
+        captured,
+    );
+
+    // Check common pitch alignments from unaligned to the largest macro tile width.
+    let mut pitches: Vec<_> = (0..=8).map(|i| width.next_multiple_of(1 << i)).collect();
+    pitches.dedup();
+
+    for tile_mode in TileMode::supported() {
+        // The swizzle only affects macro tiled modes.
+        let swizzle_count = if tile_mode.is_macro_tiled() { 8 } else { 1 };
+        for &pitch in &pitches {
+            for swizzle in (0..swizzle_count).map(|i| i << 8) {
+                let Ok(mut addresses) = TiledAddresses::new(
+                    width,
+                    height,
+                    depth_or_array_layers,
+                    swizzle,
+                    pitch,
+                    tile_mode,
+                    bytes_per_pixel,
+                    aa,
+                ) else {
+                    continue;
+                };
+                if addresses.len() > 0
+                    && addresses.by_ref().enumerate().all(|(i, texel)| {
+                        captured_address(&positions, i, bytes_per_pixel) == Some(texel.address)
+                    })
+                {
+                    return Some(TilingParams {
+                        tile_mode,
+                        pitch,
+                        swizzle,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+// Find the address in the captured data for each 4-byte chunk of the linear data.
+fn chunk_positions(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    bytes_per_pixel: u32,
+    captured: &[u8],
+) -> Vec<Option<usize>> {
+    let size = width as usize
+        * height as usize
+        * depth_or_array_layers as usize
+        * bytes_per_pixel as usize;
+    let mut positions = vec![None; size.div_ceil(4)];
+    for (i, chunk) in captured.chunks_exact(4).enumerate() {
+        let index = u32::from_le_bytes(chunk.try_into().unwrap()) as usize;
+        // Use the first occurrence of each index.
+        if let Some(position @ None) = index.checked_sub(1).and_then(|i| positions.get_mut(i)) {
+            *position = Some(i * 4);
+        }
+    }
+    positions
+}
+
+// The captured address for the texel with linear index i.
+fn captured_address(positions: &[Option<usize>], i: usize, bytes_per_pixel: u32) -> Option<usize> {
+    let linear_address = i * bytes_per_pixel as usize;
+    positions
+        .get(linear_address / 4)
+        .copied()
+        .flatten()
+        .map(|p| p + linear_address % 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swizzle_mipmap;
+
+    #[test]
+    fn index_pattern_chunks() {
+        assert_eq!(
+            &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0],
+            &index_pattern(5, 1, 1, 2)[..]
+        );
+    }
+
+    #[test]
+    fn compare_index_pattern_match() {
+        for bytes_per_pixel in [1, 4, 16] {
+            let pattern = index_pattern(32, 32, 1, bytes_per_pixel);
+            let tiled = swizzle_mipmap(
+                32,
+                32,
+                1,
+                &pattern,
+                0x700,
+                32,
+                TileMode::D2TiledThin1,
+                bytes_per_pixel,
+                AaMode::X1,
+            )
+            .unwrap();
+
+            let report = compare_index_pattern(
+                32,
+                32,
+                1,
+                0x700,
+                32,
+                TileMode::D2TiledThin1,
+                bytes_per_pixel,
+                AaMode::X1,
+                &tiled,
+            )
+            .unwrap();
+            assert!(report.is_match(), "{bytes_per_pixel}");
+            assert_eq!(None, report.inferred);
+        }
+    }
+
+    #[test]
+    fn compare_index_pattern_infer() {
+        let pattern = index_pattern(64, 64, 1, 4);
+        let tiled = swizzle_mipmap(
+            64,
+            64,
+            1,
+            &pattern,
+            0x200,
+            64,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+
+        let report = compare_index_pattern(
+            64,
+            64,
+            1,
+            0,
+            64,
+            TileMode::D1TiledThin1,
+            4,
+            AaMode::X1,
+            &tiled,
+        )
+        .unwrap();
+        assert!(!report.is_match());
+        assert!(report
+            .mismatches
+            .iter()
+            .all(|m| m.actual_address != Some(m.expected_address) && m.x < 64 && m.y < 64));
+        assert_eq!(
+            Some(TilingParams {
+                tile_mode: TileMode::D2TiledThin1,
+                pitch: 64,
+                swizzle: 0x200
+            }),
+            report.inferred
+        );
+    }
+}
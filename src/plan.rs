@@ -0,0 +1,242 @@
+use crate::{deswizzled_mipmap_size, for_each_run, AaMode, HwConfig, SwizzleError, TileMode};
+use alloc::{vec, vec::Vec};
+
+/// A precomputed mapping between the linear and tiled bytes of a single mipmap.
+///
+/// Creating a plan calculates the tiled addresses once.
+/// The plan can then be applied to any number of surfaces with the same parameters
+/// in either direction without any addrlib calculations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwizzlePlan {
+    runs: Vec<SwizzleRun>,
+    linear_size: usize,
+    tiled_size: usize,
+}
+
+/// A range of bytes with consecutive offsets in both the linear and tiled data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwizzleRun {
+    /// The offset in bytes in the linear data.
+    pub linear_offset: u32,
+    /// The offset in bytes in the tiled data.
+    pub tiled_offset: u32,
+    /// The number of bytes in the run.
+    pub len: u32,
+}
+
+impl SwizzlePlan {
+    /// Calculate the plan for a mipmap with the same parameters as [swizzle_mipmap](crate::swizzle_mipmap)
+    /// and [deswizzle_mipmap](crate::deswizzle_mipmap).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        swizzle: u32,
+        pitch: u32,
+        tile_mode: TileMode,
+        bytes_per_pixel: u32,
+        aa: AaMode,
+    ) -> Result<Self, SwizzleError> {
+        Self::new_with_config(
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            &HwConfig::LATTE,
+        )
+    }
+
+    /// The same as [SwizzlePlan::new] but using the hardware parameters in `hw_config`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_config(
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        swizzle: u32,
+        pitch: u32,
+        tile_mode: TileMode,
+        bytes_per_pixel: u32,
+        aa: AaMode,
+        hw_config: &HwConfig,
+    ) -> Result<Self, SwizzleError> {
+        let mut plan = Self {
+            runs: Vec::new(),
+            linear_size: 0,
+            tiled_size: 0,
+        };
+        if width == 0 || height == 0 || depth_or_array_layers == 0 || bytes_per_pixel == 0 {
+            return Ok(plan);
+        }
+
+        plan.linear_size =
+            deswizzled_mipmap_size(width, height, depth_or_array_layers, bytes_per_pixel);
+
+        for_each_run(
+            width,
+            height,
+            depth_or_array_layers,
+            swizzle,
+            pitch,
+            tile_mode,
+            bytes_per_pixel,
+            aa,
+            hw_config,
+            &mut |tiled_offset, linear_offset, len| {
+                plan.tiled_size = plan.tiled_size.max(tiled_offset + len);
+
+                let (tiled_offset, linear_offset, len) =
+                    (tiled_offset as u32, linear_offset as u32, len as u32);
+
+                // Merge runs that are also consecutive with the previous run.
+                match plan.runs.last_mut() {
+                    Some(run)
+                        if run.linear_offset + run.len == linear_offset
+                            && run.tiled_offset + run.len == tiled_offset =>
+                    {
+                        run.len += len;
+                    }
+                    _ => plan.runs.push(SwizzleRun {
+                        linear_offset,
+                        tiled_offset,
+                        len,
+                    }),
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(plan)
+    }
+
+    /// The runs of consecutive bytes in linear order.
+    pub fn runs(&self) -> &[SwizzleRun] {
+        &self.runs
+    }
+
+    /// The size in bytes of the linear data.
+    pub fn linear_size(&self) -> usize {
+        self.linear_size
+    }
+
+    /// The size in bytes of the tiled data up to the end of the highest tiled address.
+    pub fn tiled_size(&self) -> usize {
+        self.tiled_size
+    }
+
+    /// Convert the linear data in `source` to a tiled vector.
+    ///
+    /// This produces the same output as [swizzle_mipmap](crate::swizzle_mipmap).
+    pub fn swizzle(&self, source: &[u8]) -> Result<Vec<u8>, SwizzleError> {
+        let mut output = vec![0u8; self.tiled_size];
+        self.swizzle_into(source, &mut output)?;
+        Ok(output)
+    }
+
+    /// Convert the tiled data in `source` to a linear vector.
+    ///
+    /// This produces the same output as [deswizzle_mipmap](crate::deswizzle_mipmap).
+    pub fn deswizzle(&self, source: &[u8]) -> Result<Vec<u8>, SwizzleError> {
+        let mut output = vec![0u8; self.linear_size];
+        self.deswizzle_into(source, &mut output)?;
+        Ok(output)
+    }
+
+    /// Convert the linear data in `source` to tiled data in `output`.
+    /// Bytes in `output` not covered by the plan are left unchanged.
+    pub fn swizzle_into(&self, source: &[u8], output: &mut [u8]) -> Result<(), SwizzleError> {
+        check_size(source, self.linear_size)?;
+        check_size(output, self.tiled_size)?;
+
+        for run in &self.runs {
+            let linear = run.linear_offset as usize..(run.linear_offset + run.len) as usize;
+            let tiled = run.tiled_offset as usize..(run.tiled_offset + run.len) as usize;
+            output[tiled].copy_from_slice(&source[linear]);
+        }
+        Ok(())
+    }
+
+    /// Convert the tiled data in `source` to linear data in `output`.
+    pub fn deswizzle_into(&self, source: &[u8], output: &mut [u8]) -> Result<(), SwizzleError> {
+        check_size(source, self.tiled_size)?;
+        check_size(output, self.linear_size)?;
+
+        for run in &self.runs {
+            let linear = run.linear_offset as usize..(run.linear_offset + run.len) as usize;
+            let tiled = run.tiled_offset as usize..(run.tiled_offset + run.len) as usize;
+            output[linear].copy_from_slice(&source[tiled]);
+        }
+        Ok(())
+    }
+}
+
+fn check_size(data: &[u8], expected_size: usize) -> Result<(), SwizzleError> {
+    if data.len() < expected_size {
+        Err(SwizzleError::NotEnoughData {
+            expected_size,
+            actual_size: data.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deswizzle_mipmap, swizzle_mipmap};
+
+    #[test]
+    fn plan_linear_single_run() {
+        let plan =
+            SwizzlePlan::new(64, 8, 1, 0, 64, TileMode::LinearAligned, 4, AaMode::X1).unwrap();
+        assert_eq!(
+            &[SwizzleRun {
+                linear_offset: 0,
+                tiled_offset: 0,
+                len: 2048
+            }],
+            plan.runs()
+        );
+        assert_eq!(2048, plan.linear_size());
+        assert_eq!(2048, plan.tiled_size());
+    }
+
+    #[test]
+    fn plan_matches_swizzle_deswizzle_mipmap() {
+        let linear: Vec<_> = (0..48 * 40 * 4 * 4).map(|i| (i % 251) as u8).collect();
+        for tile_mode in [
+            TileMode::D1TiledThin1,
+            TileMode::D2TiledThin1,
+            TileMode::D2TiledThick,
+            TileMode::B2TiledThin2,
+        ] {
+            let plan = SwizzlePlan::new(48, 40, 4, 0x300, 64, tile_mode, 4, AaMode::X1).unwrap();
+
+            let tiled =
+                swizzle_mipmap(48, 40, 4, &linear, 0x300, 64, tile_mode, 4, AaMode::X1).unwrap();
+            assert_eq!(tiled, plan.swizzle(&linear).unwrap());
+            assert_eq!(
+                deswizzle_mipmap(48, 40, 4, &tiled, 0x300, 64, tile_mode, 4, AaMode::X1).unwrap(),
+                plan.deswizzle(&tiled).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn plan_deswizzle_not_enough_data() {
+        let plan =
+            SwizzlePlan::new(16, 16, 1, 0, 32, TileMode::D2TiledThin1, 4, AaMode::X1).unwrap();
+        assert_eq!(
+            Err(SwizzleError::NotEnoughData {
+                expected_size: plan.tiled_size(),
+                actual_size: 4
+            }),
+            plan.deswizzle(&[0u8; 4])
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{
+    for_each_run_in_rows, swizzled_mipmap_size, AaMode, HwConfig, SwizzleError, TileMode,
+    MICRO_TILE_SIZE,
+};
+use alloc::vec;
+
+// Called with the starting row, slice, and linear bytes for each chunk of rows.
+type ChunkFn<'a, E> = dyn FnMut(u32, u32, &[u8]) -> Result<(), E> + 'a;
+
+/// Convert the tiled data in `source` to linear data one row of texels at a time.
+///
+/// The parameters are the same as [deswizzle_mipmap](crate::deswizzle_mipmap).
+/// `f` is called with the row index, slice index, and linear bytes for each row in linear order.
+/// Only a single row of micro tiles of the linear output is kept in memory at once.
+/// `source` must still contain the entire tiled mipmap.
+#[allow(clippy::too_many_arguments)]
+pub fn deswizzle_mipmap_rows<F>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    mut f: F,
+) -> Result<(), SwizzleError>
+where
+    F: FnMut(u32, u32, &[u8]),
+{
+    let row_size = width as usize * bytes_per_pixel as usize;
+    deswizzle_chunks(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        MICRO_TILE_SIZE,
+        &mut |y, z, chunk| {
+            for (i, row) in chunk.chunks_exact(row_size).enumerate() {
+                f(y + i as u32, z, row);
+            }
+            Ok(())
+        },
+        |e| e,
+    )
+}
+
+/// Convert the tiled data in `source` to linear data one slice at a time.
+///
+/// The parameters are the same as [deswizzle_mipmap](crate::deswizzle_mipmap).
+/// `f` is called with the slice index and linear bytes for each array layer or depth slice.
+#[allow(clippy::too_many_arguments)]
+pub fn deswizzle_mipmap_slices<F>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    mut f: F,
+) -> Result<(), SwizzleError>
+where
+    F: FnMut(u32, &[u8]),
+{
+    deswizzle_chunks(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        height,
+        &mut |_, z, slice| {
+            f(z, slice);
+            Ok(())
+        },
+        |e| e,
+    )
+}
+
+/// Convert the tiled data in `source` to linear data written to `writer`.
+///
+/// The parameters are the same as [deswizzle_mipmap](crate::deswizzle_mipmap).
+/// The output is written one row of micro tiles at a time.
+/// `source` must still contain the entire tiled mipmap.
+/// Errors from deswizzling use [std::io::ErrorKind::InvalidData].
+#[cfg(feature = "std")]
+#[allow(clippy::too_many_arguments)]
+pub fn deswizzle_mipmap_to_writer<W: std::io::Write>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    deswizzle_chunks(
+        width,
+        height,
+        depth_or_array_layers,
+        source,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        MICRO_TILE_SIZE,
+        &mut |_, _, chunk| writer.write_all(chunk),
+        |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+    )
+}
+
+// Deswizzle blocks of rows starting at multiples of rows_per_chunk in linear order.
+// Only the output is chunked since tiles for a chunk can be anywhere in source.
+#[allow(clippy::too_many_arguments)]
+fn deswizzle_chunks<E>(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    source: &[u8],
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    rows_per_chunk: u32,
+    f: &mut ChunkFn<E>,
+    map_err: fn(SwizzleError) -> E,
+) -> Result<(), E> {
+    if width == 0 || height == 0 || depth_or_array_layers == 0 || bytes_per_pixel == 0 {
+        return Ok(());
+    }
+
+    // Use the same size check as deswizzle_mipmap.
+    let expected_size = swizzled_mipmap_size(
+        width,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+        &HwConfig::LATTE,
+    )
+    .map_err(map_err)?;
+    if source.len() < expected_size {
+        return Err(map_err(SwizzleError::NotEnoughData {
+            expected_size,
+            actual_size: source.len(),
+        }));
+    }
+
+    let row_size = width as usize * bytes_per_pixel as usize;
+    let slice_size = row_size * height as usize;
+
+    let mut chunk = vec![0u8; row_size * rows_per_chunk.min(height) as usize];
+    for z in 0..depth_or_array_layers {
+        for y in (0..height).step_by(rows_per_chunk as usize) {
+            let rows = y..(y + rows_per_chunk).min(height);
+            let chunk = &mut chunk[..rows.len() * row_size];
+            let start = z as usize * slice_size + y as usize * row_size;
+
+            for_each_run_in_rows(
+                width,
+                height,
+                depth_or_array_layers,
+                swizzle,
+                pitch,
+                tile_mode,
+                bytes_per_pixel,
+                aa,
+                z,
+                rows,
+                &HwConfig::LATTE,
+                &mut |address, linear_address, size| {
+                    let linear_address = linear_address - start;
+                    let bytes =
+                        source
+                            .get(address..address + size)
+                            .ok_or(SwizzleError::NotEnoughData {
+                                expected_size: address + size,
+                                actual_size: source.len(),
+                            })?;
+                    chunk[linear_address..linear_address + size].copy_from_slice(bytes);
+                    Ok(())
+                },
+            )
+            .map_err(map_err)?;
+
+            f(y, z, chunk)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deswizzle_mipmap;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn deswizzle_rows_slices_match_mipmap() {
+        let source: Vec<_> = (0..65536).map(|i| (i % 251) as u8).collect();
+        for tile_mode in [
+            TileMode::LinearAligned,
+            TileMode::D1TiledThin1,
+            TileMode::D2TiledThin1,
+            TileMode::D2TiledThick,
+        ] {
+            let expected =
+                deswizzle_mipmap(29, 23, 5, &source, 0x300, 32, tile_mode, 4, AaMode::X1).unwrap();
+
+            let mut rows = Vec::new();
+            let mut indices = Vec::new();
+            deswizzle_mipmap_rows(
+                29,
+                23,
+                5,
+                &source,
+                0x300,
+                32,
+                tile_mode,
+                4,
+                AaMode::X1,
+                |y, z, row| {
+                    indices.push((y, z));
+                    rows.extend_from_slice(row);
+                },
+            )
+            .unwrap();
+            assert_eq!(expected, rows, "{tile_mode:?}");
+            assert_eq!(23 * 5, indices.len());
+            assert_eq!(Some(&(22, 4)), indices.last());
+
+            let mut slices = Vec::new();
+            deswizzle_mipmap_slices(
+                29,
+                23,
+                5,
+                &source,
+                0x300,
+                32,
+                tile_mode,
+                4,
+                AaMode::X1,
+                |_, slice| slices.extend_from_slice(slice),
+            )
+            .unwrap();
+            assert_eq!(expected, slices, "{tile_mode:?}");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn deswizzle_to_writer() {
+        let source: Vec<_> = (0..65536).map(|i| (i % 251) as u8).collect();
+        let expected = deswizzle_mipmap(
+            64,
+            64,
+            1,
+            &source,
+            0,
+            64,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+        )
+        .unwrap();
+
+        let mut writer = Vec::new();
+        deswizzle_mipmap_to_writer(
+            64,
+            64,
+            1,
+            &source,
+            0,
+            64,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+            &mut writer,
+        )
+        .unwrap();
+        assert_eq!(expected, writer);
+
+        let result = deswizzle_mipmap_to_writer(
+            64,
+            64,
+            1,
+            &source[..64],
+            0,
+            64,
+            TileMode::D2TiledThin1,
+            4,
+            AaMode::X1,
+            &mut writer,
+        );
+        assert_eq!(std::io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{pipe_bank_swizzle, swizzle_from_pipe_bank, HwConfig, SwizzleError, TileMode};
+
+// GX2 stores the first mip level using micro tiling in bits 16-23.
+const MIP_LEVEL_MASK: u32 = 0x00FF0000;
+
+/// Suggest a swizzle value for the surface at position `index` when creating many surfaces.
+///
+/// Consecutive indices cycle through the pipes first and then the banks of [HwConfig::LATTE],
+/// repeating after every pipe and bank combination.
+/// Tile modes without macro tiling ignore the swizzle and always return 0.
+pub fn suggest_swizzle(tile_mode: TileMode, index: u32) -> u32 {
+    if !tile_mode.is_macro_tiled() {
+        return 0;
+    }
+
+    let hw = HwConfig::LATTE;
+    let pipe_swizzle = index % hw.num_pipes;
+    let bank_swizzle = index / hw.num_pipes % hw.num_banks;
+    swizzle_from_pipe_bank(&hw, pipe_swizzle, bank_swizzle)
+}
+
+/// Encode a `pipe_swizzle` and `bank_swizzle` into a swizzle value for the Wii U.
+///
+/// Returns [SwizzleError::InvalidPipeBankSwizzle] if either value is out of range
+/// for the number of pipes and banks in [HwConfig::LATTE].
+pub fn select_swizzle(pipe_swizzle: u32, bank_swizzle: u32) -> Result<u32, SwizzleError> {
+    let hw = HwConfig::LATTE;
+    if pipe_swizzle >= hw.num_pipes || bank_swizzle >= hw.num_banks {
+        return Err(SwizzleError::InvalidPipeBankSwizzle {
+            pipe_swizzle,
+            bank_swizzle,
+        });
+    }
+    Ok(swizzle_from_pipe_bank(&hw, pipe_swizzle, bank_swizzle))
+}
+
+/// Check that `swizzle` only sets the pipe and bank bits used by [pipe_bank_swizzle]
+/// and the mip level bits set by GX2CalcSurfaceSizeAndAlignment.
+pub fn validate_swizzle(swizzle: u32) -> Result<(), SwizzleError> {
+    let hw = HwConfig::LATTE;
+    let (pipe_swizzle, bank_swizzle) = pipe_bank_swizzle(&hw, swizzle);
+    let mask = swizzle_from_pipe_bank(&hw, pipe_swizzle, bank_swizzle) | MIP_LEVEL_MASK;
+    if swizzle & !mask != 0 {
+        Err(SwizzleError::InvalidSwizzle { swizzle })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{vec, vec::Vec};
+
+    #[test]
+    fn suggest_swizzle_all_pipes_banks() {
+        let swizzles: Vec<_> = (0..8)
+            .map(|i| suggest_swizzle(TileMode::D2TiledThin1, i))
+            .collect();
+        assert_eq!(
+            vec![0x000, 0x100, 0x200, 0x300, 0x400, 0x500, 0x600, 0x700],
+            swizzles
+        );
+        assert_eq!(0, suggest_swizzle(TileMode::D2TiledThin1, 8));
+        assert_eq!(0, suggest_swizzle(TileMode::D1TiledThin1, 3));
+        assert!(swizzles.into_iter().all(|s| validate_swizzle(s).is_ok()));
+    }
+
+    #[test]
+    fn select_validate_swizzle() {
+        assert_eq!(Ok(0x500), select_swizzle(1, 2));
+        assert_eq!(
+            Err(SwizzleError::InvalidPipeBankSwizzle {
+                pipe_swizzle: 2,
+                bank_swizzle: 0
+            }),
+            select_swizzle(2, 0)
+        );
+        assert_eq!(
+            Err(SwizzleError::InvalidPipeBankSwizzle {
+                pipe_swizzle: 0,
+                bank_swizzle: 4
+            }),
+            select_swizzle(0, 4)
+        );
+
+        assert_eq!(Ok(()), validate_swizzle(132352));
+        assert_eq!(
+            Err(SwizzleError::InvalidSwizzle { swizzle: 0x7FF }),
+            validate_swizzle(0x7FF)
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    max_mipmap_count, surface_layout, validate_swizzle, AaMode, Gx2Surface, SurfaceDim,
+    SurfaceLayout, SwizzleError, TileMode,
+};
+use alloc::vec::Vec;
+
+/// A problem with the parameters of a [Gx2Surface] found by [Gx2Surface::validate_detailed].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The surface would fail to deswizzle with this error.
+    InvalidSurface(SwizzleError),
+
+    /// The pitch does not match the pitch calculated from the surface parameters.
+    PitchMismatch { expected: u32, actual: u32 },
+
+    /// The image data is smaller than the calculated size for the base mip level.
+    ImageDataTooSmall { expected: usize, actual: usize },
+
+    /// The mipmap data is smaller than the calculated size for the remaining mip levels.
+    MipmapDataTooSmall { expected: usize, actual: usize },
+
+    /// The offset for mip level `mip` does not match the calculated offset.
+    MipmapOffsetMismatch {
+        mip: u32,
+        expected: u32,
+        actual: u32,
+    },
+
+    /// The mipmap count is larger than the number of mipmaps down to 1x1 pixels.
+    TooManyMipmaps { max: u32, actual: u32 },
+
+    /// The swizzle sets bits not used for the pipe, bank, or mip level.
+    InvalidSwizzle { swizzle: u32 },
+
+    /// The tile mode is not supported for surfaces with this dimension.
+    UnsupportedTileMode {
+        tile_mode: TileMode,
+        dim: SurfaceDim,
+    },
+
+    /// Multisampling is only supported for 2D surfaces.
+    UnsupportedAaMode { aa: AaMode, dim: SurfaceDim },
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::InvalidSurface(e) => write!(f, "{e}"),
+            ValidationIssue::PitchMismatch { expected, actual } => {
+                write!(f, "Expected pitch {expected} but found {actual}")
+            }
+            ValidationIssue::ImageDataTooSmall { expected, actual } => write!(
+                f,
+                "Expected at least {expected} image bytes but found {actual} bytes"
+            ),
+            ValidationIssue::MipmapDataTooSmall { expected, actual } => write!(
+                f,
+                "Expected at least {expected} mipmap bytes but found {actual} bytes"
+            ),
+            ValidationIssue::MipmapOffsetMismatch {
+                mip,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Expected offset {expected} for mip {mip} but found {actual}"
+            ),
+            ValidationIssue::TooManyMipmaps { max, actual } => {
+                write!(f, "Expected at most {max} mipmaps but found {actual}")
+            }
+            ValidationIssue::InvalidSwizzle { swizzle } => {
+                write!(f, "Swizzle 0x{swizzle:X} sets unsupported bits")
+            }
+            ValidationIssue::UnsupportedTileMode { tile_mode, dim } => {
+                write!(f, "Tile mode {tile_mode:?} is not supported for {dim:?}")
+            }
+            ValidationIssue::UnsupportedAaMode { aa, dim } => {
+                write!(f, "AA mode {aa:?} is not supported for {dim:?}")
+            }
+        }
+    }
+}
+
+impl Gx2Surface<'_> {
+    /// Check the surface parameters against the values GX2 would calculate
+    /// and return all problems found.
+    ///
+    /// An empty list means the metadata is consistent.
+    /// Some issues like a mismatched pitch may still deswizzle with incorrect output.
+    pub fn validate_detailed(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if let Err(e) = self.validate() {
+            issues.push(ValidationIssue::InvalidSurface(e));
+        }
+
+        let max = max_mipmap_count(
+            self.dim,
+            self.width,
+            self.height,
+            self.depth_or_array_layers,
+        );
+        if self.mipmap_count > max {
+            issues.push(ValidationIssue::TooManyMipmaps {
+                max,
+                actual: self.mipmap_count,
+            });
+        }
+
+        if let Err(SwizzleError::InvalidSwizzle { swizzle }) = validate_swizzle(self.swizzle) {
+            issues.push(ValidationIssue::InvalidSwizzle { swizzle });
+        }
+
+        // Cube maps and 1D surfaces only use thin tiling.
+        let is_thick_supported = !matches!(self.dim, SurfaceDim::D1 | SurfaceDim::Cube);
+        if !TileMode::supported().any(|t| t == self.tile_mode)
+            || (self.tile_mode.thickness() > 1 && !is_thick_supported)
+        {
+            issues.push(ValidationIssue::UnsupportedTileMode {
+                tile_mode: self.tile_mode,
+                dim: self.dim,
+            });
+        }
+
+        if self.aa != AaMode::X1 && self.dim != SurfaceDim::D2 {
+            issues.push(ValidationIssue::UnsupportedAaMode {
+                aa: self.aa,
+                dim: self.dim,
+            });
+        }
+
+        // Compare with the layout GX2 would use for the same parameters.
+        match surface_layout(
+            self.dim,
+            self.width,
+            self.height,
+            self.depth_or_array_layers,
+            self.mipmap_count.min(max),
+            self.format,
+            self.aa,
+            self.tile_mode,
+            self.swizzle,
+        ) {
+            Ok(layout) => self.compare_layout(&layout, &mut issues),
+            Err(e) => {
+                let issue = ValidationIssue::InvalidSurface(e);
+                if !issues.contains(&issue) {
+                    issues.push(issue);
+                }
+            }
+        }
+
+        issues
+    }
+
+    fn compare_layout(&self, layout: &SurfaceLayout, issues: &mut Vec<ValidationIssue>) {
+        if layout.mipmaps.is_empty() {
+            return;
+        }
+
+        if self.pitch != layout.pitch {
+            issues.push(ValidationIssue::PitchMismatch {
+                expected: layout.pitch,
+                actual: self.pitch,
+            });
+        }
+
+        if self.image_data.len() < layout.image_size as usize {
+            issues.push(ValidationIssue::ImageDataTooSmall {
+                expected: layout.image_size as usize,
+                actual: self.image_data.len(),
+            });
+        }
+
+        if layout.mipmaps.len() > 1 {
+            if self.mipmap_data.len() < layout.mipmap_size as usize {
+                issues.push(ValidationIssue::MipmapDataTooSmall {
+                    expected: layout.mipmap_size as usize,
+                    actual: self.mipmap_data.len(),
+                });
+            }
+
+            for mip in 1..layout.mipmaps.len() {
+                let expected = layout.mipmap_offsets[mip - 1];
+                let actual = self.mipmap_offsets[mip - 1];
+                if expected != actual {
+                    issues.push(ValidationIssue::MipmapOffsetMismatch {
+                        mip: mip as u32,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bc1_mipmaps_surface;
+    use crate::SurfaceFormat;
+    use alloc::vec;
+
+    #[test]
+    fn validate_detailed_valid() {
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+        assert_eq!(
+            Vec::<ValidationIssue>::new(),
+            bc1_mipmaps_surface(swizzled).validate_detailed()
+        );
+    }
+
+    #[test]
+    fn validate_detailed_issues() {
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+        let mut surface = bc1_mipmaps_surface(swizzled);
+        surface.pitch = 32;
+        surface.mipmap_count = 10;
+        surface.mipmap_offsets[1] = 9000;
+        surface.swizzle = 0x7FF;
+        surface.aa = AaMode::X4;
+        surface.dim = SurfaceDim::Cube;
+        surface.depth_or_array_layers = 6;
+        surface.tile_mode = TileMode::D2TiledThick;
+        let issues = surface.validate_detailed();
+
+        assert!(issues.contains(&ValidationIssue::PitchMismatch {
+            expected: 64,
+            actual: 32
+        }));
+        assert!(issues.contains(&ValidationIssue::TooManyMipmaps { max: 9, actual: 10 }));
+        assert!(issues.contains(&ValidationIssue::InvalidSwizzle { swizzle: 0x7FF }));
+        assert!(issues.contains(&ValidationIssue::UnsupportedTileMode {
+            tile_mode: TileMode::D2TiledThick,
+            dim: SurfaceDim::Cube
+        }));
+        assert!(issues.contains(&ValidationIssue::UnsupportedAaMode {
+            aa: AaMode::X4,
+            dim: SurfaceDim::Cube
+        }));
+    }
+
+    #[test]
+    fn validate_detailed_offsets_sizes() {
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+        let mut surface = bc1_mipmaps_surface(&swizzled[..1024]);
+        surface.mipmap_offsets[1] = 9000;
+        assert_eq!(
+            vec![
+                ValidationIssue::InvalidSurface(SwizzleError::InvalidMipmapOffsets {
+                    mipmap_offsets: surface.mipmap_offsets,
+                    image_data_len: 1024,
+                    mipmap_data_len: 0
+                }),
+                ValidationIssue::ImageDataTooSmall {
+                    expected: 32768,
+                    actual: 1024
+                },
+                ValidationIssue::MipmapDataTooSmall {
+                    expected: 14080,
+                    actual: 0
+                },
+                ValidationIssue::MipmapOffsetMismatch {
+                    mip: 2,
+                    expected: 9472,
+                    actual: 9000
+                },
+            ],
+            surface.validate_detailed()
+        );
+    }
+
+    #[test]
+    fn validate_detailed_xthick() {
+        let layout = surface_layout(
+            SurfaceDim::D3,
+            64,
+            64,
+            8,
+            1,
+            SurfaceFormat::R8G8B8A8Unorm,
+            AaMode::X1,
+            TileMode::D2TiledXthick,
+            0,
+        )
+        .unwrap();
+        let image_data = vec![0u8; layout.image_size as usize];
+        let surface = Gx2Surface {
+            dim: SurfaceDim::D3,
+            width: 64,
+            height: 64,
+            depth_or_array_layers: 8,
+            mipmap_count: 1,
+            format: SurfaceFormat::R8G8B8A8Unorm,
+            aa: AaMode::X1,
+            usage: 1,
+            image_data: &image_data,
+            mipmap_data: &[],
+            tile_mode: TileMode::D2TiledXthick,
+            swizzle: 0,
+            alignment: layout.alignment,
+            pitch: layout.pitch,
+            mipmap_offsets: [0; 13],
+        };
+        assert_eq!(Vec::<ValidationIssue>::new(), surface.validate_detailed());
+        assert!(surface.deswizzle().is_ok());
+    }
+
+    #[test]
+    fn validate_detailed_invalid_layout() {
+        let swizzled = include_bytes!("data/256x256_bc1_tm4_p64_s132352_mips8_swizzled.bin");
+        let mut surface = bc1_mipmaps_surface(swizzled);
+        surface.width = 0x10000;
+        assert_eq!(
+            1,
+            surface
+                .validate_detailed()
+                .iter()
+                .filter(|i| matches!(i, ValidationIssue::InvalidSurface(_)))
+                .count()
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{AaMode, HwConfig, SwizzleError, TileMode, TiledAddresses, MICRO_TILE_SIZE};
+use alloc::{vec, vec::Vec};
+
+/// The property of each texel's tiled address used to select its color in [layout_rgb].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutColoring {
+    /// The memory pipe from the address bits above the pipe interleave group.
+    Pipe,
+    /// The memory bank from the address bits above the pipe bits.
+    Bank,
+    /// The index of the micro tile in memory order.
+    MicroTile,
+    /// The index of the macro tile in memory order.
+    /// Tile modes without macro tiles use the size of a [HwConfig::LATTE] 2D macro tile.
+    MacroTile,
+    /// A gradient from the lowest to the highest address.
+    Address,
+}
+
+// Distinct colors for small values like pipes and banks.
+const PALETTE: [[u8; 3]; 8] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+];
+
+/// Render the tiled memory layout of a mipmap as RGB8 pixels with one pixel per texel.
+///
+/// The parameters are the same as [deswizzle_mipmap](crate::deswizzle_mipmap).
+/// Slices are stacked vertically, so the image is `width` pixels wide
+/// and `height * depth_or_array_layers` pixels tall.
+#[allow(clippy::too_many_arguments)]
+pub fn layout_rgb(
+    width: u32,
+    height: u32,
+    depth_or_array_layers: u32,
+    swizzle: u32,
+    pitch: u32,
+    tile_mode: TileMode,
+    bytes_per_pixel: u32,
+    aa: AaMode,
+    coloring: LayoutColoring,
+) -> Result<Vec<u8>, SwizzleError> {
+    let addresses: Vec<_> = TiledAddresses::new(
+        width,
+        height,
+        depth_or_array_layers,
+        swizzle,
+        pitch,
+        tile_mode,
+        bytes_per_pixel,
+        aa,
+    )?
+    .map(|t| t.address)
+    .collect();
+
+    let hw = HwConfig::LATTE;
+    let group_bits = hw.pipe_interleave_bytes.ilog2();
+    let pipe_bits = hw.num_pipes.ilog2();
+
+    let micro_tile_bytes = (MICRO_TILE_SIZE * MICRO_TILE_SIZE * bytes_per_pixel) as usize
+        * tile_mode.thickness() as usize;
+    let (macro_width, macro_height, _) = tile_mode
+        .macro_tile_dimensions(&hw)
+        .or(TileMode::D2TiledThin1.macro_tile_dimensions(&hw))
+        .unwrap_or_default();
+    let macro_tile_bytes =
+        (macro_width * macro_height * bytes_per_pixel) as usize * tile_mode.thickness() as usize;
+    let max_address = addresses.iter().copied().max().unwrap_or_default().max(1);
+
+    let mut rgb = vec![0u8; addresses.len() * 3];
+    for (pixel, address) in rgb.chunks_exact_mut(3).zip(addresses) {
+        let color = match coloring {
+            LayoutColoring::Pipe => {
+                PALETTE[(address >> group_bits) % hw.num_pipes as usize % PALETTE.len()]
+            }
+            LayoutColoring::Bank => {
+                let bank = (address >> (group_bits + pipe_bits)) % hw.num_banks as usize;
+                PALETTE[bank % PALETTE.len()]
+            }
+            LayoutColoring::MicroTile => index_color(address / micro_tile_bytes.max(1)),
+            LayoutColoring::MacroTile => index_color(address / macro_tile_bytes.max(1)),
+            LayoutColoring::Address => {
+                let t = (address * 255 / max_address) as u8;
+                [t, 64, 255 - t]
+            }
+        };
+        pixel.copy_from_slice(&color);
+    }
+    Ok(rgb)
+}
+
+/// Write RGB8 pixels from [layout_rgb] as a binary PPM image.
+#[cfg(feature = "std")]
+pub fn write_ppm<W: std::io::Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    rgb: &[u8],
+) -> std::io::Result<()> {
+    write!(writer, "P6\n{width} {height}\n255\n")?;
+    writer.write_all(rgb)
+}
+
+// Use a different color for consecutive indices.
+fn index_color(i: usize) -> [u8; 3] {
+    let i = i as u32;
+    [
+        i.wrapping_mul(97) as u8,
+        i.wrapping_mul(57).wrapping_add(80) as u8,
+        i.wrapping_mul(151).wrapping_add(160) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_rgb_pipes_banks() {
+        let layout = |coloring| {
+            layout_rgb(
+                64,
+                32,
+                1,
+                0,
+                64,
+                TileMode::D2TiledThin1,
+                4,
+                AaMode::X1,
+                coloring,
+            )
+            .unwrap()
+        };
+
+        let pipes = layout(LayoutColoring::Pipe);
+        assert_eq!(64 * 32 * 3, pipes.len());
+        // Each 256 byte group of 8x8 pixels is in a single pipe.
+        assert_eq!(&PALETTE[0], &pipes[..3]);
+        assert!(pipes.chunks_exact(3).any(|p| p == PALETTE[1]));
+        assert!(pipes
+            .chunks_exact(3)
+            .all(|p| p == PALETTE[0] || p == PALETTE[1]));
+
+        let banks = layout(LayoutColoring::Bank);
+        for bank in &PALETTE[..4] {
+            assert!(banks.chunks_exact(3).any(|p| p == bank));
+        }
+    }
+
+    #[test]
+    fn layout_rgb_micro_tiles_address() {
+        let tiles = layout_rgb(
+            16,
+            8,
+            1,
+            0,
+            16,
+            TileMode::D1TiledThin1,
+            4,
+            AaMode::X1,
+            LayoutColoring::MicroTile,
+        )
+        .unwrap();
+        // The second micro tile starts at x = 8.
+        assert_eq!(&index_color(0), &tiles[..3]);
+        assert_eq!(&index_color(1), &tiles[8 * 3..8 * 3 + 3]);
+
+        let addresses = layout_rgb(
+            16,
+            8,
+            1,
+            0,
+            16,
+            TileMode::LinearAligned,
+            4,
+            AaMode::X1,
+            LayoutColoring::Address,
+        )
+        .unwrap();
+        assert_eq!(&[0, 64, 255], &addresses[..3]);
+        assert_eq!(&[255, 64, 0], &addresses[addresses.len() - 3..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_ppm_header() {
+        let mut ppm = Vec::new();
+        write_ppm(&mut ppm, 2, 1, &[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06", &ppm[..]);
+    }
+}